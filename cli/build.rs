@@ -0,0 +1,12 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(feature = "grpc")]
+    {
+        // compiled without protoc, to build anywhere cargo does
+        println!("cargo:rerun-if-changed=proto");
+        let descriptors = protox::compile(["miflora.proto"], ["proto"])?;
+        tonic_build::configure()
+            .build_client(false)
+            .compile_fds(descriptors)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,296 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use bluer::Address;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, Range, Thresholds};
+use crate::record::{Reading, Source};
+use crate::sink::{Kind, Sink};
+use crate::state::{Breach, State};
+
+mod ntfy;
+mod telegram;
+mod webhook;
+
+/// Bound of the range the value crossed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Bound {
+    Min,
+    Max,
+}
+
+/// Value of a device out of its expected range.
+#[derive(Clone, Debug, Serialize)]
+pub struct Alert {
+    pub device: Source,
+    pub metric: &'static str,
+    pub value: f64,
+    pub threshold: f64,
+    pub bound: Bound,
+}
+
+impl Alert {
+    /// Name of the device, its alias when it has one.
+    pub fn name(&self) -> String {
+        self.device
+            .alias
+            .clone()
+            .unwrap_or_else(|| self.device.address.to_string())
+    }
+
+    /// Short description of what the plant needs.
+    pub fn title(&self) -> String {
+        let name = self.name();
+        match (self.metric, self.bound) {
+            ("moisture", Bound::Min) => format!("{name} needs water"),
+            ("moisture", Bound::Max) => format!("{name} is too wet"),
+            ("temperature", Bound::Min) => format!("{name} is too cold"),
+            ("temperature", Bound::Max) => format!("{name} is too hot"),
+            ("conductivity", Bound::Min) => format!("{name} needs fertilizer"),
+            ("conductivity", Bound::Max) => format!("{name} has too much fertilizer"),
+            ("battery", _) => format!("{name} needs a new battery"),
+            _ => format!("{name} needs attention"),
+        }
+    }
+}
+
+impl std::fmt::Display for Alert {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = self.name();
+        let direction = match self.bound {
+            Bound::Min => "below",
+            Bound::Max => "above",
+        };
+        write!(
+            f,
+            "{name}: {} is {direction} {} ({})",
+            self.metric, self.threshold, self.value
+        )
+    }
+}
+
+/// Destination of the alerts.
+pub trait Notifier: Send + Sync {
+    fn notify<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, anyhow::Result<()>>;
+}
+
+/// Names of the values checked against the thresholds.
+const METRICS: [&str; 4] = ["moisture", "temperature", "conductivity", "battery"];
+
+/// Values of the reading checked against the thresholds.
+fn metrics(reading: &Reading) -> [(&'static str, Option<f64>); 4] {
+    [
+        ("moisture", Some(f64::from(reading.moisture))),
+        ("temperature", Some(f64::from(reading.temperature))),
+        ("conductivity", Some(f64::from(reading.conductivity))),
+        ("battery", reading.battery.map(f64::from)),
+    ]
+}
+
+fn range(thresholds: &Thresholds, metric: &str) -> Option<Range> {
+    match metric {
+        "moisture" => thresholds.moisture,
+        "temperature" => thresholds.temperature,
+        "conductivity" => thresholds.conductivity,
+        "battery" => thresholds.battery,
+        _ => None,
+    }
+}
+
+/// Checks the realtime readings against the thresholds of the devices and notifies when a
+/// value leaves its range, once until it gets back in it.
+pub struct Alerting {
+    thresholds: BTreeMap<Address, Thresholds>,
+    notifiers: Vec<Box<dyn Notifier>>,
+    /// Values currently out of their range
+    breached: Mutex<HashSet<(Address, &'static str, Bound)>>,
+    /// Keeps the values out of their range across the restarts
+    state: Arc<State>,
+}
+
+impl Alerting {
+    /// Creates the alerting when a notifier is configured.
+    pub fn from_config(config: &Config, state: Arc<State>) -> anyhow::Result<Option<Self>> {
+        let mut notifiers: Vec<Box<dyn Notifier>> = Vec::new();
+        if let Some(ref webhook) = config.alerts.webhook {
+            notifiers.push(Box::new(webhook::WebhookNotifier::new(webhook)?));
+        }
+        if let Some(ref ntfy) = config.alerts.ntfy {
+            notifiers.push(Box::new(ntfy::NtfyNotifier::new(ntfy)));
+        }
+        if let Some(ref telegram) = config.alerts.telegram {
+            notifiers.push(Box::new(telegram::TelegramNotifier::new(telegram)));
+        }
+        if notifiers.is_empty() {
+            return Ok(None);
+        }
+        let thresholds = config
+            .devices
+            .iter()
+            .map(|device| (device.address, device.thresholds.clone()))
+            .collect();
+        let breached = config
+            .devices
+            .iter()
+            .flat_map(|device| {
+                state
+                    .get(device.address)
+                    .breached
+                    .into_iter()
+                    .filter_map(move |breach| {
+                        let metric = METRICS.into_iter().find(|name| *name == breach.metric)?;
+                        Some((device.address, metric, breach.bound))
+                    })
+            })
+            .collect();
+        Ok(Some(Self {
+            thresholds,
+            notifiers,
+            breached: Mutex::new(breached),
+            state,
+        }))
+    }
+
+    /// Alerts for the values of the reading that just left their range.
+    fn check(&self, reading: &Reading) -> Vec<Alert> {
+        let Some(thresholds) = self.thresholds.get(&reading.source.address) else {
+            return Vec::new();
+        };
+        let mut breached = self.breached.lock().expect("alerting lock poisoned");
+        let mut alerts = Vec::new();
+        let mut changed = false;
+        for (metric, value) in metrics(reading) {
+            let (Some(value), Some(range)) = (value, range(thresholds, metric)) else {
+                continue;
+            };
+            for (bound, threshold, crossed) in [
+                (
+                    Bound::Min,
+                    range.min,
+                    range.min.is_some_and(|min| value < min),
+                ),
+                (
+                    Bound::Max,
+                    range.max,
+                    range.max.is_some_and(|max| value > max),
+                ),
+            ] {
+                let key = (reading.source.address, metric, bound);
+                if !crossed {
+                    changed |= breached.remove(&key);
+                } else if breached.insert(key) {
+                    changed = true;
+                    alerts.push(Alert {
+                        device: reading.source.clone(),
+                        metric,
+                        value,
+                        threshold: threshold.unwrap_or_default(),
+                        bound,
+                    });
+                }
+            }
+        }
+        if changed {
+            let address = reading.source.address;
+            let current = breached
+                .iter()
+                .filter(|(breached, _, _)| *breached == address)
+                .map(|(_, metric, bound)| Breach {
+                    metric: metric.to_string(),
+                    bound: *bound,
+                })
+                .collect();
+            self.state
+                .update(address, |device| device.breached = current);
+        }
+        alerts
+    }
+}
+
+impl Sink for Alerting {
+    fn publish<'a>(
+        &'a self,
+        kind: Kind,
+        reading: &'a Reading,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            if kind != Kind::Realtime {
+                return Ok(());
+            }
+            for alert in self.check(reading) {
+                tracing::info!(message = "threshold crossed", alert = %alert);
+                for notifier in self.notifiers.iter() {
+                    if let Err(err) = notifier.notify(&alert).await {
+                        tracing::warn!(message = "unable to send alert", error = %err);
+                    }
+                }
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashSet};
+    use std::sync::Mutex;
+
+    use super::{Alerting, Bound};
+    use crate::config::{Range, Thresholds};
+    use crate::record::{Reading, Source};
+
+    fn reading(moisture: u8) -> Reading {
+        Reading {
+            source: Source {
+                address: "C4:7C:8D:6A:3E:1F".parse().unwrap(),
+                alias: Some("basil-kitchen".into()),
+            },
+            timestamp: 1_700_000_000,
+            temperature: 21.5,
+            brightness: Some(300),
+            moisture,
+            conductivity: 150,
+            battery: Some(90),
+            firmware: None,
+        }
+    }
+
+    #[test]
+    fn should_alert_once_when_crossing() {
+        let alerting = Alerting {
+            thresholds: BTreeMap::from([(
+                "C4:7C:8D:6A:3E:1F".parse().unwrap(),
+                Thresholds {
+                    moisture: Some(Range {
+                        min: Some(15.0),
+                        max: Some(60.0),
+                    }),
+                    ..Default::default()
+                },
+            )]),
+            notifiers: Vec::new(),
+            breached: Mutex::new(HashSet::new()),
+            state: Default::default(),
+        };
+        assert!(alerting.check(&reading(30)).is_empty());
+        let alerts = alerting.check(&reading(10));
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].metric, "moisture");
+        assert_eq!(alerts[0].bound, Bound::Min);
+        assert_eq!(alerts[0].threshold, 15.0);
+        assert_eq!(
+            alerts[0].to_string(),
+            "basil-kitchen: moisture is below 15 (10)"
+        );
+        assert_eq!(alerts[0].title(), "basil-kitchen needs water");
+        // still too dry, already notified
+        assert!(alerting.check(&reading(12)).is_empty());
+        assert!(alerting.check(&reading(30)).is_empty());
+        assert_eq!(alerting.check(&reading(70)).len(), 1);
+    }
+}
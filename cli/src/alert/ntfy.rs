@@ -0,0 +1,47 @@
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use reqwest::Client;
+
+use super::{Alert, Notifier};
+use crate::config::NtfyConfig;
+
+/// Pushes the alerts to a topic of an ntfy server.
+pub struct NtfyNotifier {
+    client: Client,
+    url: String,
+    token: Option<String>,
+    priority: Option<u8>,
+}
+
+impl NtfyNotifier {
+    pub fn new(config: &NtfyConfig) -> Self {
+        Self {
+            client: Client::new(),
+            url: format!("{}/{}", config.server.trim_end_matches('/'), config.topic),
+            token: config.token.clone(),
+            priority: config.priority,
+        }
+    }
+}
+
+impl Notifier for NtfyNotifier {
+    fn notify<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            let mut request = self
+                .client
+                .post(&self.url)
+                .header("Title", alert.title())
+                .header("Tags", "seedling")
+                .body(alert.to_string());
+            if let Some(priority) = self.priority {
+                request = request.header("Priority", priority.to_string());
+            }
+            if let Some(ref token) = self.token {
+                request = request.bearer_auth(token);
+            }
+            request.send().await?.error_for_status()?;
+            Ok(())
+        }
+        .boxed()
+    }
+}
@@ -0,0 +1,42 @@
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use reqwest::Client;
+use serde_json::json;
+
+use super::{Alert, Notifier};
+use crate::config::TelegramConfig;
+
+/// Sends the alerts to a Telegram chat through a bot.
+pub struct TelegramNotifier {
+    client: Client,
+    url: String,
+    chat_id: String,
+}
+
+impl TelegramNotifier {
+    pub fn new(config: &TelegramConfig) -> Self {
+        Self {
+            client: Client::new(),
+            url: format!("https://api.telegram.org/bot{}/sendMessage", config.token),
+            chat_id: config.chat_id.clone(),
+        }
+    }
+}
+
+impl Notifier for TelegramNotifier {
+    fn notify<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            self.client
+                .post(&self.url)
+                .json(&json!({
+                    "chat_id": self.chat_id,
+                    "text": format!("{}\n{alert}", alert.title()),
+                }))
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok(())
+        }
+        .boxed()
+    }
+}
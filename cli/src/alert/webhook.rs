@@ -0,0 +1,44 @@
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::Client;
+
+use super::{Alert, Notifier};
+use crate::config::WebhookConfig;
+
+/// Posts the alerts as JSON to an HTTP endpoint.
+pub struct WebhookNotifier {
+    client: Client,
+    url: String,
+    headers: HeaderMap,
+}
+
+impl WebhookNotifier {
+    pub fn new(config: &WebhookConfig) -> anyhow::Result<Self> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in config.headers.iter() {
+            headers.insert(HeaderName::try_from(name)?, HeaderValue::try_from(value)?);
+        }
+        Ok(Self {
+            client: Client::new(),
+            url: config.url.clone(),
+            headers,
+        })
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn notify<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            self.client
+                .post(&self.url)
+                .headers(self.headers.clone())
+                .json(alert)
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok(())
+        }
+        .boxed()
+    }
+}
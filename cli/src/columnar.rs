@@ -0,0 +1,133 @@
+use std::io::Write;
+use std::sync::Arc;
+
+use arrow_array::{
+    ArrayRef, Float32Array, RecordBatch, StringArray, TimestampSecondArray, UInt16Array,
+    UInt32Array, UInt8Array,
+};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+
+use crate::record::Reading;
+
+/// Columns of the readings, typed to load straight into the dataframes.
+fn schema() -> Schema {
+    Schema::new(vec![
+        Field::new("address", DataType::Utf8, false),
+        Field::new("alias", DataType::Utf8, true),
+        Field::new(
+            "timestamp",
+            DataType::Timestamp(TimeUnit::Second, Some("UTC".into())),
+            false,
+        ),
+        Field::new("temperature", DataType::Float32, false),
+        Field::new("brightness", DataType::UInt32, true),
+        Field::new("moisture", DataType::UInt8, false),
+        Field::new("conductivity", DataType::UInt16, false),
+        Field::new("battery", DataType::UInt8, true),
+        Field::new("firmware", DataType::Utf8, true),
+    ])
+}
+
+fn record_batch(schema: Arc<Schema>, readings: &[Reading]) -> anyhow::Result<RecordBatch> {
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(
+            readings
+                .iter()
+                .map(|reading| reading.source.address.to_string()),
+        )),
+        Arc::new(StringArray::from_iter(
+            readings
+                .iter()
+                .map(|reading| reading.source.alias.as_deref()),
+        )),
+        Arc::new(
+            TimestampSecondArray::from_iter_values(
+                readings.iter().map(|reading| reading.timestamp as i64),
+            )
+            .with_timezone("UTC"),
+        ),
+        Arc::new(Float32Array::from_iter_values(
+            readings.iter().map(|reading| reading.temperature),
+        )),
+        Arc::new(UInt32Array::from_iter(
+            readings.iter().map(|reading| reading.brightness),
+        )),
+        Arc::new(UInt8Array::from_iter_values(
+            readings.iter().map(|reading| reading.moisture),
+        )),
+        Arc::new(UInt16Array::from_iter_values(
+            readings.iter().map(|reading| reading.conductivity),
+        )),
+        Arc::new(UInt8Array::from_iter(
+            readings.iter().map(|reading| reading.battery),
+        )),
+        Arc::new(StringArray::from_iter(
+            readings.iter().map(|reading| reading.firmware.as_deref()),
+        )),
+    ];
+    Ok(RecordBatch::try_new(schema, columns)?)
+}
+
+/// Writes the readings as a Parquet file, compressed with Snappy.
+pub fn write<W: Write + Send>(writer: W, readings: &[Reading]) -> anyhow::Result<()> {
+    let schema = Arc::new(schema());
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut writer = ArrowWriter::try_new(writer, schema.clone(), Some(properties))?;
+    writer.write(&record_batch(schema, readings)?)?;
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Float32Type, TimestampSecondType, UInt8Type};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use crate::record::{Reading, Source};
+
+    #[test]
+    fn should_write_typed_columns() {
+        let reading = |timestamp, moisture| Reading {
+            source: Source {
+                address: "C4:7C:8D:6A:3E:1F".parse().unwrap(),
+                alias: Some("basil-kitchen".into()),
+            },
+            timestamp,
+            temperature: 21.5,
+            brightness: Some(300),
+            moisture,
+            conductivity: 150,
+            battery: None,
+            firmware: None,
+        };
+        let path = std::env::temp_dir().join(format!("miflora-{}.parquet", std::process::id()));
+        let file = std::fs::File::create(&path).unwrap();
+        super::write(
+            file,
+            &[reading(1_700_000_000, 30), reading(1_700_003_600, 28)],
+        )
+        .unwrap();
+
+        let file = std::fs::File::open(&path).unwrap();
+        let mut batches = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap();
+        let batch = batches.next().unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        let timestamps = batch.column(2).as_primitive::<TimestampSecondType>();
+        assert_eq!(timestamps.value(1), 1_700_003_600);
+        let temperatures = batch.column(3).as_primitive::<Float32Type>();
+        assert_eq!(temperatures.value(0), 21.5);
+        let moistures = batch.column(5).as_primitive::<UInt8Type>();
+        assert_eq!(moistures.values(), &[30, 28]);
+        assert_eq!(batch.column(7).null_count(), 2);
+    }
+}
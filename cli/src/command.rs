@@ -0,0 +1,97 @@
+use crate::context::Context;
+
+mod battery;
+mod blink;
+mod clear_history;
+mod daemon;
+mod doctor;
+#[cfg(feature = "exporter")]
+mod exporter;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod history;
+mod listen;
+mod parse;
+mod read;
+mod scan;
+mod status;
+mod system;
+mod watch;
+
+#[derive(Debug, clap::Subcommand)]
+pub enum Command {
+    /// Lists the devices around.
+    Scan(scan::Command),
+    /// Reads the system information and the current values of the devices.
+    Read(read::Command),
+    /// Reads the values stored in the history of the devices.
+    History(history::Command),
+    /// Clears the history of the devices.
+    ClearHistory(clear_history::Command),
+    /// Blinks the led of the devices, to find them.
+    Blink(blink::Command),
+    /// Reads the battery level and the firmware version of the devices.
+    System(system::Command),
+    /// Reports the battery level of the devices, lowest first.
+    Battery(battery::Command),
+    /// Prints the current values of the devices and whether their plants are doing well.
+    Status(status::Command),
+    /// Lists the services of the devices and performs the standard reads, to diagnose them.
+    Doctor(doctor::Command),
+    /// Streams the values of the devices as they are notified.
+    Watch(watch::Command),
+    /// Streams the values advertised by the devices, without ever connecting to them.
+    Listen(listen::Command),
+    /// Decodes the payloads captured with `--dump-raw`, without any Bluetooth.
+    Parse(parse::Command),
+    /// Polls the configured devices on their intervals, until stopped.
+    Daemon(daemon::Command),
+    /// Polls the configured devices like the daemon and serves their metrics to Prometheus.
+    #[cfg(feature = "exporter")]
+    Exporter(exporter::Command),
+    /// Polls the configured devices like the daemon and serves the gRPC API, for the fleet
+    /// gateways.
+    #[cfg(feature = "grpc")]
+    Grpc(grpc::Command),
+}
+
+impl Command {
+    /// Whether the command works without communicating with the devices.
+    pub fn is_offline(&self) -> bool {
+        matches!(self, Self::Parse(_))
+    }
+
+    /// Whether the command runs until stopped, its results never being all known.
+    pub fn is_streaming(&self) -> bool {
+        match self {
+            Self::Watch(_) | Self::Daemon(_) => true,
+            #[cfg(feature = "exporter")]
+            Self::Exporter(_) => true,
+            #[cfg(feature = "grpc")]
+            Self::Grpc(_) => true,
+            _ => false,
+        }
+    }
+
+    pub async fn run(self, ctx: &Context) -> anyhow::Result<()> {
+        match self {
+            Self::Scan(inner) => inner.run(ctx).await,
+            Self::Read(inner) => inner.run(ctx).await,
+            Self::History(inner) => inner.run(ctx).await,
+            Self::ClearHistory(inner) => inner.run(ctx).await,
+            Self::Blink(inner) => inner.run(ctx).await,
+            Self::System(inner) => inner.run(ctx).await,
+            Self::Battery(inner) => inner.run(ctx).await,
+            Self::Status(inner) => inner.run(ctx).await,
+            Self::Doctor(inner) => inner.run(ctx).await,
+            Self::Watch(inner) => inner.run(ctx).await,
+            Self::Listen(inner) => inner.run(ctx).await,
+            Self::Parse(inner) => inner.run(ctx).await,
+            Self::Daemon(inner) => inner.run(ctx).await,
+            #[cfg(feature = "exporter")]
+            Self::Exporter(inner) => inner.run(ctx).await,
+            #[cfg(feature = "grpc")]
+            Self::Grpc(inner) => inner.run(ctx).await,
+        }
+    }
+}
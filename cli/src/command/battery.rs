@@ -0,0 +1,98 @@
+use bluer_miflora::{BatteryThresholds, Miflora};
+use futures::StreamExt;
+
+use crate::context::Context;
+use crate::output::Format;
+use crate::record::Battery;
+
+#[derive(Debug, clap::Args)]
+pub struct Command {
+    /// Battery level in % at or below which the battery is reported as low.
+    #[arg(long, default_value_t = 20)]
+    low: u8,
+    /// Battery level in % at or below which the battery is reported as critical.
+    #[arg(long, default_value_t = 10)]
+    critical: u8,
+}
+
+impl Command {
+    pub async fn run(self, ctx: &Context) -> anyhow::Result<()> {
+        let thresholds = BatteryThresholds::default()
+            .with_low(self.low)
+            .with_critical(self.critical);
+        let config = ctx.config();
+        // without any address, only the configured devices are swept when there are some
+        let configured = |miflora: &Miflora| {
+            !ctx.args().addresses.is_empty()
+                || config.devices.is_empty()
+                || config
+                    .devices
+                    .iter()
+                    .any(|device| device.address == miflora.address())
+        };
+
+        let mut found: Vec<_> = ctx
+            .discovered()
+            .filter(|miflora| futures::future::ready(configured(miflora)))
+            .map(|miflora| {
+                ctx.handle(miflora, |miflora| async move {
+                    read(ctx, &miflora, &thresholds).await
+                })
+            })
+            .buffer_unordered(ctx.concurrency())
+            .filter_map(futures::future::ready)
+            .collect()
+            .await;
+        found.sort_by_key(|battery| battery.battery);
+
+        match ctx.output().format() {
+            Format::Text => print_table(&found),
+            _ => {
+                for battery in found {
+                    ctx.output().write(&battery)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn read(
+    ctx: &Context,
+    miflora: &Miflora,
+    thresholds: &BatteryThresholds,
+) -> anyhow::Result<Battery> {
+    let system = miflora
+        .with_connection(|miflora| async move { miflora.read_system().await })
+        .await?;
+    Ok(Battery::new(
+        ctx.source(miflora.address()),
+        &system,
+        thresholds,
+    ))
+}
+
+fn print_table(batteries: &[Battery]) {
+    let rows: Vec<[String; 5]> = batteries
+        .iter()
+        .map(|battery| {
+            [
+                battery.source.address.to_string(),
+                battery.source.alias.clone().unwrap_or_else(|| "-".into()),
+                format!("{}%", battery.battery),
+                battery.state.to_string(),
+                battery.firmware.clone(),
+            ]
+        })
+        .collect();
+    let header = ["ADDRESS", "ALIAS", "BATTERY", "STATE", "FIRMWARE"].map(String::from);
+    let alias_width = rows
+        .iter()
+        .chain(std::iter::once(&header))
+        .map(|row| row[1].chars().count())
+        .max()
+        .unwrap_or_default();
+    for [address, alias, battery, state, firmware] in std::iter::once(&header).chain(&rows) {
+        println!("{address:<17}  {alias:<alias_width$}  {battery:>7}  {state:<8}  {firmware}");
+    }
+}
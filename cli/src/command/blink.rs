@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+use bluer_miflora::Miflora;
+
+use crate::context::Context;
+use crate::record::Action;
+
+#[derive(Debug, clap::Args)]
+pub struct Command {
+    /// Number of times the led blinks, a single blink being easy to miss.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    times: u32,
+    /// Duration between two blinks, like `2s`.
+    #[arg(long, default_value = "1s", value_parser = humantime::parse_duration)]
+    interval: Duration,
+}
+
+impl Command {
+    pub async fn run(self, ctx: &Context) -> anyhow::Result<()> {
+        ctx.for_each_device(|miflora| handle(miflora, ctx, self.times, self.interval))
+            .await
+    }
+}
+
+async fn handle(
+    miflora: Miflora,
+    ctx: &Context,
+    times: u32,
+    interval: Duration,
+) -> anyhow::Result<()> {
+    miflora
+        .with_connection(|miflora| async move {
+            for index in 0..times {
+                if index > 0 {
+                    tokio::time::sleep(interval).await;
+                }
+                miflora.blink_led().await?;
+            }
+            Ok(())
+        })
+        .await?;
+    ctx.output().write(&Action {
+        source: ctx.source(miflora.address()),
+        action: "led blinked",
+    })
+}
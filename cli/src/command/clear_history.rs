@@ -0,0 +1,23 @@
+use bluer_miflora::Miflora;
+
+use crate::context::Context;
+use crate::record::Action;
+
+#[derive(Debug, clap::Args)]
+pub struct Command;
+
+impl Command {
+    pub async fn run(self, ctx: &Context) -> anyhow::Result<()> {
+        ctx.for_each_device(|miflora| handle(miflora, ctx)).await
+    }
+}
+
+async fn handle(miflora: Miflora, ctx: &Context) -> anyhow::Result<()> {
+    miflora
+        .with_connection(|miflora| async move { miflora.force_clear_history().await })
+        .await?;
+    ctx.output().write(&Action {
+        source: ctx.source(miflora.address()),
+        action: "history cleared",
+    })
+}
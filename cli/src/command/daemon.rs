@@ -0,0 +1,193 @@
+use std::future::Future;
+use std::sync::Arc;
+
+use bluer_miflora::{Miflora, Registry};
+use tokio::sync::Mutex;
+use tokio::time::MissedTickBehavior;
+use tracing::Instrument;
+
+use crate::config::{Config, DeviceConfig};
+use crate::context::Context;
+use crate::record::Reading;
+use crate::sink::{Kind, Sinks};
+use crate::state::State;
+
+#[derive(Debug, clap::Args)]
+pub struct Command;
+
+impl Command {
+    pub async fn run(self, ctx: &Context) -> anyhow::Result<()> {
+        let registry = Registry::new(ctx.adapter().clone());
+        let state = ctx.load_state()?;
+        let sinks = Sinks::from_config(ctx.config(), &state)?;
+        serve(ctx, &registry, &state, sinks).await
+    }
+}
+
+/// Polls the configured devices on their intervals and publishes the readings to the sinks,
+/// until the discovery of the registry stops.
+pub async fn serve(
+    ctx: &Context,
+    registry: &Registry,
+    state: &Arc<State>,
+    sinks: Sinks,
+) -> anyhow::Result<()> {
+    let config = ctx.config();
+    anyhow::ensure!(
+        !config.devices.is_empty(),
+        "no device configured, see the --config option"
+    );
+
+    let sources: Vec<_> = config
+        .devices
+        .iter()
+        .map(|device| ctx.source(device.address))
+        .collect();
+    sinks.announce(&sources).await;
+    // connections through a single adapter tend to fail when concurrent
+    let connection = Mutex::new(());
+    let pollers = futures::future::join_all(config.devices.iter().map(|device| {
+        Poller {
+            ctx,
+            config,
+            device,
+            connection: &connection,
+            state,
+            sinks: &sinks,
+        }
+        .run()
+        .instrument(ctx.span(device.address))
+    }));
+    tokio::select! {
+        result = registry.run() => {
+            result?;
+            anyhow::bail!("discovery stopped");
+        }
+        // the pollers stop once a shutdown is requested
+        _ = pollers => {}
+    }
+    sinks.close().await;
+    Ok(())
+}
+
+/// Polls a device on its own intervals.
+struct Poller<'a> {
+    ctx: &'a Context,
+    config: &'a Config,
+    device: &'a DeviceConfig,
+    connection: &'a Mutex<()>,
+    state: &'a State,
+    sinks: &'a Sinks,
+}
+
+impl Poller<'_> {
+    async fn run(self) {
+        let mut realtime = tokio::time::interval(self.config.realtime_interval(self.device));
+        realtime.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut history = tokio::time::interval(self.config.history_interval(self.device));
+        history.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        // timestamp of the last historical entry read, before a restart too
+        let mut since = self
+            .state
+            .get(self.device.address)
+            .history_since
+            .unwrap_or_default();
+
+        loop {
+            tokio::select! {
+                _ = self.ctx.cancelled() => return,
+                _ = realtime.tick() => {
+                    self.retrying(Kind::Realtime, || self.read_realtime()).await;
+                }
+                _ = history.tick() => {
+                    if let Some(last) = self.retrying(Kind::History, || self.read_history(since)).await {
+                        since = last.unwrap_or(since);
+                        self.state.update(self.device.address, |device| device.history_since = Some(since));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Runs the operation with the retry policy, logging the failure once exhausted.
+    async fn retrying<F, Fut, T>(&self, kind: Kind, operation: F) -> Option<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let policy = self.ctx.retry_policy();
+        let mut attempts = 0;
+        loop {
+            match operation().await {
+                Ok(value) => {
+                    self.sinks
+                        .collected(kind, &self.ctx.source(self.device.address), true);
+                    return Some(value);
+                }
+                Err(_) if self.ctx.is_shutting_down() => return None,
+                Err(err) if attempts < policy.max_retries() => {
+                    attempts += 1;
+                    let delay = policy.delay(attempts);
+                    tracing::debug!(message = "poll failed", tries = attempts, delay = ?delay, error = %err);
+                    tokio::select! {
+                        _ = self.ctx.cancelled() => return None,
+                        _ = tokio::time::sleep(delay) => {}
+                    }
+                }
+                Err(err) => {
+                    tracing::warn!(message = "unable to poll device", error = %err);
+                    self.sinks
+                        .collected(kind, &self.ctx.source(self.device.address), false);
+                    return None;
+                }
+            }
+        }
+    }
+
+    async fn miflora(&self) -> anyhow::Result<Miflora> {
+        let miflora = Miflora::try_from_adapter(self.ctx.adapter(), self.device.address).await?;
+        Ok(self.ctx.configure(miflora))
+    }
+
+    async fn read_realtime(&self) -> anyhow::Result<()> {
+        let miflora = self.miflora().await?;
+        let snapshot = {
+            let _connection = self.connection.lock().await;
+            self.ctx
+                .interruptible(&miflora, async {
+                    Ok(miflora
+                        .with_connection(|miflora| async move { miflora.read_all(false).await })
+                        .await?)
+                })
+                .await?
+        };
+        let reading = Reading::new(self.ctx.source(miflora.address()), snapshot.realtime())
+            .with_system(snapshot.system());
+        self.ctx.output().write(&reading)?;
+        self.sinks.publish(Kind::Realtime, &reading).await;
+        Ok(())
+    }
+
+    /// Reads the entries after the given timestamp, returning the timestamp of the last one.
+    async fn read_history(&self, since: u64) -> anyhow::Result<Option<u64>> {
+        let miflora = self.miflora().await?;
+        let entries = {
+            let _connection = self.connection.lock().await;
+            self.ctx
+                .interruptible(&miflora, async {
+                    Ok(miflora
+                        .with_connection(|miflora| async move {
+                            miflora.read_historical_values_since(since).await
+                        })
+                        .await?)
+                })
+                .await?
+        };
+        for entry in entries.iter() {
+            let reading = Reading::new(self.ctx.source(miflora.address()), entry);
+            self.ctx.output().write(&reading)?;
+            self.sinks.publish(Kind::History, &reading).await;
+        }
+        Ok(entries.iter().map(|entry| entry.timestamp()).max())
+    }
+}
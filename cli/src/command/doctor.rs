@@ -0,0 +1,280 @@
+use std::fmt::Display;
+
+use bluer::gatt::remote::Service;
+use bluer::gatt::CharacteristicFlags;
+use bluer_miflora::{Error, Miflora};
+use serde::Serialize;
+
+use crate::context::Context;
+use crate::output::{Format, Record};
+use crate::record::Source;
+
+#[derive(Debug, clap::Args)]
+pub struct Command;
+
+impl Command {
+    pub async fn run(self, ctx: &Context) -> anyhow::Result<()> {
+        ctx.for_each_device(|miflora| handle(miflora, ctx)).await
+    }
+}
+
+async fn handle(miflora: Miflora, ctx: &Context) -> anyhow::Result<()> {
+    let device = miflora.client().device().clone();
+    let name = device.name().await.ok().flatten();
+    let rssi = miflora.rssi().await.ok().flatten();
+    let (services, checks) = miflora
+        .with_connection(|miflora| async move {
+            let services = describe_services(&device)
+                .await
+                .map_err(|cause| Error::CommandFailed { cause })?;
+            Ok((services, run_checks(&miflora).await))
+        })
+        .await?;
+    let report = Report {
+        source: ctx.source(miflora.address()),
+        model: format!("{:?}", miflora.model()),
+        name,
+        rssi,
+        services,
+        checks,
+    };
+    match ctx.output().format() {
+        Format::Text => {
+            print_report(&report);
+            Ok(())
+        }
+        _ => ctx.output().write(&report),
+    }
+}
+
+/// Everything known about a device, to be attached to the bug reports.
+#[derive(Debug, Serialize)]
+struct Report {
+    #[serde(flatten)]
+    source: Source,
+    model: String,
+    name: Option<String>,
+    rssi: Option<i16>,
+    services: Vec<ServiceReport>,
+    checks: Vec<Check>,
+}
+
+impl Report {
+    fn failures(&self) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| check.error.is_some())
+            .count()
+    }
+}
+
+impl Record for Report {
+    const COLUMNS: &'static [&'static str] = &[
+        "address", "alias", "model", "name", "rssi", "services", "checks", "failures",
+    ];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.source.address.to_string(),
+            self.source.alias.clone().unwrap_or_default(),
+            self.model.clone(),
+            self.name.clone().unwrap_or_default(),
+            self.rssi.map(|value| value.to_string()).unwrap_or_default(),
+            self.services.len().to_string(),
+            self.checks.len().to_string(),
+            self.failures().to_string(),
+        ]
+    }
+
+    fn log(&self) {
+        tracing::info!(
+            message = "diagnostic",
+            model = %self.model,
+            services = self.services.len(),
+            failures = self.failures(),
+        );
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ServiceReport {
+    uuid: String,
+    primary: bool,
+    characteristics: Vec<CharacteristicReport>,
+}
+
+#[derive(Debug, Serialize)]
+struct CharacteristicReport {
+    uuid: String,
+    flags: Vec<&'static str>,
+    descriptors: Vec<String>,
+}
+
+/// Result of one of the standard operations.
+#[derive(Debug, Serialize)]
+struct Check {
+    name: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl Check {
+    fn new<T, F: FnOnce(T) -> String>(
+        name: &'static str,
+        result: Result<T, Error>,
+        describe: F,
+    ) -> Self {
+        match result {
+            Ok(value) => Self {
+                name,
+                value: Some(describe(value)),
+                error: None,
+            },
+            Err(err) => Self {
+                name,
+                value: None,
+                error: Some(err.to_string()),
+            },
+        }
+    }
+}
+
+fn flag_names(flags: &CharacteristicFlags) -> Vec<&'static str> {
+    [
+        (flags.broadcast, "broadcast"),
+        (flags.read, "read"),
+        (flags.write_without_response, "write-without-response"),
+        (flags.write, "write"),
+        (flags.notify, "notify"),
+        (flags.indicate, "indicate"),
+        (
+            flags.authenticated_signed_writes,
+            "authenticated-signed-writes",
+        ),
+        (flags.extended_properties, "extended-properties"),
+        (flags.reliable_write, "reliable-write"),
+        (flags.writable_auxiliaries, "writable-auxiliaries"),
+        (flags.encrypt_read, "encrypt-read"),
+        (flags.encrypt_write, "encrypt-write"),
+        (
+            flags.encrypt_authenticated_read,
+            "encrypt-authenticated-read",
+        ),
+        (
+            flags.encrypt_authenticated_write,
+            "encrypt-authenticated-write",
+        ),
+        (flags.secure_read, "secure-read"),
+        (flags.secure_write, "secure-write"),
+        (flags.authorize, "authorize"),
+    ]
+    .into_iter()
+    .filter_map(|(set, name)| set.then_some(name))
+    .collect()
+}
+
+async fn describe_service(service: &Service) -> bluer::Result<ServiceReport> {
+    let mut characteristics = Vec::new();
+    for characteristic in service.characteristics().await? {
+        let mut descriptors = Vec::new();
+        for descriptor in characteristic.descriptors().await? {
+            descriptors.push(descriptor.uuid().await?.to_string());
+        }
+        characteristics.push(CharacteristicReport {
+            uuid: characteristic.uuid().await?.to_string(),
+            flags: flag_names(&characteristic.flags().await?),
+            descriptors,
+        });
+    }
+    characteristics.sort_by(|left, right| left.uuid.cmp(&right.uuid));
+    Ok(ServiceReport {
+        uuid: service.uuid().await?.to_string(),
+        primary: service.primary().await?,
+        characteristics,
+    })
+}
+
+async fn describe_services(device: &bluer::Device) -> bluer::Result<Vec<ServiceReport>> {
+    let mut services = Vec::new();
+    for service in device.services().await? {
+        services.push(describe_service(&service).await?);
+    }
+    services.sort_by(|left, right| left.uuid.cmp(&right.uuid));
+    Ok(services)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn optional<T: Display>(value: Option<T>) -> String {
+    value.map_or_else(|| "-".into(), |value| value.to_string())
+}
+
+/// Performs the standard reads, one after the other, whatever their results.
+async fn run_checks(miflora: &Miflora) -> Vec<Check> {
+    vec![
+        Check::new("system", miflora.read_system().await, |system| {
+            format!("{system} (raw {})", hex(system.as_bytes()))
+        }),
+        Check::new("device info", miflora.read_device_info().await, |info| {
+            format!(
+                "manufacturer {}, model {}, hardware {}, firmware {}, software {}",
+                optional(info.manufacturer()),
+                optional(info.model_number()),
+                optional(info.hardware_revision()),
+                optional(info.firmware_revision()),
+                optional(info.software_revision()),
+            )
+        }),
+        Check::new("boot time", miflora.read_epoch_time().await, |value| {
+            value.to_string()
+        }),
+        Check::new("history count", miflora.history_count().await, |value| {
+            value.to_string()
+        }),
+        Check::new("realtime", miflora.read_realtime_values().await, |entry| {
+            format!("{entry} (raw {})", hex(entry.as_bytes()))
+        }),
+    ]
+}
+
+fn print_report(report: &Report) {
+    println!(
+        "device {} ({})",
+        report.source.address,
+        report.source.alias.as_deref().unwrap_or("-")
+    );
+    println!("  model: {}", report.model);
+    println!("  name: {}", optional(report.name.as_ref()));
+    println!("  rssi: {}", optional(report.rssi));
+    println!("services");
+    for service in &report.services {
+        let kind = if service.primary {
+            "primary"
+        } else {
+            "secondary"
+        };
+        println!("  {} ({kind})", service.uuid);
+        for characteristic in &service.characteristics {
+            println!(
+                "    {} [{}]",
+                characteristic.uuid,
+                characteristic.flags.join(", ")
+            );
+            for descriptor in &characteristic.descriptors {
+                println!("      {descriptor}");
+            }
+        }
+    }
+    println!("checks");
+    for check in &report.checks {
+        match (&check.value, &check.error) {
+            (_, Some(error)) => println!("  {}: failed, {error}", check.name),
+            (Some(value), None) => println!("  {}: {value}", check.name),
+            (None, None) => println!("  {}: -", check.name),
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+
+use axum::extract::ws::rejection::WebSocketUpgradeRejection;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::http::header;
+use axum::response::sse::{self, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use bluer_miflora::Registry;
+use futures::{pin_mut, StreamExt};
+
+use super::daemon;
+use crate::context::Context;
+use crate::metrics::Metrics;
+use crate::sink::Sinks;
+use crate::stream::LiveStream;
+
+#[derive(Debug, clap::Args)]
+pub struct Command {
+    /// Address the metrics and the stream are served on.
+    #[arg(long, default_value = "0.0.0.0:9294")]
+    listen: SocketAddr,
+}
+
+async fn metrics(State((metrics, registry)): State<(Metrics, Registry)>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics.render(&registry),
+    )
+}
+
+/// Pushes the readings and the advertised values as they come, over a WebSocket when
+/// upgraded or as Server-Sent Events otherwise.
+async fn stream(
+    State(live): State<LiveStream>,
+    upgrade: Result<WebSocketUpgrade, WebSocketUpgradeRejection>,
+) -> Response {
+    match upgrade {
+        Ok(upgrade) => upgrade
+            .on_upgrade(move |socket| websocket(socket, live))
+            .into_response(),
+        Err(_) => Sse::new(
+            live.subscribe()
+                .map(|event| Ok::<_, Infallible>(sse::Event::default().data(&*event))),
+        )
+        .keep_alive(KeepAlive::default())
+        .into_response(),
+    }
+}
+
+async fn websocket(mut socket: WebSocket, live: LiveStream) {
+    let events = live.subscribe();
+    pin_mut!(events);
+    loop {
+        tokio::select! {
+            event = events.next() => {
+                let Some(event) = event else {
+                    break;
+                };
+                if socket.send(Message::Text((&*event).into())).await.is_err() {
+                    break;
+                }
+            }
+            // the messages of the client are ignored, until it closes the socket
+            message = socket.recv() => {
+                if !matches!(message, Some(Ok(message)) if !matches!(message, Message::Close(_))) {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl Command {
+    pub async fn run(self, ctx: &Context) -> anyhow::Result<()> {
+        let registry = Registry::new(ctx.adapter().clone());
+        let sources = ctx
+            .config()
+            .devices
+            .iter()
+            .map(|device| ctx.source(device.address))
+            .collect();
+        let metrics = Metrics::new(sources);
+        let live = LiveStream::default();
+        let state = ctx.load_state()?;
+        let sinks = Sinks::from_config(ctx.config(), &state)?
+            .with_sink(metrics.clone())
+            .with_sink(live.clone());
+
+        let app = Router::new()
+            .route("/metrics", get(self::metrics))
+            .with_state((metrics, registry.clone()))
+            .merge(
+                Router::new()
+                    .route("/stream", get(self::stream))
+                    .with_state(live.clone()),
+            );
+        let listener = tokio::net::TcpListener::bind(self.listen).await?;
+        tracing::info!(message = "serving metrics", address = %self.listen);
+
+        tokio::select! {
+            result = daemon::serve(ctx, &registry, &state, sinks) => result,
+            result = axum::serve(listener, app) => Ok(result?),
+            () = live.forward_advertisements(ctx, &registry) => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,321 @@
+// the errors of the generated API are tonic statuses, too large for clippy
+#![allow(clippy::result_large_err)]
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use bluer::Address;
+use bluer_miflora::{ErrorKind, Miflora, Registry};
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use futures::{FutureExt, StreamExt};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tonic::{Request, Response, Status};
+use tracing::Instrument;
+
+use super::daemon;
+use crate::context::{Context, Interrupted};
+use crate::record::{Reading, Source};
+use crate::sink::{Kind, Sink, Sinks};
+
+mod proto {
+    tonic::include_proto!("miflora.v1");
+}
+
+use proto::collector_server::{Collector, CollectorServer};
+
+/// Number of readings kept for the streams too slow to receive them.
+const CAPACITY: usize = 256;
+
+#[derive(Debug, clap::Args)]
+pub struct Command {
+    /// Address the gRPC API is served on.
+    #[arg(long, default_value = "0.0.0.0:50051")]
+    listen: SocketAddr,
+}
+
+fn to_proto(kind: Kind, reading: &Reading) -> proto::Reading {
+    let kind = match kind {
+        Kind::Realtime => proto::Kind::Realtime,
+        Kind::History => proto::Kind::History,
+    };
+    proto::Reading {
+        address: reading.source.address.to_string(),
+        alias: reading.source.alias.clone(),
+        timestamp: reading.timestamp,
+        kind: kind.into(),
+        temperature: reading.temperature,
+        brightness: reading.brightness,
+        moisture: reading.moisture.into(),
+        conductivity: reading.conductivity.into(),
+        battery: reading.battery.map(u32::from),
+        firmware: reading.firmware.clone(),
+    }
+}
+
+fn parse_address(value: &str) -> Result<Address, Status> {
+    value
+        .parse()
+        .map_err(|_| Status::invalid_argument(format!("invalid address {value:?}")))
+}
+
+/// Status of an operation with a device that failed.
+fn failed(err: anyhow::Error) -> Status {
+    if err.is::<Interrupted>() {
+        return Status::aborted(err.to_string());
+    }
+    match err
+        .downcast_ref::<bluer_miflora::Error>()
+        .map(bluer_miflora::Error::kind)
+    {
+        Some(ErrorKind::NotFound) => Status::not_found(err.to_string()),
+        Some(ErrorKind::Timeout) => Status::deadline_exceeded(err.to_string()),
+        Some(ErrorKind::Transient) => Status::unavailable(err.to_string()),
+        _ => Status::internal(err.to_string()),
+    }
+}
+
+/// Operation with a device, run by the command holding the context.
+enum Operation {
+    ReadRealtime(oneshot::Sender<anyhow::Result<Reading>>),
+    FetchHistory {
+        since: u64,
+        reply: oneshot::Sender<anyhow::Result<Vec<Reading>>>,
+    },
+    Blink(oneshot::Sender<anyhow::Result<()>>),
+}
+
+/// Implementation of the API, the operations with the devices being sent to the command.
+struct Api {
+    registry: Registry,
+    /// Devices polled by the daemon
+    configured: Arc<BTreeMap<Address, Source>>,
+    operations: mpsc::Sender<(Address, Operation)>,
+    readings: broadcast::Sender<proto::Reading>,
+}
+
+impl Api {
+    async fn operate<T, F>(&self, address: &str, operation: F) -> Result<T, Status>
+    where
+        F: FnOnce(oneshot::Sender<anyhow::Result<T>>) -> Operation,
+    {
+        let address = parse_address(address)?;
+        let (reply, receiver) = oneshot::channel();
+        let stopped = || Status::unavailable("the gateway is shutting down");
+        self.operations
+            .send((address, operation(reply)))
+            .await
+            .map_err(|_| stopped())?;
+        receiver.await.map_err(|_| stopped())?.map_err(failed)
+    }
+}
+
+#[tonic::async_trait]
+impl Collector for Api {
+    async fn list_devices(
+        &self,
+        _request: Request<proto::ListDevicesRequest>,
+    ) -> Result<Response<proto::ListDevicesResponse>, Status> {
+        let devices = self
+            .registry
+            .devices()
+            .into_iter()
+            .map(|(address, state)| {
+                let source = self.configured.get(&address);
+                proto::Device {
+                    address: address.to_string(),
+                    alias: source.and_then(|source| source.alias.clone()),
+                    model: format!("{:?}", state.model()),
+                    rssi: state.rssi().map(i32::from),
+                    last_seen: state
+                        .last_seen()
+                        .duration_since(UNIX_EPOCH)
+                        .map(|value| value.as_secs())
+                        .unwrap_or_default(),
+                    configured: source.is_some(),
+                }
+            })
+            .collect();
+        Ok(Response::new(proto::ListDevicesResponse { devices }))
+    }
+
+    async fn read_realtime(
+        &self,
+        request: Request<proto::DeviceRequest>,
+    ) -> Result<Response<proto::Reading>, Status> {
+        let reading = self
+            .operate(&request.into_inner().address, Operation::ReadRealtime)
+            .await?;
+        Ok(Response::new(to_proto(Kind::Realtime, &reading)))
+    }
+
+    type StreamReadingsStream = BoxStream<'static, Result<proto::Reading, Status>>;
+
+    async fn stream_readings(
+        &self,
+        request: Request<proto::StreamReadingsRequest>,
+    ) -> Result<Response<Self::StreamReadingsStream>, Status> {
+        let addresses = request
+            .into_inner()
+            .addresses
+            .iter()
+            .map(|address| parse_address(address).map(|address| address.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let readings =
+            futures::stream::unfold(self.readings.subscribe(), |mut receiver| async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(reading) => return Some((reading, receiver)),
+                        Err(broadcast::error::RecvError::Lagged(count)) => {
+                            tracing::debug!(message = "grpc stream lagging", skipped = count);
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            })
+            .filter(move |reading| {
+                futures::future::ready(addresses.is_empty() || addresses.contains(&reading.address))
+            })
+            .map(Ok);
+        Ok(Response::new(readings.boxed()))
+    }
+
+    type FetchHistoryStream = BoxStream<'static, Result<proto::Reading, Status>>;
+
+    async fn fetch_history(
+        &self,
+        request: Request<proto::FetchHistoryRequest>,
+    ) -> Result<Response<Self::FetchHistoryStream>, Status> {
+        let request = request.into_inner();
+        let since = request.since.unwrap_or_default();
+        let readings = self
+            .operate(&request.address, |reply| Operation::FetchHistory {
+                since,
+                reply,
+            })
+            .await?;
+        let readings = readings
+            .into_iter()
+            .map(|reading| Ok(to_proto(Kind::History, &reading)));
+        Ok(Response::new(futures::stream::iter(readings).boxed()))
+    }
+
+    async fn blink(
+        &self,
+        request: Request<proto::DeviceRequest>,
+    ) -> Result<Response<proto::BlinkResponse>, Status> {
+        self.operate(&request.into_inner().address, Operation::Blink)
+            .await?;
+        Ok(Response::new(proto::BlinkResponse {}))
+    }
+}
+
+/// Sends the readings collected by the daemon to the streams of the API.
+struct Broadcast(broadcast::Sender<proto::Reading>);
+
+impl Sink for Broadcast {
+    fn publish<'a>(
+        &'a self,
+        kind: Kind,
+        reading: &'a Reading,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        // failing only without any stream
+        let _ = self.0.send(to_proto(kind, reading));
+        futures::future::ready(Ok(())).boxed()
+    }
+}
+
+/// Connects to the device and runs the function, disconnecting afterwards.
+async fn with_device<F, Fut, T>(ctx: &Context, address: Address, func: F) -> anyhow::Result<T>
+where
+    F: FnOnce(Miflora) -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    let miflora = ctx.configure(Miflora::try_from_adapter(ctx.adapter(), address).await?);
+    let result = ctx
+        .interruptible(&miflora, async {
+            ctx.connect(&miflora).await?;
+            func(miflora.clone()).await
+        })
+        .await;
+    if let Err(err) = miflora.try_disconnect().await {
+        tracing::debug!(message = "unable to disconnect", error = %err);
+    }
+    result
+}
+
+async fn run_operation(ctx: &Context, address: Address, operation: Operation) {
+    let source = ctx.source(address);
+    match operation {
+        Operation::ReadRealtime(reply) => {
+            let result = with_device(ctx, address, |miflora| async move {
+                let snapshot = miflora.read_all(false).await?;
+                Ok(Reading::new(source, snapshot.realtime()).with_system(snapshot.system()))
+            })
+            .await;
+            let _ = reply.send(result);
+        }
+        Operation::FetchHistory { since, reply } => {
+            let result = with_device(ctx, address, |miflora| async move {
+                let entries = miflora.read_historical_values_since(since).await?;
+                Ok(entries
+                    .iter()
+                    .map(|entry| Reading::new(source.clone(), entry))
+                    .collect())
+            })
+            .await;
+            let _ = reply.send(result);
+        }
+        Operation::Blink(reply) => {
+            let result = with_device(ctx, address, |miflora| async move {
+                Ok(miflora.blink_led().await?)
+            })
+            .await;
+            let _ = reply.send(result);
+        }
+    }
+}
+
+impl Command {
+    pub async fn run(self, ctx: &Context) -> anyhow::Result<()> {
+        let registry = Registry::new(ctx.adapter().clone());
+        let state = ctx.load_state()?;
+        let (readings, _) = broadcast::channel(CAPACITY);
+        let sinks =
+            Sinks::from_config(ctx.config(), &state)?.with_sink(Broadcast(readings.clone()));
+        let (operations, mut receiver) = mpsc::channel(ctx.concurrency());
+        let api = Api {
+            registry: registry.clone(),
+            configured: Arc::new(
+                ctx.config()
+                    .devices
+                    .iter()
+                    .map(|device| (device.address, ctx.source(device.address)))
+                    .collect(),
+            ),
+            operations,
+            readings,
+        };
+        let server = tonic::transport::Server::builder()
+            .add_service(CollectorServer::new(api))
+            .serve(self.listen);
+        tracing::info!(message = "serving grpc api", address = %self.listen);
+
+        // the operations borrow the context, so they can't run in the tasks of the server
+        let operations = futures::stream::poll_fn(|cx| receiver.poll_recv(cx)).for_each_concurrent(
+            ctx.concurrency(),
+            |(address, operation)| {
+                run_operation(ctx, address, operation).instrument(ctx.span(address))
+            },
+        );
+
+        tokio::select! {
+            result = daemon::serve(ctx, &registry, &state, sinks) => result,
+            result = server => Ok(result?),
+            () = operations => Ok(()),
+        }
+    }
+}
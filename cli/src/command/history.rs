@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bluer::Address;
+use bluer_miflora::Miflora;
+
+use crate::context::{Context, Usage};
+use crate::output::{Format, Record};
+use crate::record::Reading;
+use crate::state::State;
+
+#[derive(Debug, clap::Args)]
+pub struct Command {
+    /// CSV file to write the entries to, instead of printing them, appended to when it
+    /// exists, or Parquet file with `--format parquet`.
+    #[arg(long)]
+    output: Option<PathBuf>,
+    /// Clears the history of the devices once their entries have been written to the disk,
+    /// or printed as csv or ndjson. Not available with a range, the entries outside of it
+    /// being lost otherwise.
+    #[arg(long, conflicts_with_all = ["since", "until"])]
+    clear_after_read: bool,
+    /// Exports all the entries of the history, including the ones already exported by a
+    /// previous run.
+    #[arg(long)]
+    full: bool,
+    /// Only outputs the entries recorded since this time, like `2024-05-01T12:00:00Z`,
+    /// `2024-05-01` or `24h` ago.
+    #[arg(long, value_parser = parse_time)]
+    since: Option<u64>,
+    /// Only outputs the entries recorded before this time, in the same formats as `--since`.
+    #[arg(long, value_parser = parse_time)]
+    until: Option<u64>,
+}
+
+/// Parses a RFC3339 date, or a duration before now, into a unix timestamp in seconds.
+fn parse_time(value: &str) -> Result<u64, String> {
+    let time = if let Ok(duration) = humantime::parse_duration(value) {
+        SystemTime::now()
+            .checked_sub(duration)
+            .ok_or_else(|| format!("{value} ago is before the unix epoch"))?
+    } else if value.len() == 10 {
+        // a date alone, from its midnight
+        humantime::parse_rfc3339_weak(&format!("{value}T00:00:00"))
+            .map_err(|err| err.to_string())?
+    } else {
+        humantime::parse_rfc3339_weak(value).map_err(|err| err.to_string())?
+    };
+    time.duration_since(UNIX_EPOCH)
+        .map(|value| value.as_secs())
+        .map_err(|err| err.to_string())
+}
+
+/// Range of timestamps of the entries to output.
+#[derive(Clone, Copy, Debug)]
+struct Range {
+    since: Option<u64>,
+    until: Option<u64>,
+}
+
+impl Range {
+    fn contains(&self, timestamp: u64) -> bool {
+        self.since.is_none_or(|since| timestamp >= since)
+            && self.until.is_none_or(|until| timestamp < until)
+    }
+}
+
+/// CSV file the entries are exported to.
+struct Export {
+    writer: BufWriter<File>,
+}
+
+impl Export {
+    /// Opens the file to append the entries to, the header being only written to a new file.
+    fn open(path: &PathBuf, header: bool) -> anyhow::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let is_empty = file.metadata()?.len() == 0;
+        let mut writer = BufWriter::new(file);
+        if header && is_empty {
+            csv::Writer::from_writer(&mut writer).write_record(Reading::COLUMNS)?;
+        }
+        Ok(Self { writer })
+    }
+
+    /// Writes the readings and waits for them to reach the disk.
+    fn write(&mut self, readings: &[Reading]) -> anyhow::Result<()> {
+        let mut writer = csv::Writer::from_writer(&mut self.writer);
+        for reading in readings {
+            writer.write_record(reading.row())?;
+        }
+        writer.flush()?;
+        drop(writer);
+        self.writer.flush()?;
+        self.writer.get_ref().sync_data()?;
+        Ok(())
+    }
+}
+
+/// Where the entries are written.
+enum Destination {
+    /// Output of the command, in the requested format
+    Output,
+    Csv(Mutex<Export>),
+    /// Entries kept until the end of the command, to print them in a single JSON array
+    Json(Mutex<Vec<Reading>>),
+    /// Entries kept until the end of the command, to write them in a single file
+    #[cfg(feature = "parquet")]
+    Parquet(Mutex<Vec<Reading>>),
+}
+
+impl Destination {
+    fn write(&self, ctx: &Context, readings: &[Reading]) -> anyhow::Result<()> {
+        match self {
+            Self::Output => readings
+                .iter()
+                .try_for_each(|reading| ctx.output().write(reading)),
+            Self::Csv(export) => export.lock().expect("export lock poisoned").write(readings),
+            Self::Json(collected) => {
+                collected
+                    .lock()
+                    .expect("export lock poisoned")
+                    .extend_from_slice(readings);
+                Ok(())
+            }
+            #[cfg(feature = "parquet")]
+            Self::Parquet(collected) => {
+                collected
+                    .lock()
+                    .expect("export lock poisoned")
+                    .extend_from_slice(readings);
+                Ok(())
+            }
+        }
+    }
+
+    /// Whether the entries only reach their destination at the end of the command.
+    fn is_deferred(&self) -> bool {
+        match self {
+            Self::Output | Self::Csv(_) => false,
+            Self::Json(_) => true,
+            #[cfg(feature = "parquet")]
+            Self::Parquet(_) => true,
+        }
+    }
+
+    /// Whether the entries are known to be written once [`Self::write`] returns, so the
+    /// history can be cleared, unlike the logs of the text format.
+    fn is_written_at_once(&self, ctx: &Context) -> bool {
+        match self {
+            Self::Output => ctx.output().format() != Format::Text,
+            Self::Csv(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Remembers the last entry exported of each device, for the next runs.
+fn record_exported(state: &State, readings: &[Reading]) {
+    let mut last: HashMap<Address, u64> = HashMap::new();
+    for reading in readings {
+        let timestamp = last.entry(reading.source.address).or_default();
+        *timestamp = reading.timestamp.max(*timestamp);
+    }
+    for (address, timestamp) in last {
+        state.update(address, |device| {
+            device.exported_since = Some(timestamp.max(device.exported_since.unwrap_or_default()))
+        });
+    }
+}
+
+impl Command {
+    #[cfg(feature = "parquet")]
+    fn destination(&self, ctx: &Context) -> anyhow::Result<Destination> {
+        if ctx.output().format() == Format::Parquet {
+            return Ok(Destination::Parquet(Mutex::default()));
+        }
+        self.csv_destination(ctx)
+    }
+
+    #[cfg(not(feature = "parquet"))]
+    fn destination(&self, ctx: &Context) -> anyhow::Result<Destination> {
+        self.csv_destination(ctx)
+    }
+
+    fn csv_destination(&self, ctx: &Context) -> anyhow::Result<Destination> {
+        Ok(match self.output {
+            Some(ref path) => {
+                let export = Export::open(path, !ctx.args().no_header)?;
+                Destination::Csv(Mutex::new(export))
+            }
+            None if ctx.output().format() == Format::Json => Destination::Json(Mutex::default()),
+            None => Destination::Output,
+        })
+    }
+
+    pub async fn run(self, ctx: &Context) -> anyhow::Result<()> {
+        let destination = self.destination(ctx)?;
+        anyhow::ensure!(
+            !self.clear_after_read || destination.is_written_at_once(ctx),
+            Usage(
+                "the history can only be cleared once written with --output, or printed as csv or ndjson"
+            )
+        );
+        let destination = &destination;
+        let clear = self.clear_after_read;
+        // a given range is exported whole, without changing what the next runs export
+        let incremental = !self.full && self.since.is_none() && self.until.is_none();
+        let range = Range {
+            since: self.since,
+            until: self.until,
+        };
+        let state = ctx.load_state()?;
+        let state = state.as_ref();
+        ctx.for_each_device(|miflora| async move {
+            handle(&miflora, ctx, destination, state, range, clear, incremental).await
+        })
+        .await?;
+        if let Destination::Json(collected) = destination {
+            let readings = collected.lock().expect("export lock poisoned");
+            readings
+                .iter()
+                .try_for_each(|reading| ctx.output().write(reading))?;
+            ctx.output().finish()?;
+            if incremental {
+                record_exported(state, &readings);
+            }
+        }
+        #[cfg(feature = "parquet")]
+        if let Destination::Parquet(collected) = destination {
+            let readings = collected.lock().expect("export lock poisoned");
+            match self.output {
+                Some(ref path) => crate::columnar::write(File::create(path)?, &readings)?,
+                None => {
+                    let mut buffer = Vec::new();
+                    crate::columnar::write(&mut buffer, &readings)?;
+                    std::io::stdout().write_all(&buffer)?;
+                }
+            }
+            if incremental {
+                record_exported(state, &readings);
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn handle(
+    miflora: &Miflora,
+    ctx: &Context,
+    destination: &Destination,
+    state: &State,
+    range: Range,
+    clear: bool,
+    incremental: bool,
+) -> anyhow::Result<()> {
+    tracing::debug!("reading history...");
+    let session = miflora.read_history_session().await?;
+    // the entries exported by the previous runs are skipped
+    let since = if incremental {
+        state.get(miflora.address()).exported_since
+    } else {
+        None
+    };
+    let readings: Vec<_> = session
+        .entries()
+        .iter()
+        .filter(|entry| since.is_none_or(|since| entry.timestamp() > since))
+        .filter(|entry| range.contains(entry.timestamp()))
+        .map(|entry| Reading::new(ctx.source(miflora.address()), entry))
+        .collect();
+    tracing::debug!(message = "new entries", count = readings.len(), since = ?since);
+    let written = destination.write(ctx, &readings);
+    if written.is_ok() && incremental && !destination.is_deferred() {
+        record_exported(state, &readings);
+    }
+    match written {
+        Ok(()) if clear => {
+            session.commit().await?;
+            tracing::info!("history cleared");
+        }
+        Ok(()) => session.abort().await?,
+        Err(err) => {
+            session.abort().await?;
+            return Err(err);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use bluer::{AdapterEvent, Address};
+use bluer_miflora::advertisement::{read_advertisement, MiBeacon, PassiveReading};
+use bluer_miflora::Error;
+use futures::{pin_mut, StreamExt};
+
+use crate::context::Context;
+use crate::record::{now, Reading, Source};
+use crate::sink::{Kind, Sinks};
+
+#[derive(Debug, clap::Args)]
+pub struct Command {
+    /// Duration of the listening, like `1h`. Listens until stopped when not provided.
+    #[arg(long, value_parser = humantime::parse_duration)]
+    duration: Option<Duration>,
+}
+
+/// Last values advertised by a device, each advertisement carrying a single value.
+#[derive(Debug, Default)]
+struct Received {
+    frame_counter: Option<u8>,
+    /// Temperature in 0.1 °C
+    temperature: Option<i16>,
+    brightness: Option<u32>,
+    moisture: Option<u8>,
+    conductivity: Option<u16>,
+    battery: Option<u8>,
+}
+
+impl Received {
+    /// Keeps the value of the frame, returning whether it's a new one.
+    fn update(&mut self, beacon: &MiBeacon) -> bool {
+        if self.frame_counter.replace(beacon.frame_counter()) == Some(beacon.frame_counter()) {
+            return false;
+        }
+        match beacon.reading() {
+            Some(PassiveReading::Temperature(value)) => self.temperature = Some(value),
+            Some(PassiveReading::Brightness(value)) => self.brightness = Some(value),
+            Some(PassiveReading::Moisture(value)) => self.moisture = Some(value),
+            Some(PassiveReading::Conductivity(value)) => self.conductivity = Some(value),
+            Some(PassiveReading::Battery(value)) => self.battery = Some(value),
+            Some(PassiveReading::Unknown(..)) | None => return false,
+        }
+        true
+    }
+
+    /// Reading made of the last values, once the temperature, the moisture and the
+    /// conductivity have all been advertised.
+    fn reading(&self, source: Source) -> Option<Reading> {
+        Some(Reading {
+            source,
+            timestamp: now(),
+            temperature: f32::from(self.temperature?) / 10.0,
+            brightness: self.brightness,
+            moisture: self.moisture?,
+            conductivity: self.conductivity?,
+            battery: self.battery,
+            firmware: None,
+        })
+    }
+}
+
+impl Command {
+    pub async fn run(self, ctx: &Context) -> anyhow::Result<()> {
+        let addresses = &ctx.args().addresses;
+        let sinks = Sinks::from_config(ctx.config(), &ctx.load_state()?)?;
+        let stop = async {
+            match self.duration {
+                Some(duration) => tokio::select! {
+                    _ = tokio::time::sleep(duration) => {}
+                    _ = ctx.cancelled() => {}
+                },
+                None => ctx.cancelled().await,
+            }
+        };
+        let events = ctx
+            .adapter()
+            .discover_devices_with_changes()
+            .await?
+            .take_until(Box::pin(stop));
+        pin_mut!(events);
+
+        let mut received: HashMap<Address, Received> = HashMap::new();
+        while let Some(event) = events.next().await {
+            let AdapterEvent::DeviceAdded(address) = event else {
+                continue;
+            };
+            if !addresses.is_empty() && !addresses.contains(&address) {
+                continue;
+            }
+            let device = ctx.adapter().device(address)?;
+            let beacon = match read_advertisement(&device).await {
+                // other Xiaomi devices advertise on the same service
+                Ok(Some(beacon)) if beacon.model().is_some() => beacon,
+                Ok(_) | Err(Error::NoServiceData) => continue,
+                Err(err) => {
+                    tracing::debug!(message = "unable to decode advertisement", address = %address, error = %err);
+                    continue;
+                }
+            };
+            let state = received.entry(address).or_default();
+            if !state.update(&beacon) {
+                continue;
+            }
+            if let Some(reading) = state.reading(ctx.source(address)) {
+                ctx.output().write(&reading)?;
+                sinks.publish(Kind::Realtime, &reading).await;
+            }
+        }
+        sinks.close().await;
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use bluer::Address;
+use bluer_miflora::{EpochTime, Model, RawPayload};
+use serde::Serialize;
+
+use crate::context::Context;
+use crate::output::Record;
+
+/// Model of the device the payloads come from.
+#[derive(Clone, Copy, Debug, Default, clap::ValueEnum)]
+enum ModelArg {
+    #[default]
+    FlowerCare,
+    Ropot,
+    GrowCareGarden,
+}
+
+impl From<ModelArg> for Model {
+    fn from(value: ModelArg) -> Self {
+        match value {
+            ModelArg::FlowerCare => Self::FlowerCare,
+            ModelArg::Ropot => Self::Ropot,
+            ModelArg::GrowCareGarden => Self::GrowCareGarden,
+        }
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct Command {
+    /// Directory written with `--dump-raw`, or one of its files.
+    path: PathBuf,
+    /// Model of the devices, changing how the realtime values and the history are decoded.
+    #[arg(long, value_enum, default_value_t)]
+    model: ModelArg,
+}
+
+impl Command {
+    pub async fn run(self, ctx: &Context) -> anyhow::Result<()> {
+        let payloads = if self.path.is_dir() {
+            RawPayload::load_dir(&self.path)?
+        } else {
+            vec![RawPayload::load(&self.path)?]
+        };
+        let model = Model::from(self.model);
+        // the history entries are timestamped with the last uptime read from their device
+        let mut boot_times: HashMap<Address, EpochTime> = HashMap::new();
+        for payload in payloads {
+            if let Some(boot_time) = payload.boot_time() {
+                boot_times.insert(payload.address, boot_time);
+            }
+            let epoch = boot_times
+                .get(&payload.address)
+                .copied()
+                .unwrap_or(EpochTime::exact(0));
+            ctx.output().write(&Parsed::new(&payload, model, epoch))?;
+        }
+        Ok(())
+    }
+}
+
+/// Payload decoded from a dump.
+#[derive(Debug, Serialize)]
+struct Parsed {
+    address: Address,
+    /// Time the payload was read, in milliseconds since the unix epoch.
+    timestamp: u64,
+    kind: &'static str,
+    raw: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl Parsed {
+    fn new(payload: &RawPayload, model: Model, epoch: EpochTime) -> Self {
+        let (value, error) = match payload.decode(model, epoch) {
+            Ok(decoded) => (Some(decoded.to_string()), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+        Self {
+            address: payload.address,
+            timestamp: payload.timestamp,
+            kind: payload.kind.name(),
+            raw: payload
+                .payload
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect(),
+            value,
+            error,
+        }
+    }
+}
+
+impl Record for Parsed {
+    const COLUMNS: &'static [&'static str] =
+        &["address", "timestamp", "kind", "raw", "value", "error"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.address.to_string(),
+            self.timestamp.to_string(),
+            self.kind.to_string(),
+            self.raw.clone(),
+            self.value.clone().unwrap_or_default(),
+            self.error.clone().unwrap_or_default(),
+        ]
+    }
+
+    fn log(&self) {
+        match (&self.value, &self.error) {
+            (_, Some(error)) => tracing::warn!(
+                message = "unable to decode payload",
+                address = %self.address,
+                timestamp = self.timestamp,
+                kind = self.kind,
+                raw = %self.raw,
+                error = %error,
+            ),
+            (value, None) => tracing::info!(
+                message = "payload",
+                address = %self.address,
+                timestamp = self.timestamp,
+                kind = self.kind,
+                raw = %self.raw,
+                value = value.as_deref().unwrap_or_default(),
+            ),
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use bluer_miflora::Miflora;
+
+use crate::context::Context;
+use crate::record::Reading;
+
+#[derive(Debug, clap::Args)]
+pub struct Command;
+
+impl Command {
+    pub async fn run(self, ctx: &Context) -> anyhow::Result<()> {
+        ctx.for_each_device(|miflora| handle(miflora, ctx)).await
+    }
+}
+
+async fn handle(miflora: Miflora, ctx: &Context) -> anyhow::Result<()> {
+    tracing::debug!("reading values...");
+    let snapshot = miflora
+        .with_connection(|miflora| async move { miflora.read_all(false).await })
+        .await?;
+    ctx.output().write(
+        &Reading::new(ctx.source(miflora.address()), snapshot.realtime())
+            .with_system(snapshot.system()),
+    )
+}
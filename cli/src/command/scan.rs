@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use bluer_miflora::advertisement::read_advertisement;
+use bluer_miflora::{Error, Miflora};
+use futures::{pin_mut, StreamExt};
+
+use crate::context::Context;
+use crate::output::Format;
+use crate::record::Device;
+
+#[derive(Debug, clap::Args)]
+pub struct Command {
+    /// Duration of the discovery, like `30s`.
+    #[arg(long, default_value = "10s", value_parser = humantime::parse_duration)]
+    duration: Duration,
+}
+
+async fn describe(ctx: &Context, miflora: &Miflora) -> Device {
+    let device = miflora.client().device();
+    let encrypted = match read_advertisement(device).await {
+        Ok(_) => Some(false),
+        Err(Error::EncryptedAdvertisement) => Some(true),
+        Err(_) => None,
+    };
+    Device {
+        source: ctx.source(miflora.address()),
+        name: device.name().await.ok().flatten(),
+        rssi: miflora.rssi().await.ok().flatten(),
+        encrypted,
+    }
+}
+
+impl Command {
+    pub async fn run(self, ctx: &Context) -> anyhow::Result<()> {
+        let addresses = &ctx.args().addresses;
+        let deadline = tokio::time::Instant::now() + self.duration;
+        let devices = bluer_miflora::scan(ctx.adapter());
+        pin_mut!(devices);
+
+        let mut found = Vec::new();
+        while let Ok(Some(miflora)) = tokio::time::timeout_at(deadline, devices.next()).await {
+            match miflora {
+                Ok(miflora) if addresses.is_empty() || addresses.contains(&miflora.address()) => {
+                    found.push(describe(ctx, &miflora).await);
+                }
+                Ok(_) => {}
+                Err(err) => tracing::warn!(message = "unable to check device", error = %err),
+            }
+        }
+        match ctx.output().format() {
+            Format::Text => print_table(&found),
+            _ => {
+                for device in found {
+                    ctx.output().write(&device)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn print_table(devices: &[Device]) {
+    let rows: Vec<[String; 5]> = devices
+        .iter()
+        .map(|device| {
+            [
+                device.source.address.to_string(),
+                device.source.alias.clone().unwrap_or_else(|| "-".into()),
+                device.name.clone().unwrap_or_else(|| "-".into()),
+                device
+                    .rssi
+                    .map_or_else(|| "-".into(), |value| value.to_string()),
+                match device.encrypted {
+                    Some(true) => "yes".into(),
+                    Some(false) => "no".into(),
+                    None => "?".into(),
+                },
+            ]
+        })
+        .collect();
+    let header = ["ADDRESS", "ALIAS", "NAME", "RSSI", "ENCRYPTED"].map(String::from);
+    let width = |column: usize| {
+        rows.iter()
+            .chain(std::iter::once(&header))
+            .map(|row| row[column].chars().count())
+            .max()
+            .unwrap_or_default()
+    };
+    let (alias_width, name_width) = (width(1), width(2));
+    for [address, alias, name, rssi, encrypted] in std::iter::once(&header).chain(&rows) {
+        println!(
+            "{address:<17}  {alias:<alias_width$}  {name:<name_width$}  {rssi:>4}  {encrypted}"
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use std::io::IsTerminal;
+
+use bluer_miflora::plants::{evaluate, Issue, PlantDatabase};
+use bluer_miflora::{BatteryState, BatteryThresholds, Miflora};
+use futures::StreamExt;
+use serde::Serialize;
+
+use crate::context::Context;
+use crate::output::{Format, Record};
+use crate::record::Reading;
+
+#[derive(Debug, clap::Args)]
+pub struct Command {
+    /// Doesn't colorize the table, like when the `NO_COLOR` variable is set or the output
+    /// isn't a terminal.
+    #[arg(long)]
+    no_color: bool,
+}
+
+impl Command {
+    pub async fn run(self, ctx: &Context) -> anyhow::Result<()> {
+        let database = PlantDatabase::bundled();
+        let database = &database;
+        let config = ctx.config();
+        // without any address, only the configured devices are checked when there are some
+        let configured = |miflora: &Miflora| {
+            !ctx.args().addresses.is_empty()
+                || config.devices.is_empty()
+                || config
+                    .devices
+                    .iter()
+                    .any(|device| device.address == miflora.address())
+        };
+
+        let mut found: Vec<_> = ctx
+            .discovered()
+            .filter(|miflora| futures::future::ready(configured(miflora)))
+            .map(|miflora| {
+                ctx.handle(miflora, |miflora| async move {
+                    read(ctx, &miflora, database).await
+                })
+            })
+            .buffer_unordered(ctx.concurrency())
+            .filter_map(futures::future::ready)
+            .collect()
+            .await;
+        found.sort_by_key(|status| status.name());
+
+        match ctx.output().format() {
+            Format::Text => {
+                let colored = !self.no_color
+                    && std::env::var_os("NO_COLOR").is_none()
+                    && std::io::stdout().is_terminal();
+                print_table(&found, Palette { colored });
+            }
+            _ => {
+                for status in found {
+                    ctx.output().write(&status)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn read(
+    ctx: &Context,
+    miflora: &Miflora,
+    database: &PlantDatabase,
+) -> anyhow::Result<Status> {
+    let snapshot = miflora
+        .with_connection(|miflora| async move { miflora.read_all(false).await })
+        .await?;
+    let species = ctx
+        .config()
+        .devices
+        .iter()
+        .find(|device| device.address == miflora.address())
+        .and_then(|device| device.species.clone());
+    let profile = species.as_deref().and_then(|species| {
+        let profile = database.get(species);
+        if profile.is_none() {
+            tracing::warn!(message = "unknown species", species = species);
+        }
+        profile
+    });
+    Ok(Status {
+        reading: Reading::new(ctx.source(miflora.address()), snapshot.realtime())
+            .with_system(snapshot.system()),
+        battery_state: snapshot
+            .system()
+            .battery_state_with(&BatteryThresholds::default()),
+        species,
+        issues: profile.map(|profile| evaluate(snapshot.realtime(), profile).issues().to_vec()),
+    })
+}
+
+/// Current values of a device, evaluated against the profile of its plant.
+#[derive(Debug, Serialize)]
+struct Status {
+    #[serde(flatten)]
+    reading: Reading,
+    #[serde(serialize_with = "crate::record::display")]
+    battery_state: BatteryState,
+    species: Option<String>,
+    /// Missing when the species of the plant isn't known
+    #[serde(serialize_with = "display_issues")]
+    issues: Option<Vec<Issue>>,
+}
+
+fn display_issues<S: serde::Serializer>(
+    issues: &Option<Vec<Issue>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match issues {
+        Some(issues) => serializer.collect_seq(issues.iter().map(Issue::to_string)),
+        None => serializer.serialize_none(),
+    }
+}
+
+impl Status {
+    fn name(&self) -> String {
+        self.reading
+            .source
+            .alias
+            .clone()
+            .unwrap_or_else(|| self.reading.source.address.to_string())
+    }
+
+    fn verdict(&self) -> String {
+        match self.issues {
+            None => "-".into(),
+            Some(ref issues) if issues.is_empty() => "✅ healthy".into(),
+            Some(ref issues) => {
+                let issues: Vec<_> = issues.iter().map(Issue::to_string).collect();
+                format!("⚠️ {}", issues.join(", "))
+            }
+        }
+    }
+
+    fn has_any(&self, candidates: [Issue; 2]) -> bool {
+        self.issues
+            .as_ref()
+            .is_some_and(|issues| candidates.iter().any(|issue| issues.contains(issue)))
+    }
+}
+
+impl Record for Status {
+    const COLUMNS: &'static [&'static str] = &[
+        "address",
+        "alias",
+        "timestamp",
+        "temperature",
+        "brightness",
+        "moisture",
+        "conductivity",
+        "battery",
+        "firmware",
+        "battery_state",
+        "species",
+        "issues",
+    ];
+
+    fn row(&self) -> Vec<String> {
+        let mut row = self.reading.row();
+        row.extend([
+            self.battery_state.to_string(),
+            self.species.clone().unwrap_or_default(),
+            self.issues
+                .as_ref()
+                .map(|issues| {
+                    let issues: Vec<_> = issues.iter().map(Issue::to_string).collect();
+                    issues.join(",")
+                })
+                .unwrap_or_default(),
+        ]);
+        row
+    }
+
+    fn log(&self) {
+        tracing::info!(
+            message = "status",
+            temperature = self.reading.temperature,
+            moisture = self.reading.moisture,
+            conductivity = self.reading.conductivity,
+            battery = self.reading.battery,
+            verdict = %self.verdict(),
+        );
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Color {
+    Red,
+    Yellow,
+    Green,
+}
+
+/// Colorizes the cells of the table with ANSI escape codes, when enabled.
+#[derive(Clone, Copy)]
+struct Palette {
+    colored: bool,
+}
+
+impl Palette {
+    fn paint(&self, text: String, color: Option<Color>) -> String {
+        let code = match color {
+            Some(Color::Red) if self.colored => 31,
+            Some(Color::Yellow) if self.colored => 33,
+            Some(Color::Green) if self.colored => 32,
+            _ => return text,
+        };
+        format!("\x1b[{code}m{text}\x1b[0m")
+    }
+}
+
+fn print_table(statuses: &[Status], palette: Palette) {
+    let name_width = statuses
+        .iter()
+        .map(|status| status.name().chars().count())
+        .chain(std::iter::once("DEVICE".len()))
+        .max()
+        .unwrap_or_default();
+    println!(
+        "{:<name_width$}  {:>11}  {:>8}  {:>9}  {:>10}  {:>7}  STATUS",
+        "DEVICE", "TEMPERATURE", "MOISTURE", "LIGHT", "FERTILITY", "BATTERY"
+    );
+    for status in statuses {
+        let reading = &status.reading;
+        let warn = |issues: [Issue; 2]| status.has_any(issues).then_some(Color::Yellow);
+        let temperature = palette.paint(
+            format!("{:>9.1}°C", reading.temperature),
+            warn([Issue::TooCold, Issue::TooHot]),
+        );
+        let moisture = palette.paint(
+            format!("{:>7}%", reading.moisture),
+            warn([Issue::TooDry, Issue::TooWet]),
+        );
+        let brightness = palette.paint(
+            format!(
+                "{:>9}",
+                reading
+                    .brightness
+                    .map_or_else(|| "-".into(), |value| format!("{value} lx"))
+            ),
+            warn([Issue::TooDark, Issue::TooBright]),
+        );
+        let conductivity = palette.paint(
+            format!("{:>10}", format!("{} µS/cm", reading.conductivity)),
+            warn([Issue::LowFertility, Issue::HighFertility]),
+        );
+        let battery = palette.paint(
+            format!("{:>6}%", reading.battery.unwrap_or_default()),
+            Some(match status.battery_state {
+                BatteryState::Critical => Color::Red,
+                BatteryState::Low => Color::Yellow,
+                BatteryState::Ok => Color::Green,
+            }),
+        );
+        let verdict = palette.paint(
+            status.verdict(),
+            status.issues.as_ref().map(|issues| {
+                if issues.is_empty() {
+                    Color::Green
+                } else {
+                    Color::Yellow
+                }
+            }),
+        );
+        println!(
+            "{:<name_width$}  {temperature}  {moisture}  {brightness}  {conductivity}  {battery}  {verdict}",
+            status.name()
+        );
+    }
+}
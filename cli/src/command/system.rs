@@ -0,0 +1,21 @@
+use bluer_miflora::Miflora;
+
+use crate::context::Context;
+use crate::record::SystemInfo;
+
+#[derive(Debug, clap::Args)]
+pub struct Command;
+
+impl Command {
+    pub async fn run(self, ctx: &Context) -> anyhow::Result<()> {
+        ctx.for_each_device(|miflora| handle(miflora, ctx)).await
+    }
+}
+
+async fn handle(miflora: Miflora, ctx: &Context) -> anyhow::Result<()> {
+    let system = miflora
+        .with_connection(|miflora| async move { miflora.read_system().await })
+        .await?;
+    ctx.output()
+        .write(&SystemInfo::new(ctx.source(miflora.address()), &system))
+}
@@ -0,0 +1,48 @@
+use bluer_miflora::Miflora;
+use futures::StreamExt;
+use tracing::Instrument;
+
+use crate::context::Context;
+use crate::record::Reading;
+
+#[derive(Debug, clap::Args)]
+pub struct Command;
+
+impl Command {
+    pub async fn run(self, ctx: &Context) -> anyhow::Result<()> {
+        // each device is streamed until the end, without any concurrency limit
+        ctx.discovered()
+            .for_each_concurrent(None, |miflora| {
+                let span = ctx.span(miflora.address());
+                async move {
+                    if let Err(err) = handle(miflora, ctx).await {
+                        tracing::warn!(message = "something went wrong", error = %err);
+                    }
+                }
+                .instrument(span)
+            })
+            .await;
+        Ok(())
+    }
+}
+
+async fn handle(miflora: Miflora, ctx: &Context) -> anyhow::Result<()> {
+    ctx.interruptible(&miflora, ctx.connect(&miflora)).await?;
+    let result = ctx.interruptible(&miflora, stream(&miflora, ctx)).await;
+    miflora.try_disconnect().await?;
+    result
+}
+
+async fn stream(miflora: &Miflora, ctx: &Context) -> anyhow::Result<()> {
+    let values = miflora.subscribe_realtime().await?;
+    futures::pin_mut!(values);
+    while let Some(values) = values.next().await {
+        match values {
+            Ok(values) => ctx
+                .output()
+                .write(&Reading::new(ctx.source(miflora.address()), &values))?,
+            Err(err) => tracing::warn!(message = "invalid values", error = %err),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,412 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use bluer::Address;
+use serde::Deserialize;
+
+fn default_realtime_interval() -> Duration {
+    Duration::from_secs(10 * 60)
+}
+
+fn default_history_interval() -> Duration {
+    Duration::from_secs(24 * 60 * 60)
+}
+
+fn default_mqtt_port() -> u16 {
+    1883
+}
+
+fn default_mqtt_client_id() -> String {
+    "miflora".into()
+}
+
+fn default_mqtt_qos() -> u8 {
+    1
+}
+
+fn default_mqtt_state_topic() -> String {
+    "miflora/{alias}/state".into()
+}
+
+fn default_mqtt_history_topic() -> String {
+    "miflora/{alias}/history".into()
+}
+
+fn default_nats_state_subject() -> String {
+    "miflora.{alias}.state".into()
+}
+
+fn default_nats_history_subject() -> String {
+    "miflora.{alias}.history".into()
+}
+
+fn default_jetstream_stream() -> String {
+    "MIFLORA".into()
+}
+
+fn default_discovery_prefix() -> String {
+    "homeassistant".into()
+}
+
+fn default_ntfy_server() -> String {
+    "https://ntfy.sh".into()
+}
+
+fn default_influxdb_measurement() -> String {
+    "miflora".into()
+}
+
+fn default_influxdb_batch_size() -> usize {
+    100
+}
+
+fn default_parquet_rotation() -> Duration {
+    Duration::from_secs(60 * 60)
+}
+
+fn default_influxdb_flush_interval() -> Duration {
+    Duration::from_secs(10)
+}
+
+/// Configuration of the devices, read from a TOML file.
+///
+/// ```toml
+/// realtime_interval = "10m"
+/// history_interval = "1d"
+///
+/// [[devices]]
+/// address = "C4:7C:8D:6A:3E:1F"
+/// alias = "basil-kitchen"
+/// realtime_interval = "5m"
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Name or address of the bluetooth adapter, when not set with `--adapter`.
+    pub adapter: Option<String>,
+    /// Directory the daemon keeps the state of the devices in, when not set with
+    /// `--state-dir`.
+    pub state_dir: Option<PathBuf>,
+    /// Maximum duration of the discovery and of each operation with a device, when not
+    /// set with `--timeout`.
+    #[serde(default, with = "humantime_serde")]
+    pub timeout: Option<Duration>,
+    /// Maximum duration of each connection attempt, when not set with `--connect-timeout`.
+    #[serde(default, with = "humantime_serde")]
+    pub connect_timeout: Option<Duration>,
+    /// Number of times a failing operation is retried, when not set with `--retries`.
+    pub retries: Option<u8>,
+    /// Interval between the reads of the realtime values, when not set on the device.
+    #[serde(default = "default_realtime_interval", with = "humantime_serde")]
+    pub realtime_interval: Duration,
+    /// Interval between the reads of the history, when not set on the device.
+    #[serde(default = "default_history_interval", with = "humantime_serde")]
+    pub history_interval: Duration,
+    #[serde(default)]
+    pub devices: Vec<DeviceConfig>,
+    /// Broker the daemon publishes the readings to.
+    pub mqtt: Option<MqttConfig>,
+    /// NATS server the daemon publishes the readings to.
+    pub nats: Option<NatsConfig>,
+    /// Database the daemon stores the readings in.
+    pub sqlite: Option<SqliteConfig>,
+    /// InfluxDB 2 bucket the daemon writes the readings to.
+    pub influxdb: Option<InfluxDbConfig>,
+    /// Directory the daemon writes Parquet files of the readings to.
+    pub parquet: Option<ParquetConfig>,
+    /// D-Bus service the daemon exposes the readings on, to the other services of the host.
+    pub dbus: Option<DbusConfig>,
+    /// Notifiers of the readings crossing the thresholds of the devices.
+    #[serde(default)]
+    pub alerts: AlertsConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceConfig {
+    pub address: Address,
+    /// Name used in the logs and the results instead of the address alone.
+    pub alias: Option<String>,
+    #[serde(default, with = "humantime_serde")]
+    pub realtime_interval: Option<Duration>,
+    #[serde(default, with = "humantime_serde")]
+    pub history_interval: Option<Duration>,
+    /// Scientific name of the plant, like `ocimum basilicum`, its care being evaluated
+    /// against the bundled profile of the species.
+    pub species: Option<String>,
+    /// Ranges the values are expected in, an alert being sent when leaving them.
+    #[serde(default)]
+    pub thresholds: Thresholds,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Thresholds {
+    pub moisture: Option<Range>,
+    pub temperature: Option<Range>,
+    pub conductivity: Option<Range>,
+    pub battery: Option<Range>,
+}
+
+/// Bounds of a value, both included.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Range {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlertsConfig {
+    pub webhook: Option<WebhookConfig>,
+    pub ntfy: Option<NtfyConfig>,
+    pub telegram: Option<TelegramConfig>,
+}
+
+/// Endpoint receiving the alerts as JSON with a `POST` request.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Headers added to the requests, like an authorization.
+    #[serde(default)]
+    pub headers: std::collections::BTreeMap<String, String>,
+}
+
+/// Topic of an ntfy server the alerts are pushed to.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NtfyConfig {
+    #[serde(default = "default_ntfy_server")]
+    pub server: String,
+    pub topic: String,
+    /// Access token, for the protected topics.
+    pub token: Option<String>,
+    /// Priority of the messages, from 1 to 5.
+    pub priority: Option<u8>,
+}
+
+/// Telegram chat the alerts are sent to by a bot.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TelegramConfig {
+    /// Token of the bot, given by the BotFather.
+    pub token: String,
+    /// Identifier of the chat, or `@name` of the channel.
+    pub chat_id: String,
+}
+
+/// Connection to an MQTT broker and topics of the readings.
+///
+/// In the topics, `{address}` is replaced by the address of the device and `{alias}` by its
+/// alias, or its address when it has none.
+///
+/// Parsed even without the `mqtt` feature, to report it instead of an unknown section.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "mqtt"), allow(dead_code))]
+pub struct MqttConfig {
+    pub host: String,
+    #[serde(default = "default_mqtt_port")]
+    pub port: u16,
+    #[serde(default = "default_mqtt_client_id")]
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Connects with TLS, checking the certificate of the broker with the system ones.
+    #[serde(default)]
+    pub tls: bool,
+    /// PEM file of the authority that signed the certificate of the broker, enables TLS.
+    pub ca_file: Option<PathBuf>,
+    /// Quality of service of the messages, from 0 to 2.
+    #[serde(default = "default_mqtt_qos")]
+    pub qos: u8,
+    /// Keeps the last message of each topic on the broker.
+    #[serde(default)]
+    pub retain: bool,
+    #[serde(default = "default_mqtt_state_topic")]
+    pub state_topic: String,
+    #[serde(default = "default_mqtt_history_topic")]
+    pub history_topic: String,
+    /// Announces the sensors of the devices to Home Assistant on startup.
+    #[serde(default)]
+    pub discovery: bool,
+    /// Topic prefix Home Assistant listens to for the discovery.
+    #[serde(default = "default_discovery_prefix")]
+    pub discovery_prefix: String,
+}
+
+/// Connection to a NATS server and subjects of the readings.
+///
+/// In the subjects, `{address}` is replaced by the address of the device and `{alias}` by its
+/// alias, or its address when it has none.
+///
+/// Parsed even without the `nats` feature, to report it instead of an unknown section.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "nats"), allow(dead_code))]
+pub struct NatsConfig {
+    /// Address of the server, like `nats://localhost:4222`.
+    pub url: String,
+    /// Credentials file of the user, as generated by `nsc`.
+    pub credentials_file: Option<PathBuf>,
+    pub token: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    #[serde(default = "default_nats_state_subject")]
+    pub state_subject: String,
+    #[serde(default = "default_nats_history_subject")]
+    pub history_subject: String,
+    /// Stores the readings in a JetStream stream, each one being acknowledged by the server.
+    pub jetstream: Option<JetStreamConfig>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "nats"), allow(dead_code))]
+pub struct JetStreamConfig {
+    /// Name of the stream, created when missing.
+    #[serde(default = "default_jetstream_stream")]
+    pub stream: String,
+    /// Maximum age of the readings kept in the stream, forever when not set.
+    #[serde(default, with = "humantime_serde")]
+    pub max_age: Option<Duration>,
+}
+
+/// Parsed even without the `sqlite` feature, to report it instead of an unknown section.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+pub struct SqliteConfig {
+    /// Database file, created when missing.
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InfluxDbConfig {
+    /// Address of the server, like `http://localhost:8086`.
+    pub url: String,
+    pub org: String,
+    pub bucket: String,
+    pub token: String,
+    #[serde(default = "default_influxdb_measurement")]
+    pub measurement: String,
+    /// Number of readings sent in a single request.
+    #[serde(default = "default_influxdb_batch_size")]
+    pub batch_size: usize,
+    /// Maximum time a reading waits to be sent.
+    #[serde(default = "default_influxdb_flush_interval", with = "humantime_serde")]
+    pub flush_interval: Duration,
+}
+
+/// Message bus the D-Bus service is registered on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(not(feature = "dbus"), allow(dead_code))]
+pub enum Bus {
+    #[default]
+    System,
+    /// Bus of the user session, not requiring a policy to own the service name
+    Session,
+}
+
+/// Parsed even without the `dbus` feature, to report it instead of an unknown section.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "dbus"), allow(dead_code))]
+pub struct DbusConfig {
+    #[serde(default)]
+    pub bus: Bus,
+}
+
+/// Parsed even without the `parquet` feature, to report it instead of an unknown section.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "parquet"), allow(dead_code))]
+pub struct ParquetConfig {
+    /// Directory of the files, created when missing.
+    pub directory: PathBuf,
+    /// Interval between the files, each one holding the readings received meanwhile.
+    #[serde(default = "default_parquet_rotation", with = "humantime_serde")]
+    pub rotation: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            adapter: None,
+            state_dir: None,
+            timeout: None,
+            connect_timeout: None,
+            retries: None,
+            realtime_interval: default_realtime_interval(),
+            history_interval: default_history_interval(),
+            devices: Vec::new(),
+            mqtt: None,
+            nats: None,
+            sqlite: None,
+            influxdb: None,
+            parquet: None,
+            dbus: None,
+            alerts: AlertsConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| anyhow::anyhow!("unable to read {}: {err}", path.display()))?;
+        Ok(toml::from_str(&content)?)
+    }
+
+    pub fn alias(&self, address: &Address) -> Option<&str> {
+        self.devices
+            .iter()
+            .find(|device| &device.address == address)
+            .and_then(|device| device.alias.as_deref())
+    }
+
+    pub fn realtime_interval(&self, device: &DeviceConfig) -> Duration {
+        device.realtime_interval.unwrap_or(self.realtime_interval)
+    }
+
+    pub fn history_interval(&self, device: &DeviceConfig) -> Duration {
+        device.history_interval.unwrap_or(self.history_interval)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Config;
+
+    #[test]
+    fn should_use_default_intervals() {
+        let config: Config = toml::from_str(
+            r#"
+history_interval = "12h"
+
+[[devices]]
+address = "C4:7C:8D:6A:3E:1F"
+realtime_interval = "5m"
+
+[[devices]]
+address = "C4:7C:8D:6A:3E:20"
+alias = "basil-kitchen"
+"#,
+        )
+        .unwrap();
+        let [first, second] = config.devices.as_slice() else {
+            panic!("expected two devices");
+        };
+        assert_eq!(config.realtime_interval(first), Duration::from_secs(300));
+        assert_eq!(config.history_interval(first), Duration::from_secs(43200));
+        assert_eq!(config.realtime_interval(second), Duration::from_secs(600));
+        assert_eq!(config.alias(&second.address), Some("basil-kitchen"));
+        assert_eq!(config.alias(&first.address), None);
+    }
+}
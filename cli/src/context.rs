@@ -0,0 +1,429 @@
+use std::collections::HashSet;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use bluer::{Adapter, Address};
+use bluer_miflora::{Miflora, RetryPolicy};
+use futures::stream::{self, Stream, StreamExt};
+use tokio::sync::{watch, Mutex};
+use tracing::Instrument;
+
+use crate::config::Config;
+use crate::logging::{DeviceLog, LogFormat};
+use crate::output::{Format, Output};
+use crate::record::Source;
+use crate::state::State;
+
+/// Options shared by all the commands.
+#[derive(Clone, Debug, clap::Args)]
+pub struct CommonArgs {
+    /// Address of the device to communicate with, can be repeated. All the devices
+    /// discovered before the timeout are used when not provided.
+    #[arg(long = "address", value_name = "ADDRESS", global = true)]
+    pub addresses: Vec<Address>,
+    /// Name or address of the bluetooth adapter, like `hci1`. The one of the configuration
+    /// file or else the default adapter is used when not provided.
+    #[arg(long, global = true)]
+    pub adapter: Option<String>,
+    /// Configuration file describing the devices.
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+    /// Maximum duration of the discovery and of each operation with a device, like `30s`.
+    /// Defaults to the one of the configuration file, or else 30 seconds.
+    #[arg(long, global = true, value_parser = humantime::parse_duration)]
+    pub timeout: Option<Duration>,
+    /// Maximum duration of each connection attempt, like `1m`. Defaults to the one of the
+    /// configuration file, or else the timeout.
+    #[arg(long, global = true, value_parser = humantime::parse_duration)]
+    pub connect_timeout: Option<Duration>,
+    /// Minimum duration before handling again a device announced again during the
+    /// discovery, like `10m`.
+    #[arg(long, global = true, default_value = "10m", value_parser = humantime::parse_duration)]
+    pub cooldown: Duration,
+    /// Number of times a failing operation is retried. Defaults to the one of the
+    /// configuration file, or else 3.
+    #[arg(long, global = true)]
+    pub retries: Option<u8>,
+    /// Maximum number of devices handled at the same time, the connections being still
+    /// established one at a time.
+    #[arg(long, global = true, default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+    /// Format of the results.
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub format: Format,
+    /// Doesn't print the names of the columns in the CSV format.
+    #[arg(long, global = true)]
+    pub no_header: bool,
+    /// Directory each payload read from the devices is written to, in its own file, to
+    /// share the captures of an unusual firmware.
+    #[arg(long, global = true, value_name = "DIR")]
+    pub dump_raw: Option<PathBuf>,
+    /// Directory the state of the devices is kept in across the restarts, like the last
+    /// history entry read. Defaults to `$XDG_STATE_HOME/miflora`.
+    #[arg(long, global = true, value_name = "DIR")]
+    pub state_dir: Option<PathBuf>,
+    /// Stops at the first device failing, instead of handling the other devices.
+    #[arg(long, global = true)]
+    pub fail_fast: bool,
+    /// Format of the logs printed on the standard error, `json` logging each operation with
+    /// a device with its duration and outcome.
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub log_format: LogFormat,
+    /// OpenTelemetry collector the metrics and the traces are exported to over OTLP/HTTP,
+    /// like `http://localhost:4318`.
+    #[cfg(feature = "opentelemetry")]
+    #[arg(long, global = true, value_name = "URL")]
+    pub otlp_endpoint: Option<String>,
+}
+
+/// Maximum duration of the operations when not configured.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Number of retries of the failing operations when not configured.
+const DEFAULT_RETRIES: u8 = 3;
+
+/// Exit code when some of the devices failed.
+pub const EXIT_PARTIAL_FAILURE: u8 = 1;
+/// Exit code when all the devices failed, or the command itself failed.
+pub const EXIT_FAILURE: u8 = 2;
+/// Exit code when the arguments are invalid.
+pub const EXIT_USAGE: u8 = 64;
+
+/// Error of the operations interrupted by a shutdown.
+#[derive(Debug)]
+pub struct Interrupted;
+
+impl std::fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("interrupted by a shutdown")
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+/// Error of the arguments that can't be used together, exiting with [`EXIT_USAGE`].
+#[derive(Debug)]
+pub struct Usage(pub &'static str);
+
+impl std::fmt::Display for Usage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl std::error::Error for Usage {}
+
+/// Bluetooth adapter and options used by the commands.
+#[derive(Debug)]
+pub struct Context {
+    /// Missing for the commands working offline
+    adapter: Option<Adapter>,
+    args: CommonArgs,
+    config: Config,
+    output: Output,
+    /// Held while connecting, concurrent connection attempts through a single adapter
+    /// tending to fail
+    connect_lock: Mutex<()>,
+    /// Set once a shutdown is requested
+    shutdown: watch::Sender<bool>,
+    /// Number of devices handled successfully
+    succeeded: AtomicUsize,
+    /// Number of devices that failed or weren't found
+    failed: AtomicUsize,
+}
+
+impl Context {
+    pub async fn new(args: CommonArgs) -> anyhow::Result<Self> {
+        let mut ctx = Self::offline(args)?;
+        let session = bluer::Session::new().await?;
+        let adapter = match ctx.args.adapter.as_ref().or(ctx.config.adapter.as_ref()) {
+            Some(name) => bluer_miflora::select_adapter(&session, name).await?,
+            None => session.default_adapter().await?,
+        };
+        tracing::debug!(
+            "discovering devices using Bluetooth adapter {}",
+            adapter.name()
+        );
+        adapter.set_powered(true).await?;
+        ctx.adapter = Some(adapter);
+        Ok(ctx)
+    }
+
+    /// Context of the commands that don't communicate with the devices, without any
+    /// Bluetooth adapter.
+    pub fn offline(args: CommonArgs) -> anyhow::Result<Self> {
+        let config = match args.config {
+            Some(ref path) => Config::load(path)?,
+            None => Config::default(),
+        };
+        let output = Output::new(args.format).with_header(!args.no_header);
+        Ok(Self {
+            adapter: None,
+            args,
+            config,
+            output,
+            connect_lock: Mutex::new(()),
+            shutdown: watch::Sender::new(false),
+            succeeded: AtomicUsize::new(0),
+            failed: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn adapter(&self) -> &Adapter {
+        self.adapter
+            .as_ref()
+            .expect("bluetooth adapter used by an offline command")
+    }
+
+    pub fn args(&self) -> &CommonArgs {
+        &self.args
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    /// Device a record comes from, with its alias.
+    pub fn source(&self, address: Address) -> Source {
+        Source {
+            address,
+            alias: self.config.alias(&address).map(String::from),
+        }
+    }
+
+    /// Loads the state of the devices from the state directory, only keeping it in memory
+    /// when no directory can be found.
+    pub fn load_state(&self) -> anyhow::Result<Arc<State>> {
+        let directory = self
+            .args
+            .state_dir
+            .clone()
+            .or_else(|| self.config.state_dir.clone())
+            .or_else(crate::state::default_directory);
+        let state = match directory {
+            Some(directory) => {
+                tracing::debug!(message = "loading state", directory = %directory.display());
+                State::load(&directory)?
+            }
+            None => {
+                tracing::warn!("no state directory found, the state is kept in memory");
+                State::default()
+            }
+        };
+        Ok(Arc::new(state))
+    }
+
+    /// Span identifying the device in the logs.
+    pub fn span(&self, address: Address) -> tracing::Span {
+        tracing::info_span!("device", address = %address, alias = self.config.alias(&address))
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        let retries = self.args.retries.or(self.config.retries);
+        RetryPolicy::default().with_max_retries(retries.unwrap_or(DEFAULT_RETRIES))
+    }
+
+    /// Maximum duration of the discovery and of each operation with a device.
+    pub fn timeout(&self) -> Duration {
+        let timeout = self.args.timeout.or(self.config.timeout);
+        timeout.unwrap_or(DEFAULT_TIMEOUT)
+    }
+
+    /// Maximum duration of each connection attempt.
+    pub fn connect_timeout(&self) -> Duration {
+        let timeout = self.args.connect_timeout.or(self.config.connect_timeout);
+        timeout.unwrap_or_else(|| self.timeout())
+    }
+
+    /// Applies the timeout, retries, dump and log options to the discovered device, reading
+    /// again the implausible values.
+    pub fn configure(&self, miflora: Miflora) -> Miflora {
+        let address = miflora.address();
+        let mut builder = Miflora::builder(miflora.client().device().clone())
+            .with_model(miflora.model())
+            .with_retry_policy(self.retry_policy())
+            .with_gatt_retry_policy(self.retry_policy())
+            .with_operation_timeout(self.timeout())
+            .with_connect_timeout(self.connect_timeout())
+            .with_reject_implausible(true);
+        if self.args.log_format == LogFormat::Json {
+            let alias = self.config.alias(&address).map(String::from);
+            builder = builder.with_observer(DeviceLog::new(alias));
+        }
+        match self.args.dump_raw {
+            Some(ref directory) => builder.with_raw_dump(directory),
+            None => builder,
+        }
+        .build()
+    }
+
+    /// Requests the commands to stop, the operations in progress being interrupted.
+    pub fn shutdown(&self) {
+        self.shutdown.send_replace(true);
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        *self.shutdown.borrow()
+    }
+
+    /// Completes once a shutdown is requested.
+    pub async fn cancelled(&self) {
+        let mut receiver = self.shutdown.subscribe();
+        // the sender lives as long as the context
+        let _ = receiver.wait_for(|value| *value).await;
+    }
+
+    /// Runs the operation on the device until it completes or a shutdown is requested.
+    ///
+    /// When interrupted, the history download in progress if any is aborted, so the device
+    /// doesn't consider its entries as read, and the device is disconnected.
+    pub async fn interruptible<F, T>(&self, miflora: &Miflora, operation: F) -> anyhow::Result<T>
+    where
+        F: Future<Output = anyhow::Result<T>>,
+    {
+        tokio::select! {
+            result = operation => result,
+            _ = self.cancelled() => {
+                tracing::info!("interrupted, disconnecting");
+                if miflora.is_connected().await.unwrap_or_default() {
+                    if miflora.is_history_open() {
+                        if let Err(err) = miflora.abort_history_read().await {
+                            tracing::debug!(message = "unable to abort history read", error = %err);
+                        }
+                    }
+                    if let Err(err) = miflora.try_disconnect().await {
+                        tracing::warn!(message = "unable to disconnect", error = %err);
+                    }
+                }
+                Err(Interrupted.into())
+            }
+        }
+    }
+
+    /// Streams the requested devices as they are discovered, stopping once they have all
+    /// been found, when reaching the timeout or when a shutdown is requested.
+    pub fn discovered(&self) -> impl Stream<Item = Miflora> + '_ {
+        let missing: HashSet<Address> = self.args.addresses.iter().copied().collect();
+        let deadline = tokio::time::Instant::now() + self.timeout();
+        let devices = Box::pin(bluer_miflora::scan_with_cooldown(
+            self.adapter(),
+            self.args.cooldown,
+        ));
+
+        let devices = stream::unfold(Some((devices, missing)), move |state| async move {
+            let (mut devices, mut missing) = state?;
+            while let Ok(Some(miflora)) = tokio::time::timeout_at(deadline, devices.next()).await {
+                let miflora = match miflora {
+                    Ok(miflora) => miflora,
+                    Err(err) => {
+                        tracing::warn!(message = "unable to check device", error = %err);
+                        continue;
+                    }
+                };
+                let address = miflora.address();
+                tracing::debug!(message = "device discovered", address = %address, model = ?miflora.model());
+                if self.args.addresses.is_empty() {
+                    return Some((self.configure(miflora), Some((devices, missing))));
+                } else if missing.remove(&address) {
+                    // stops the discovery once all the requested devices have been found
+                    let state = (!missing.is_empty()).then_some((devices, missing));
+                    return Some((self.configure(miflora), state));
+                }
+            }
+            for address in missing {
+                tracing::warn!(message = "device not found", address = %address);
+                self.record_failure();
+            }
+            None
+        });
+        devices.take_until(Box::pin(self.cancelled()))
+    }
+
+    /// Runs the function on each requested device as soon as it's discovered, up to the
+    /// `--concurrency` option at the same time, so a slow device doesn't hold the others.
+    pub async fn for_each_device<F, Fut>(&self, func: F) -> anyhow::Result<()>
+    where
+        F: Fn(Miflora) -> Fut,
+        Fut: Future<Output = anyhow::Result<()>>,
+    {
+        let func = &func;
+        self.discovered()
+            .for_each_concurrent(self.concurrency(), |miflora| async move {
+                self.handle(miflora, func).await;
+            })
+            .await;
+        Ok(())
+    }
+
+    /// Counts a device as failed, stopping the command with `--fail-fast`.
+    fn record_failure(&self) {
+        self.failed.fetch_add(1, Ordering::Relaxed);
+        if self.args.fail_fast && !self.is_shutting_down() {
+            tracing::info!("stopping at the first failure");
+            self.shutdown();
+        }
+    }
+
+    /// Exit code matching the devices handled so far: 0 when none failed, 2 when all
+    /// failed, 1 otherwise.
+    pub fn exit_code(&self) -> u8 {
+        let succeeded = self.succeeded.load(Ordering::Relaxed);
+        match self.failed.load(Ordering::Relaxed) {
+            0 => 0,
+            _ if succeeded == 0 => EXIT_FAILURE,
+            _ => EXIT_PARTIAL_FAILURE,
+        }
+    }
+
+    /// Maximum number of devices handled at the same time.
+    pub fn concurrency(&self) -> usize {
+        self.args.concurrency as usize
+    }
+
+    /// Connects to the device, waiting for the other devices to be connected first.
+    pub async fn connect(&self, miflora: &Miflora) -> anyhow::Result<()> {
+        let _guard = self.connect_lock.lock().await;
+        Ok(miflora.try_connect().await?)
+    }
+
+    /// Connects to the device and runs the function, disconnecting afterwards if the
+    /// function didn't, the failures being logged.
+    pub async fn handle<F, Fut, T>(&self, miflora: Miflora, func: F) -> Option<T>
+    where
+        F: FnOnce(Miflora) -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let span = self.span(miflora.address());
+        let result = async {
+            self.interruptible(&miflora, self.connect(&miflora)).await?;
+            let result = self.interruptible(&miflora, func(miflora.clone())).await;
+            let disconnected = miflora.try_disconnect().await;
+            let value = result?;
+            disconnected?;
+            Ok::<_, anyhow::Error>(value)
+        }
+        .instrument(span.clone())
+        .await;
+        match result {
+            Ok(value) => {
+                self.succeeded.fetch_add(1, Ordering::Relaxed);
+                Some(value)
+            }
+            Err(err) if err.is::<Interrupted>() => {
+                span.in_scope(|| tracing::debug!(message = "interrupted", error = %err));
+                None
+            }
+            Err(err) => {
+                span.in_scope(|| tracing::warn!(message = "something went wrong", error = %err));
+                self.record_failure();
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! Logs of the commands, readable by humans or as JSON objects to be shipped to a log
+//! aggregator and queried per device.
+
+use std::fmt;
+use std::io::Write;
+use std::time::{Duration, SystemTime};
+
+use bluer::{Address, Uuid};
+use bluer_miflora::{Error, Observer, Operation};
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+/// How the logs are printed on the standard error.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Lines readable by humans.
+    #[default]
+    Text,
+    /// One JSON object per event, with the fields of the spans it happened in.
+    Json,
+}
+
+/// Logs each operation with a device, with its duration and outcome.
+#[derive(Debug)]
+pub struct DeviceLog {
+    alias: Option<String>,
+}
+
+impl DeviceLog {
+    pub fn new(alias: Option<String>) -> Self {
+        Self { alias }
+    }
+}
+
+impl Observer for DeviceLog {
+    fn on_connect(&self, address: Address, elapsed: Duration) {
+        tracing::info!(
+            message = "device connected",
+            address = %address,
+            alias = self.alias.as_deref(),
+            operation = %Operation::Connect,
+            duration_ms = elapsed.as_millis() as u64,
+            outcome = "success",
+        );
+    }
+
+    fn on_read(&self, address: Address, characteristic: Uuid, _: &[u8], elapsed: Duration) {
+        tracing::debug!(
+            message = "characteristic read",
+            address = %address,
+            alias = self.alias.as_deref(),
+            operation = %Operation::Read,
+            characteristic = %characteristic,
+            duration_ms = elapsed.as_millis() as u64,
+            outcome = "success",
+        );
+    }
+
+    fn on_error(&self, address: Address, operation: Operation, error: &Error, elapsed: Duration) {
+        tracing::warn!(
+            message = "operation failed",
+            address = %address,
+            alias = self.alias.as_deref(),
+            operation = %operation,
+            duration_ms = elapsed.as_millis() as u64,
+            outcome = "failure",
+            error = %error,
+        );
+    }
+
+    fn on_disconnect(&self, address: Address, elapsed: Duration) {
+        tracing::info!(
+            message = "device disconnected",
+            address = %address,
+            alias = self.alias.as_deref(),
+            operation = %Operation::Disconnect,
+            duration_ms = elapsed.as_millis() as u64,
+            outcome = "success",
+        );
+    }
+}
+
+/// Fields of a span, kept in its extensions to be added to the events inside it.
+struct SpanFields(Map<String, Value>);
+
+struct Visitor<'a>(&'a mut Map<String, Value>);
+
+impl Visit for Visitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().into(), Value::String(format!("{value:?}")));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
+        self.0
+            .insert(field.name().into(), Value::String(value.to_string()));
+    }
+}
+
+/// Writes each event as a single line JSON object, with the fields of its spans flattened
+/// so the address and alias of the device are at the top level.
+pub struct JsonLayer<W> {
+    writer: W,
+}
+
+impl<W> JsonLayer<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
+
+impl<S, W> Layer<S> for JsonLayer<W>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'a> MakeWriter<'a> + 'static,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut fields = Map::new();
+        attrs.record(&mut Visitor(&mut fields));
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanFields(fields));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(fields) = extensions.get_mut::<SpanFields>() {
+            values.record(&mut Visitor(&mut fields.0));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut object = Map::new();
+        object.insert(
+            "timestamp".into(),
+            humantime::format_rfc3339_millis(SystemTime::now())
+                .to_string()
+                .into(),
+        );
+        object.insert("level".into(), metadata.level().to_string().into());
+        object.insert("target".into(), metadata.target().into());
+        // the innermost spans and the event itself take precedence
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(fields) = span.extensions().get::<SpanFields>() {
+                    object.extend(fields.0.clone());
+                }
+            }
+        }
+        event.record(&mut Visitor(&mut object));
+        let mut line = Value::Object(object).to_string();
+        line.push('\n');
+        let _ = self.writer.make_writer().write_all(line.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use bluer_miflora::Observer;
+    use serde_json::Value;
+    use tracing_subscriber::prelude::*;
+
+    use super::{DeviceLog, JsonLayer};
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn should_log_operations_as_json() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber =
+            tracing_subscriber::registry().with(JsonLayer::new(move || writer.clone()));
+        let address = "C4:7C:8D:6A:3E:1F".parse().unwrap();
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("device", address = %address, alias = "ficus");
+            span.in_scope(|| {
+                DeviceLog::new(Some("ficus".into()))
+                    .on_connect(address, Duration::from_millis(1500));
+                tracing::info!(message = "done", count = 2);
+            });
+        });
+
+        let output = buffer.0.lock().unwrap();
+        let lines: Vec<Value> = output
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["address"], "C4:7C:8D:6A:3E:1F");
+        assert_eq!(lines[0]["alias"], "ficus");
+        assert_eq!(lines[0]["operation"], "connect");
+        assert_eq!(lines[0]["duration_ms"], 1500);
+        assert_eq!(lines[0]["outcome"], "success");
+        assert_eq!(lines[0]["level"], "INFO");
+        // the fields of the span are added to the other events
+        assert_eq!(lines[1]["message"], "done");
+        assert_eq!(lines[1]["count"], 2);
+        assert_eq!(lines[1]["alias"], "ficus");
+    }
+}
@@ -1,93 +1,161 @@
-use std::collections::HashSet;
-use std::env;
+use std::process::ExitCode;
+use std::time::Duration;
 
-use bluer::{Adapter, AdapterEvent, Address, DiscoveryFilter, DiscoveryTransport};
-use bluer_miflora::Miflora;
-use futures::{pin_mut, StreamExt};
+use clap::Parser;
+use tokio::signal::unix::{signal, SignalKind};
 
-fn enable_tracing() {
+mod alert;
+#[cfg(feature = "parquet")]
+mod columnar;
+mod command;
+mod config;
+mod context;
+mod logging;
+#[cfg(feature = "exporter")]
+mod metrics;
+mod output;
+mod record;
+mod sink;
+mod state;
+#[cfg(feature = "exporter")]
+mod stream;
+#[cfg(feature = "opentelemetry")]
+mod telemetry;
+
+/// Communicates with the miflora devices around.
+#[derive(Debug, Parser)]
+#[command(name = "miflora", version, about)]
+struct Args {
+    #[command(flatten)]
+    common: context::CommonArgs,
+    #[command(subcommand)]
+    command: command::Command,
+}
+
+fn enable_tracing(
+    format: logging::LogFormat,
+    #[cfg(feature = "opentelemetry")] telemetry: Option<&telemetry::Telemetry>,
+) {
     use tracing_subscriber::prelude::*;
     use tracing_subscriber::EnvFilter;
 
-    if tracing_subscriber::registry()
-        .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| "miflora=debug".into()))
-        .with(tracing_subscriber::fmt::layer())
-        .try_init()
-        .is_err()
-    {
+    let filter = || EnvFilter::try_from_default_env().unwrap_or_else(|_| "miflora=debug".into());
+    let registry = tracing_subscriber::registry()
+        .with((format == logging::LogFormat::Text).then(|| {
+            tracing_subscriber::fmt::layer()
+                .with_writer(std::io::stderr)
+                .with_filter(filter())
+        }))
+        .with(
+            (format == logging::LogFormat::Json)
+                .then(|| logging::JsonLayer::new(std::io::stderr).with_filter(filter())),
+        );
+    #[cfg(feature = "opentelemetry")]
+    let registry = registry.with(telemetry.map(telemetry::Telemetry::layer));
+    if registry.try_init().is_err() {
         tracing::warn!("tracing already set");
     }
 }
 
-#[tracing::instrument(skip(adapter))]
-pub async fn handle(adapter: Adapter, addr: Address) -> anyhow::Result<()> {
-    let miflora = Miflora::try_from_adapter(&adapter, addr).await?;
-    tracing::info!("connecting...");
-    miflora.try_connect(5).await?;
-    tracing::info!("reading system info...");
-    let system = miflora.read_system().await?;
-    tracing::info!(message = "system information", battery = system.battery(), firmware = %system.firmware());
-    let values = miflora.read_realtime_values().await?;
-    tracing::info!(
-        message = "realtime values",
-        address = %addr,
-        temperature = values.temperature(),
-        brightness = values.brightness(),
-        moisture = values.moisture(),
-        conductivity = values.conductivity(),
+/// Time given to the command to disconnect from the devices and flush the sinks, once a
+/// signal is received.
+const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// Waits for SIGINT or SIGTERM, returning the exit code matching the signal.
+async fn shutdown_signal() -> anyhow::Result<u8> {
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => {
+            result?;
+            Ok(130)
+        }
+        _ = terminate.recv() => Ok(143),
+    }
+}
+
+async fn run(args: Args) -> anyhow::Result<u8> {
+    // the json array is only printed once the command is done
+    anyhow::ensure!(
+        !(args.command.is_streaming() && args.common.format == output::Format::Json),
+        context::Usage(
+            "the json format isn't supported by the commands running until stopped, use ndjson instead"
+        )
     );
-    miflora.try_disconnect(5).await?;
-    Ok(())
+    let ctx = if args.command.is_offline() {
+        context::Context::offline(args.common)?
+    } else {
+        context::Context::new(args.common).await?
+    };
+    let command = args.command.run(&ctx);
+    tokio::pin!(command);
+    let code = tokio::select! {
+        result = &mut command => {
+            result?;
+            ctx.exit_code()
+        }
+        code = shutdown_signal() => {
+            let code = code?;
+            tracing::info!(message = "shutting down");
+            ctx.shutdown();
+            match tokio::time::timeout(SHUTDOWN_GRACE_PERIOD, &mut command).await {
+                Ok(Err(err)) => tracing::warn!(message = "command failed while shutting down", error = %err),
+                Err(_) => tracing::warn!(message = "command did not stop in time"),
+                Ok(Ok(())) => {}
+            }
+            code
+        }
+    };
+    ctx.output().finish()?;
+    Ok(code)
 }
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    enable_tracing();
-
-    let addresses: HashSet<_> = env::args()
-        .filter_map(|arg| arg.parse::<Address>().ok())
-        .collect();
+async fn main() -> ExitCode {
+    let args = match Args::try_parse() {
+        Ok(args) => args,
+        Err(err) => {
+            let _ = err.print();
+            // printing the help or the version isn't a failure
+            let code = if err.use_stderr() {
+                context::EXIT_USAGE
+            } else {
+                0
+            };
+            return ExitCode::from(code);
+        }
+    };
 
-    let session = bluer::Session::new().await?;
-    let adapter = session.default_adapter().await?;
-    tracing::info!(
-        "discovering devices using Bluetooth adapter {}",
-        adapter.name()
+    #[cfg(feature = "opentelemetry")]
+    let telemetry = match args.common.otlp_endpoint.as_deref() {
+        Some(endpoint) => match telemetry::Telemetry::new(endpoint) {
+            Ok(telemetry) => Some(telemetry),
+            Err(err) => {
+                eprintln!("Error: {err:?}");
+                return ExitCode::from(context::EXIT_FAILURE);
+            }
+        },
+        None => None,
+    };
+    enable_tracing(
+        args.common.log_format,
+        #[cfg(feature = "opentelemetry")]
+        telemetry.as_ref(),
     );
-    adapter.set_powered(true).await?;
-
-    adapter
-        .set_discovery_filter(DiscoveryFilter {
-            transport: DiscoveryTransport::Le,
-            pattern: Some("Flower care".into()),
-            ..Default::default()
-        })
-        .await?;
-
-    let device_events = adapter.discover_devices().await?;
-    pin_mut!(device_events);
 
-    while let Some(event) = device_events.next().await {
-        match event {
-            AdapterEvent::DeviceAdded(addr) => {
-                let Ok(device) = adapter.device(addr) else {
-                    tracing::error!(message = "device not found", address = %addr);
-                    continue;
-                };
-                let name = device.name().await?;
-                tracing::debug!(message = "device discovered", address = %addr, name = ?name);
-                if addresses.contains(&addr) {
-                    if let Err(err) = handle(adapter.clone(), addr).await {
-                        tracing::warn!(message = "something went wrong", address = %addr, error = %err);
-                    }
-                }
-            }
-            AdapterEvent::DeviceRemoved(addr) => {
-                tracing::debug!(message = "device disappeared", address = %addr);
-            }
-            _ => {}
+    let result = run(args).await;
+    #[cfg(feature = "opentelemetry")]
+    if let Some(telemetry) = telemetry {
+        let _ = tokio::task::spawn_blocking(move || telemetry.shutdown()).await;
+    }
+    match result {
+        Ok(code) => ExitCode::from(code),
+        Err(err) if err.is::<context::Usage>() => {
+            eprintln!("Error: {err}");
+            ExitCode::from(context::EXIT_USAGE)
+        }
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            ExitCode::from(context::EXIT_FAILURE)
         }
     }
-
-    Ok(())
 }
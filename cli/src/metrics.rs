@@ -0,0 +1,158 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+
+use bluer::Address;
+use bluer_miflora::Registry;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+
+use crate::record::{Reading, Source};
+use crate::sink::{Kind, Sink};
+
+/// Gauges of the last realtime reading of each device, in the Prometheus units.
+const GAUGES: [(&str, &str); 5] = [
+    (
+        "miflora_temperature_celsius",
+        "Temperature measured by the device.",
+    ),
+    ("miflora_moisture_percent", "Moisture of the soil."),
+    (
+        "miflora_conductivity_microsiemens_per_centimeter",
+        "Conductivity of the soil, the higher the more fertile.",
+    ),
+    (
+        "miflora_brightness_lux",
+        "Brightness measured by the device.",
+    ),
+    ("miflora_battery_percent", "Battery level of the device."),
+];
+
+#[derive(Debug, Default)]
+struct DeviceMetrics {
+    /// Values of the gauges, in the order of [`GAUGES`]
+    gauges: [Option<f64>; 5],
+    /// Number of collections by kind and result
+    collections: BTreeMap<(&'static str, &'static str), u64>,
+}
+
+/// Metrics of the devices polled by the daemon, exposed in the Prometheus text format.
+#[derive(Clone, Debug)]
+pub struct Metrics {
+    sources: Arc<Vec<Source>>,
+    devices: Arc<Mutex<BTreeMap<Address, DeviceMetrics>>>,
+}
+
+fn kind_label(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Realtime => "realtime",
+        Kind::History => "history",
+    }
+}
+
+impl Metrics {
+    pub fn new(sources: Vec<Source>) -> Self {
+        Self {
+            sources: Arc::new(sources),
+            devices: Default::default(),
+        }
+    }
+
+    /// Renders the metrics, with the signal of the devices as last seen by the registry.
+    pub fn render(&self, registry: &Registry) -> String {
+        let devices = self.devices.lock().expect("metrics lock poisoned");
+        let labels = |source: &Source| {
+            format!(
+                "address=\"{}\",alias=\"{}\"",
+                source.address,
+                source
+                    .alias
+                    .as_deref()
+                    .unwrap_or_default()
+                    .replace('"', "\\\"")
+            )
+        };
+        let mut output = String::new();
+        for (index, (name, help)) in GAUGES.iter().enumerate() {
+            let _ = writeln!(output, "# HELP {name} {help}\n# TYPE {name} gauge");
+            for source in self.sources.iter() {
+                let value = devices
+                    .get(&source.address)
+                    .and_then(|device| device.gauges[index]);
+                if let Some(value) = value {
+                    let _ = writeln!(output, "{name}{{{}}} {value}", labels(source));
+                }
+            }
+        }
+
+        let _ = writeln!(output, "# HELP miflora_last_seen_timestamp_seconds Last time the device was seen advertising.\n# TYPE miflora_last_seen_timestamp_seconds gauge");
+        for source in self.sources.iter() {
+            let last_seen = registry
+                .get(&source.address)
+                .and_then(|state| state.last_seen().duration_since(UNIX_EPOCH).ok());
+            if let Some(last_seen) = last_seen {
+                let _ = writeln!(
+                    output,
+                    "miflora_last_seen_timestamp_seconds{{{}}} {}",
+                    labels(source),
+                    last_seen.as_secs()
+                );
+            }
+        }
+
+        let _ = writeln!(output, "# HELP miflora_rssi_dbm Signal strength of the last advertisement of the device.\n# TYPE miflora_rssi_dbm gauge");
+        for source in self.sources.iter() {
+            let rssi = registry.get(&source.address).and_then(|state| state.rssi());
+            if let Some(rssi) = rssi {
+                let _ = writeln!(output, "miflora_rssi_dbm{{{}}} {rssi}", labels(source));
+            }
+        }
+
+        let _ = writeln!(output, "# HELP miflora_collections_total Number of polls of the device, by kind and result.\n# TYPE miflora_collections_total counter");
+        for source in self.sources.iter() {
+            let Some(device) = devices.get(&source.address) else {
+                continue;
+            };
+            for ((kind, result), count) in device.collections.iter() {
+                let _ = writeln!(
+                    output,
+                    "miflora_collections_total{{{},kind=\"{kind}\",result=\"{result}\"}} {count}",
+                    labels(source)
+                );
+            }
+        }
+        output
+    }
+}
+
+impl Sink for Metrics {
+    fn publish<'a>(
+        &'a self,
+        kind: Kind,
+        reading: &'a Reading,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        if kind == Kind::Realtime {
+            let mut devices = self.devices.lock().expect("metrics lock poisoned");
+            let device = devices.entry(reading.source.address).or_default();
+            device.gauges = [
+                Some(f64::from(reading.temperature)),
+                Some(f64::from(reading.moisture)),
+                Some(f64::from(reading.conductivity)),
+                reading.brightness.map(f64::from),
+                reading.battery.map(f64::from),
+            ];
+        }
+        futures::future::ready(Ok(())).boxed()
+    }
+
+    fn collected(&self, kind: Kind, source: &Source, success: bool) {
+        let result = if success { "success" } else { "failure" };
+        let mut devices = self.devices.lock().expect("metrics lock poisoned");
+        let device = devices.entry(source.address).or_default();
+        *device
+            .collections
+            .entry((kind_label(kind), result))
+            .or_default() += 1;
+    }
+}
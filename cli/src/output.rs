@@ -0,0 +1,110 @@
+use std::io::Write;
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+/// How the results of the commands are printed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// Logs readable by humans.
+    #[default]
+    Text,
+    /// Array of JSON objects, printed once the command is done.
+    Json,
+    /// Comma separated values, one line per record.
+    Csv,
+    /// One JSON object per line, printed as soon as available.
+    Ndjson,
+    /// Parquet file with typed columns, only for the history.
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+/// Result of a command, printed in the requested format.
+pub trait Record: Serialize {
+    /// Columns of the record in the CSV format.
+    const COLUMNS: &'static [&'static str];
+
+    /// Values of the columns, in the same order.
+    fn row(&self) -> Vec<String>;
+
+    /// Logs the record in the text format.
+    fn log(&self);
+}
+
+#[derive(Debug, Default)]
+struct State {
+    records: Vec<serde_json::Value>,
+    header_written: bool,
+    finished: bool,
+}
+
+/// Prints the records produced by the commands.
+#[derive(Debug)]
+pub struct Output {
+    format: Format,
+    header: bool,
+    state: Mutex<State>,
+}
+
+impl Output {
+    pub fn new(format: Format) -> Self {
+        Self {
+            format,
+            header: true,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Prints the names of the columns before the first CSV record.
+    pub fn with_header(mut self, value: bool) -> Self {
+        self.header = value;
+        self
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    pub fn write<R: Record>(&self, record: &R) -> anyhow::Result<()> {
+        let mut state = self.state.lock().expect("output lock poisoned");
+        match self.format {
+            Format::Text => record.log(),
+            Format::Json => state.records.push(serde_json::to_value(record)?),
+            Format::Csv => {
+                let mut writer = csv::Writer::from_writer(std::io::stdout().lock());
+                if self.header && !state.header_written {
+                    writer.write_record(R::COLUMNS)?;
+                    state.header_written = true;
+                }
+                writer.write_record(record.row())?;
+                writer.flush()?;
+            }
+            Format::Ndjson => {
+                let mut stdout = std::io::stdout().lock();
+                serde_json::to_writer(&mut stdout, record)?;
+                writeln!(stdout)?;
+                stdout.flush()?;
+            }
+            #[cfg(feature = "parquet")]
+            Format::Parquet => anyhow::bail!("the parquet format is only supported by the history"),
+        }
+        Ok(())
+    }
+
+    /// Prints the records kept until the end of the command, only once when called again.
+    pub fn finish(&self) -> anyhow::Result<()> {
+        let mut state = self.state.lock().expect("output lock poisoned");
+        if std::mem::replace(&mut state.finished, true) {
+            return Ok(());
+        }
+        if self.format == Format::Json {
+            let records = std::mem::take(&mut state.records);
+            let mut stdout = std::io::stdout().lock();
+            serde_json::to_writer_pretty(&mut stdout, &records)?;
+            writeln!(stdout)?;
+            stdout.flush()?;
+        }
+        Ok(())
+    }
+}
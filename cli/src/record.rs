@@ -0,0 +1,258 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bluer::Address;
+use bluer_miflora::{BatteryState, BatteryThresholds, SensorReading, System};
+use serde::{Serialize, Serializer};
+
+use crate::output::Record;
+
+/// Unix timestamp of the current time, in seconds.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|value| value.as_secs())
+        .unwrap_or_default()
+}
+
+fn optional<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map(T::to_string).unwrap_or_default()
+}
+
+pub fn display<T: std::fmt::Display, S: Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+/// Device a record comes from, with its alias from the configuration.
+#[derive(Clone, Debug, Serialize)]
+pub struct Source {
+    pub address: Address,
+    pub alias: Option<String>,
+}
+
+impl Source {
+    fn row(&self) -> [String; 2] {
+        [self.address.to_string(), optional(&self.alias)]
+    }
+}
+
+/// Device found by the scan.
+#[derive(Debug, Serialize)]
+pub struct Device {
+    #[serde(flatten)]
+    pub source: Source,
+    pub name: Option<String>,
+    pub rssi: Option<i16>,
+    pub encrypted: Option<bool>,
+}
+
+impl Record for Device {
+    const COLUMNS: &'static [&'static str] = &["address", "alias", "name", "rssi", "encrypted"];
+
+    fn row(&self) -> Vec<String> {
+        let mut row = self.source.row().to_vec();
+        row.extend([
+            optional(&self.name),
+            optional(&self.rssi),
+            optional(&self.encrypted),
+        ]);
+        row
+    }
+
+    fn log(&self) {
+        tracing::info!(
+            message = "device found",
+            address = %self.source.address,
+            alias = self.source.alias,
+            name = ?self.name,
+            rssi = ?self.rssi,
+            encrypted = ?self.encrypted,
+        );
+    }
+}
+
+/// Battery level and firmware version of a device.
+#[derive(Debug, Serialize)]
+pub struct SystemInfo {
+    #[serde(flatten)]
+    pub source: Source,
+    pub battery: u8,
+    pub firmware: String,
+}
+
+impl SystemInfo {
+    pub fn new(source: Source, system: &System) -> Self {
+        Self {
+            source,
+            battery: system.battery(),
+            firmware: system.firmware().into_owned(),
+        }
+    }
+}
+
+impl Record for SystemInfo {
+    const COLUMNS: &'static [&'static str] = &["address", "alias", "battery", "firmware"];
+
+    fn row(&self) -> Vec<String> {
+        let mut row = self.source.row().to_vec();
+        row.extend([self.battery.to_string(), self.firmware.clone()]);
+        row
+    }
+
+    fn log(&self) {
+        tracing::info!(
+            message = "system information",
+            battery = self.battery,
+            firmware = %self.firmware,
+        );
+    }
+}
+
+/// Battery level of a device, with its state according to the thresholds.
+#[derive(Debug, Serialize)]
+pub struct Battery {
+    #[serde(flatten)]
+    pub source: Source,
+    pub battery: u8,
+    #[serde(serialize_with = "display")]
+    pub state: BatteryState,
+    pub firmware: String,
+}
+
+impl Battery {
+    pub fn new(source: Source, system: &System, thresholds: &BatteryThresholds) -> Self {
+        Self {
+            source,
+            battery: system.battery(),
+            state: system.battery_state_with(thresholds),
+            firmware: system.firmware().into_owned(),
+        }
+    }
+}
+
+impl Record for Battery {
+    const COLUMNS: &'static [&'static str] = &["address", "alias", "battery", "state", "firmware"];
+
+    fn row(&self) -> Vec<String> {
+        let mut row = self.source.row().to_vec();
+        row.extend([
+            self.battery.to_string(),
+            self.state.to_string(),
+            self.firmware.clone(),
+        ]);
+        row
+    }
+
+    fn log(&self) {
+        tracing::info!(
+            message = "battery",
+            battery = self.battery,
+            state = %self.state,
+            firmware = %self.firmware,
+        );
+    }
+}
+
+/// Values measured by a device, with its system information when read together.
+#[derive(Clone, Debug, Serialize)]
+pub struct Reading {
+    #[serde(flatten)]
+    pub source: Source,
+    /// Unix timestamp of the measure, in seconds.
+    pub timestamp: u64,
+    pub temperature: f32,
+    pub brightness: Option<u32>,
+    pub moisture: u8,
+    pub conductivity: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub battery: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub firmware: Option<String>,
+}
+
+impl Reading {
+    /// Reading of a realtime or historical entry, the realtime ones being timestamped now.
+    pub fn new<R: SensorReading>(source: Source, entry: &R) -> Self {
+        Self {
+            source,
+            timestamp: entry.timestamp().unwrap_or_else(now),
+            temperature: entry.temperature_celsius(),
+            brightness: entry.brightness(),
+            moisture: entry.moisture(),
+            conductivity: entry.conductivity(),
+            battery: None,
+            firmware: None,
+        }
+    }
+
+    pub fn with_system(mut self, system: &System) -> Self {
+        self.battery = Some(system.battery());
+        self.firmware = Some(system.firmware().into_owned());
+        self
+    }
+}
+
+impl Record for Reading {
+    const COLUMNS: &'static [&'static str] = &[
+        "address",
+        "alias",
+        "timestamp",
+        "temperature",
+        "brightness",
+        "moisture",
+        "conductivity",
+        "battery",
+        "firmware",
+    ];
+
+    fn row(&self) -> Vec<String> {
+        let mut row = self.source.row().to_vec();
+        row.extend([
+            self.timestamp.to_string(),
+            self.temperature.to_string(),
+            optional(&self.brightness),
+            self.moisture.to_string(),
+            self.conductivity.to_string(),
+            optional(&self.battery),
+            optional(&self.firmware),
+        ]);
+        row
+    }
+
+    fn log(&self) {
+        tracing::info!(
+            message = "values",
+            timestamp = self.timestamp,
+            temperature = self.temperature,
+            brightness = ?self.brightness,
+            moisture = self.moisture,
+            conductivity = self.conductivity,
+            battery = ?self.battery,
+            firmware = ?self.firmware,
+        );
+    }
+}
+
+/// Action performed on a device.
+#[derive(Debug, Serialize)]
+pub struct Action {
+    #[serde(flatten)]
+    pub source: Source,
+    pub action: &'static str,
+}
+
+impl Record for Action {
+    const COLUMNS: &'static [&'static str] = &["address", "alias", "action"];
+
+    fn row(&self) -> Vec<String> {
+        let mut row = self.source.row().to_vec();
+        row.push(self.action.to_string());
+        row
+    }
+
+    fn log(&self) {
+        tracing::info!(message = self.action);
+    }
+}
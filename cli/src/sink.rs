@@ -0,0 +1,157 @@
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+
+use crate::config::Config;
+use crate::record::{Reading, Source};
+use crate::state::State;
+
+#[cfg(feature = "dbus")]
+mod dbus;
+mod influxdb;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+#[cfg(feature = "nats")]
+mod nats;
+#[cfg(feature = "parquet")]
+mod parquet;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+/// Kind of the published reading.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Realtime,
+    History,
+}
+
+/// Destination of the readings collected by the daemon.
+pub trait Sink: Send + Sync {
+    /// Describes the devices to the sink, before any reading is published.
+    fn announce<'a>(&'a self, _sources: &'a [Source]) -> BoxFuture<'a, anyhow::Result<()>> {
+        futures::future::ready(Ok(())).boxed()
+    }
+
+    fn publish<'a>(&'a self, kind: Kind, reading: &'a Reading)
+        -> BoxFuture<'a, anyhow::Result<()>>;
+
+    /// Notifies the sink whether polling the device succeeded.
+    fn collected(&self, _kind: Kind, _source: &Source, _success: bool) {}
+
+    /// Sends the readings still buffered, before shutting down.
+    fn close(&self) -> BoxFuture<'_, anyhow::Result<()>> {
+        futures::future::ready(Ok(())).boxed()
+    }
+}
+
+/// Sinks enabled in the configuration.
+#[derive(Default)]
+pub struct Sinks {
+    inner: Vec<Box<dyn Sink>>,
+}
+
+impl Sinks {
+    pub fn from_config(config: &Config, state: &Arc<State>) -> anyhow::Result<Self> {
+        let mut sinks = Self::default();
+        #[cfg(feature = "mqtt")]
+        if let Some(ref mqtt) = config.mqtt {
+            sinks.inner.push(Box::new(mqtt::MqttSink::new(mqtt)?));
+        }
+        #[cfg(not(feature = "mqtt"))]
+        anyhow::ensure!(
+            config.mqtt.is_none(),
+            "the mqtt sink requires the mqtt feature"
+        );
+        #[cfg(feature = "nats")]
+        if let Some(ref nats) = config.nats {
+            sinks.inner.push(Box::new(nats::NatsSink::new(nats)));
+        }
+        #[cfg(not(feature = "nats"))]
+        anyhow::ensure!(
+            config.nats.is_none(),
+            "the nats sink requires the nats feature"
+        );
+        if let Some(ref influxdb) = config.influxdb {
+            sinks
+                .inner
+                .push(Box::new(influxdb::InfluxDbSink::new(influxdb)?));
+        }
+        #[cfg(feature = "dbus")]
+        if let Some(ref dbus) = config.dbus {
+            sinks.inner.push(Box::new(dbus::DbusSink::new(dbus)));
+        }
+        #[cfg(not(feature = "dbus"))]
+        anyhow::ensure!(
+            config.dbus.is_none(),
+            "the dbus sink requires the dbus feature"
+        );
+        if let Some(alerting) = crate::alert::Alerting::from_config(config, state.clone())? {
+            sinks.inner.push(Box::new(alerting));
+        }
+        #[cfg(feature = "parquet")]
+        if let Some(ref parquet) = config.parquet {
+            sinks
+                .inner
+                .push(Box::new(parquet::ParquetSink::new(parquet)));
+        }
+        #[cfg(not(feature = "parquet"))]
+        anyhow::ensure!(
+            config.parquet.is_none(),
+            "the parquet sink requires the parquet feature"
+        );
+        #[cfg(feature = "sqlite")]
+        if let Some(ref sqlite) = config.sqlite {
+            sinks
+                .inner
+                .push(Box::new(sqlite::SqliteSink::open(&sqlite.path)?));
+        }
+        #[cfg(not(feature = "sqlite"))]
+        anyhow::ensure!(
+            config.sqlite.is_none(),
+            "the sqlite sink requires the sqlite feature"
+        );
+        Ok(sinks)
+    }
+
+    /// Adds a sink of a command, like the live readings of the exporter.
+    #[cfg(any(feature = "exporter", feature = "grpc"))]
+    pub fn with_sink<S: Sink + 'static>(mut self, sink: S) -> Self {
+        self.inner.push(Box::new(sink));
+        self
+    }
+
+    pub async fn announce(&self, sources: &[Source]) {
+        for sink in self.inner.iter() {
+            if let Err(err) = sink.announce(sources).await {
+                tracing::warn!(message = "unable to announce devices", error = %err);
+            }
+        }
+    }
+
+    pub fn collected(&self, kind: Kind, source: &Source, success: bool) {
+        for sink in self.inner.iter() {
+            sink.collected(kind, source, success);
+        }
+    }
+
+    /// Flushes all the sinks, before shutting down.
+    pub async fn close(&self) {
+        for sink in self.inner.iter() {
+            if let Err(err) = sink.close().await {
+                tracing::warn!(message = "unable to close sink", error = %err);
+            }
+        }
+    }
+
+    /// Publishes the reading to all the sinks, a failing sink not preventing the others
+    /// from receiving it.
+    pub async fn publish(&self, kind: Kind, reading: &Reading) {
+        for sink in self.inner.iter() {
+            if let Err(err) = sink.publish(kind, reading).await {
+                tracing::warn!(message = "unable to publish reading", error = %err);
+            }
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use bluer::Address;
+use dbus::arg::{PropMap, RefArg, Variant};
+use dbus::channel::{MatchingReceiver, Sender};
+use dbus::message::MatchRule;
+use dbus::nonblock::stdintf::org_freedesktop_dbus::RequestNameReply;
+use dbus::nonblock::SyncConnection;
+use dbus::Message;
+use dbus_crossroads::{Crossroads, IfaceBuilder};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use tokio::sync::OnceCell;
+use tokio::task::JoinHandle;
+
+use super::{Kind, Sink};
+use crate::config::{Bus, DbusConfig};
+use crate::record::{Reading, Source};
+
+/// Name owned by the daemon on the bus.
+const SERVICE_NAME: &str = "org.bluer_miflora.Collector";
+/// Path of the object manager, the devices being its children.
+const ROOT_PATH: &str = "/org/bluer_miflora/Collector";
+const DEVICE_INTERFACE: &str = "org.bluer_miflora.Device1";
+
+/// Path of the object of a device, named like the ones of BlueZ.
+fn device_path(address: Address) -> dbus::Path<'static> {
+    format!("{ROOT_PATH}/dev_{}", address.to_string().replace(':', "_")).into()
+}
+
+/// Values of the `ReadingChanged` signal, the missing values being left out.
+fn reading_properties(reading: &Reading) -> PropMap {
+    let mut properties: PropMap = HashMap::new();
+    let mut insert = |name: &str, value: Box<dyn RefArg>| {
+        properties.insert(name.into(), Variant(value));
+    };
+    insert("Timestamp", Box::new(reading.timestamp));
+    insert("Temperature", Box::new(f64::from(reading.temperature)));
+    insert("Moisture", Box::new(reading.moisture));
+    insert("Conductivity", Box::new(reading.conductivity));
+    if let Some(brightness) = reading.brightness {
+        insert("Brightness", Box::new(brightness));
+    }
+    if let Some(battery) = reading.battery {
+        insert("Battery", Box::new(battery));
+    }
+    properties
+}
+
+/// Data of the object of a device, its last reading being shared with the sink.
+struct DeviceObject {
+    source: Source,
+    last: Arc<Mutex<Option<Reading>>>,
+}
+
+impl DeviceObject {
+    /// Value of the last reading, zero until the device is read.
+    fn value<A: Default, F: FnOnce(&Reading) -> A>(&self, func: F) -> A {
+        self.last
+            .lock()
+            .expect("device object poisoned")
+            .as_ref()
+            .map(func)
+            .unwrap_or_default()
+    }
+}
+
+fn register_device(crossroads: &mut Crossroads) -> dbus_crossroads::IfaceToken<DeviceObject> {
+    crossroads.register(
+        DEVICE_INTERFACE,
+        |builder: &mut IfaceBuilder<DeviceObject>| {
+            builder
+                .property("Address")
+                .get(|_, device| Ok(device.source.address.to_string()))
+                .emits_changed_const();
+            builder
+                .property("Alias")
+                .get(|_, device| Ok(device.source.alias.clone().unwrap_or_default()))
+                .emits_changed_const();
+            // changes are notified with the ReadingChanged signal instead
+            builder
+                .property("Timestamp")
+                .get(|_, device| Ok(device.value(|reading| reading.timestamp)))
+                .emits_changed_false();
+            builder
+                .property("Temperature")
+                .get(|_, device| Ok(device.value(|reading| f64::from(reading.temperature))))
+                .emits_changed_false();
+            builder
+                .property("Moisture")
+                .get(|_, device| Ok(device.value(|reading| reading.moisture)))
+                .emits_changed_false();
+            builder
+                .property("Conductivity")
+                .get(|_, device| Ok(device.value(|reading| reading.conductivity)))
+                .emits_changed_false();
+            builder
+                .property("Brightness")
+                .get(|_, device| Ok(device.value(|reading| reading.brightness.unwrap_or_default())))
+                .emits_changed_false();
+            builder
+                .property("Battery")
+                .get(|_, device| Ok(device.value(|reading| reading.battery.unwrap_or_default())))
+                .emits_changed_false();
+            builder.signal::<(PropMap,), _>("ReadingChanged", ("reading",));
+        },
+    )
+}
+
+/// Service registered once the devices are announced.
+struct Service {
+    connection: Arc<SyncConnection>,
+    devices: BTreeMap<Address, Arc<Mutex<Option<Reading>>>>,
+    tasks: [JoinHandle<()>; 2],
+}
+
+/// Exposes the devices and their last realtime reading as a D-Bus service, signaling each
+/// new reading.
+pub struct DbusSink {
+    bus: Bus,
+    service: OnceCell<Service>,
+}
+
+impl DbusSink {
+    pub fn new(config: &DbusConfig) -> Self {
+        Self {
+            bus: config.bus,
+            service: OnceCell::new(),
+        }
+    }
+
+    async fn register(&self, sources: &[Source]) -> anyhow::Result<Service> {
+        let connect = match self.bus {
+            Bus::System => dbus_tokio::connection::new_system_sync,
+            Bus::Session => dbus_tokio::connection::new_session_sync,
+        };
+        let (resource, connection) = tokio::task::spawn_blocking(connect).await??;
+        let connection_task = tokio::spawn(async move {
+            let err = resource.await;
+            tracing::warn!(message = "lost connection to the message bus", error = %err);
+        });
+        let reply = connection
+            .request_name(SERVICE_NAME, false, true, true)
+            .await
+            .map_err(|err| anyhow::anyhow!("unable to own {SERVICE_NAME}: {err}"))?;
+        anyhow::ensure!(
+            reply == RequestNameReply::PrimaryOwner,
+            "unable to own {SERVICE_NAME}, already owned by another service"
+        );
+
+        let mut crossroads = Crossroads::new();
+        crossroads.set_object_manager_support(Some(connection.clone()));
+        let token = register_device(&mut crossroads);
+        crossroads.insert(ROOT_PATH, &[crossroads.object_manager::<()>()], ());
+        let mut devices = BTreeMap::new();
+        for source in sources {
+            let last = Arc::new(Mutex::new(None));
+            devices.insert(source.address, last.clone());
+            crossroads.insert(
+                device_path(source.address),
+                &[token],
+                DeviceObject {
+                    source: source.clone(),
+                    last,
+                },
+            );
+        }
+
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        connection.start_receive(
+            MatchRule::new_method_call(),
+            Box::new(move |message, _| sender.send(message).is_ok()),
+        );
+        let replies = connection.clone();
+        let handler_task = tokio::spawn(async move {
+            while let Some(message) = receiver.recv().await {
+                let _ = crossroads.handle_message(message, &*replies);
+            }
+        });
+        tracing::info!(message = "registered dbus service", name = SERVICE_NAME);
+        Ok(Service {
+            connection,
+            devices,
+            tasks: [connection_task, handler_task],
+        })
+    }
+}
+
+impl Sink for DbusSink {
+    fn announce<'a>(&'a self, sources: &'a [Source]) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            self.service
+                .get_or_try_init(|| self.register(sources))
+                .await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn publish<'a>(
+        &'a self,
+        kind: Kind,
+        reading: &'a Reading,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        let result = (|| {
+            let Some(service) = self.service.get() else {
+                return Ok(());
+            };
+            let Some(last) = service.devices.get(&reading.source.address) else {
+                return Ok(());
+            };
+            // the history entries are past values
+            if kind != Kind::Realtime {
+                return Ok(());
+            }
+            *last.lock().expect("device object poisoned") = Some(reading.clone());
+            let signal = Message::signal(
+                &device_path(reading.source.address),
+                &DEVICE_INTERFACE.into(),
+                &"ReadingChanged".into(),
+            )
+            .append1(reading_properties(reading));
+            service
+                .connection
+                .send(signal)
+                .map(|_| ())
+                .map_err(|_| anyhow::anyhow!("unable to send the ReadingChanged signal"))
+        })();
+        futures::future::ready(result).boxed()
+    }
+
+    fn close(&self) -> BoxFuture<'_, anyhow::Result<()>> {
+        if let Some(service) = self.service.get() {
+            service.tasks.iter().for_each(JoinHandle::abort);
+        }
+        futures::future::ready(Ok(())).boxed()
+    }
+}
@@ -0,0 +1,238 @@
+use std::fmt::Write;
+
+use bluer_miflora::RetryPolicy;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use reqwest::Client;
+use tokio::sync::{mpsc, oneshot};
+
+use super::{Kind, Sink};
+use crate::config::InfluxDbConfig;
+use crate::record::Reading;
+
+/// Writes the readings to an InfluxDB 2 bucket, in batches.
+pub struct InfluxDbSink {
+    measurement: String,
+    sender: mpsc::UnboundedSender<Message>,
+}
+
+enum Message {
+    Line(String),
+    /// Sends the pending lines right away, notifying once done.
+    Flush(oneshot::Sender<()>),
+}
+
+/// Escapes the commas, spaces and equal signs of a tag value of the line protocol.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace('=', "\\=")
+        .replace(' ', "\\ ")
+}
+
+/// Renders the reading in the line protocol, with a timestamp in seconds.
+fn line(measurement: &str, kind: Kind, reading: &Reading) -> String {
+    let mut line = format!(
+        "{},address={}",
+        escape(measurement),
+        escape(&reading.source.address.to_string())
+    );
+    if let Some(ref alias) = reading.source.alias {
+        let _ = write!(line, ",alias={}", escape(alias));
+    }
+    let kind = match kind {
+        Kind::Realtime => "realtime",
+        Kind::History => "history",
+    };
+    let _ = write!(
+        line,
+        ",kind={kind} temperature={},moisture={}i,conductivity={}i",
+        reading.temperature, reading.moisture, reading.conductivity
+    );
+    if let Some(brightness) = reading.brightness {
+        let _ = write!(line, ",brightness={brightness}i");
+    }
+    if let Some(battery) = reading.battery {
+        let _ = write!(line, ",battery={battery}i");
+    }
+    let _ = write!(line, " {}", reading.timestamp);
+    line
+}
+
+/// Sends the lines to the server, when the batch is full or the flush interval elapsed.
+struct Writer {
+    client: Client,
+    url: String,
+    token: String,
+    retry_policy: RetryPolicy,
+}
+
+impl Writer {
+    async fn run(self, config: Batching, mut receiver: mpsc::UnboundedReceiver<Message>) {
+        let mut batch = Vec::with_capacity(config.size);
+        let mut interval = tokio::time::interval(config.interval);
+        loop {
+            tokio::select! {
+                message = receiver.recv() => match message {
+                    Some(Message::Line(line)) => {
+                        batch.push(line);
+                        if batch.len() < config.size {
+                            continue;
+                        }
+                    }
+                    Some(Message::Flush(done)) => {
+                        self.flush(&mut batch).await;
+                        let _ = done.send(());
+                        continue;
+                    }
+                    None => {
+                        self.flush(&mut batch).await;
+                        return;
+                    }
+                },
+                _ = interval.tick() => {}
+            }
+            self.flush(&mut batch).await;
+        }
+    }
+
+    async fn flush(&self, batch: &mut Vec<String>) {
+        if batch.is_empty() {
+            return;
+        }
+        let body = batch.join("\n");
+        let mut attempts = 0;
+        loop {
+            match self.send(body.clone()).await {
+                Ok(()) => break,
+                Err(err) if err.retryable && attempts < self.retry_policy.max_retries() => {
+                    attempts += 1;
+                    let delay = self.retry_policy.delay(attempts);
+                    tracing::debug!(message = "influxdb write failed", tries = attempts, delay = ?delay, error = %err.cause);
+                    tokio::time::sleep(delay).await;
+                }
+                Err(err) => {
+                    tracing::warn!(message = "unable to write to influxdb", lines = batch.len(), error = %err.cause);
+                    break;
+                }
+            }
+        }
+        batch.clear();
+    }
+
+    async fn send(&self, body: String) -> Result<(), SendError> {
+        let response = self
+            .client
+            .post(&self.url)
+            .header("Authorization", format!("Token {}", self.token))
+            .header("Content-Type", "text/plain; charset=utf-8")
+            .body(body)
+            .send()
+            .await
+            .map_err(|err| SendError {
+                retryable: true,
+                cause: err.into(),
+            })?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let message = response.text().await.unwrap_or_default();
+        Err(SendError {
+            retryable: status.is_server_error(),
+            cause: anyhow::anyhow!("influxdb responded with {status}: {message}"),
+        })
+    }
+}
+
+struct SendError {
+    retryable: bool,
+    cause: anyhow::Error,
+}
+
+struct Batching {
+    size: usize,
+    interval: std::time::Duration,
+}
+
+impl InfluxDbSink {
+    pub fn new(config: &InfluxDbConfig) -> anyhow::Result<Self> {
+        let mut url = reqwest::Url::parse(&config.url)?.join("api/v2/write")?;
+        url.query_pairs_mut()
+            .append_pair("org", &config.org)
+            .append_pair("bucket", &config.bucket)
+            .append_pair("precision", "s");
+        let writer = Writer {
+            client: Client::new(),
+            url: url.to_string(),
+            token: config.token.clone(),
+            retry_policy: RetryPolicy::default(),
+        };
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let batching = Batching {
+            size: config.batch_size.max(1),
+            interval: config.flush_interval,
+        };
+        tokio::spawn(writer.run(batching, receiver));
+        Ok(Self {
+            measurement: config.measurement.clone(),
+            sender,
+        })
+    }
+}
+
+impl Sink for InfluxDbSink {
+    fn publish<'a>(
+        &'a self,
+        kind: Kind,
+        reading: &'a Reading,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        let result = self
+            .sender
+            .send(Message::Line(line(&self.measurement, kind, reading)))
+            .map_err(|_| anyhow::anyhow!("influxdb writer stopped"));
+        futures::future::ready(result).boxed()
+    }
+
+    fn close(&self) -> BoxFuture<'_, anyhow::Result<()>> {
+        async move {
+            let (done, flushed) = oneshot::channel();
+            self.sender
+                .send(Message::Flush(done))
+                .map_err(|_| anyhow::anyhow!("influxdb writer stopped"))?;
+            flushed
+                .await
+                .map_err(|_| anyhow::anyhow!("influxdb writer stopped"))
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::line;
+    use crate::record::{Reading, Source};
+    use crate::sink::Kind;
+
+    #[test]
+    fn should_render_line_protocol() {
+        let reading = Reading {
+            source: Source {
+                address: "C4:7C:8D:6A:3E:1F".parse().unwrap(),
+                alias: Some("basil kitchen".into()),
+            },
+            timestamp: 1_700_000_000,
+            temperature: 21.5,
+            brightness: Some(300),
+            moisture: 25,
+            conductivity: 150,
+            battery: Some(90),
+            firmware: None,
+        };
+        assert_eq!(
+            line("miflora", Kind::Realtime, &reading),
+            "miflora,address=C4:7C:8D:6A:3E:1F,alias=basil\\ kitchen,kind=realtime temperature=21.5,moisture=25i,conductivity=150i,brightness=300i,battery=90i 1700000000"
+        );
+    }
+}
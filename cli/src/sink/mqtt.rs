@@ -0,0 +1,251 @@
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use rumqttc::{AsyncClient, ClientError, EventLoop, MqttOptions, QoS, TlsConfiguration, Transport};
+use serde_json::json;
+
+use super::{Kind, Sink};
+use crate::config::MqttConfig;
+use crate::record::{Reading, Source};
+
+/// Publishes the readings as JSON to an MQTT broker.
+pub struct MqttSink {
+    client: AsyncClient,
+    qos: QoS,
+    retain: bool,
+    state_topic: String,
+    history_topic: String,
+    discovery_prefix: Option<String>,
+}
+
+/// Messages queued until the broker is reachable, like the discovery messages sent on
+/// startup, the next ones being dropped so the polling of the devices and the other sinks
+/// never wait for the broker.
+const QUEUE_CAPACITY: usize = 1024;
+
+/// Sensors announced to Home Assistant: field of the reading, name, device class and unit.
+const SENSORS: [(&str, &str, &str, &str); 5] = [
+    ("temperature", "Temperature", "temperature", "°C"),
+    ("moisture", "Moisture", "moisture", "%"),
+    ("conductivity", "Conductivity", "conductivity", "µS/cm"),
+    ("brightness", "Illuminance", "illuminance", "lx"),
+    ("battery", "Battery", "battery", "%"),
+];
+
+fn qos(value: u8) -> anyhow::Result<QoS> {
+    match value {
+        0 => Ok(QoS::AtMostOnce),
+        1 => Ok(QoS::AtLeastOnce),
+        2 => Ok(QoS::ExactlyOnce),
+        other => Err(anyhow::anyhow!(
+            "invalid mqtt qos {other}, expected 0, 1 or 2"
+        )),
+    }
+}
+
+/// Makes the value usable as a single level of a topic, the slashes separating the levels.
+fn topic_level(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '/' | '+' | '#' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Replaces the placeholders of the topic with the values of the device.
+fn topic_for(template: &str, source: &Source) -> String {
+    let address = source.address.to_string();
+    template
+        .replace(
+            "{alias}",
+            &topic_level(source.alias.as_deref().unwrap_or(&address)),
+        )
+        .replace("{address}", &address)
+}
+
+/// Home Assistant discovery messages of the sensors of the device, with their topics.
+fn discovery_messages(
+    prefix: &str,
+    state_topic: &str,
+    source: &Source,
+) -> Vec<(String, serde_json::Value)> {
+    let id = format!(
+        "miflora_{}",
+        source.address.to_string().replace(':', "").to_lowercase()
+    );
+    let name = source
+        .alias
+        .clone()
+        .unwrap_or_else(|| source.address.to_string());
+    SENSORS
+        .iter()
+        .map(|(field, label, class, unit)| {
+            let topic = format!("{prefix}/sensor/{id}/{field}/config");
+            let payload = json!({
+                "name": label,
+                "unique_id": format!("{id}_{field}"),
+                "object_id": format!("{name}_{field}"),
+                "state_topic": topic_for(state_topic, source),
+                "value_template": format!("{{{{ value_json.{field} }}}}"),
+                "device_class": class,
+                "unit_of_measurement": unit,
+                "state_class": "measurement",
+                "device": {
+                    "identifiers": [id],
+                    "connections": [["mac", source.address.to_string()]],
+                    "name": name,
+                    "manufacturer": "Xiaomi",
+                },
+            });
+            (topic, payload)
+        })
+        .collect()
+}
+
+/// Reports the messages dropped because the queue is full.
+fn queued(result: Result<(), ClientError>) -> anyhow::Result<()> {
+    match result {
+        Ok(()) => Ok(()),
+        Err(ClientError::TryRequest(_)) => {
+            anyhow::bail!("mqtt queue full, the broker being unreachable, message dropped")
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Handles the connection to the broker, reconnecting when it's lost.
+async fn run(mut eventloop: EventLoop) {
+    loop {
+        if let Err(err) = eventloop.poll().await {
+            tracing::warn!(message = "mqtt connection failed", error = %err);
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+    }
+}
+
+impl MqttSink {
+    pub fn new(config: &MqttConfig) -> anyhow::Result<Self> {
+        let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let Some(ref username) = config.username {
+            options.set_credentials(username, config.password.as_deref().unwrap_or_default());
+        }
+        if let Some(ref path) = config.ca_file {
+            options.set_transport(Transport::tls_with_config(TlsConfiguration::Simple {
+                ca: std::fs::read(path)?,
+                alpn: None,
+                client_auth: None,
+            }));
+        } else if config.tls {
+            options.set_transport(Transport::tls_with_default_config());
+        }
+        let (client, eventloop) = AsyncClient::new(options, QUEUE_CAPACITY);
+        tokio::spawn(run(eventloop));
+        Ok(Self {
+            client,
+            qos: qos(config.qos)?,
+            retain: config.retain,
+            state_topic: config.state_topic.clone(),
+            history_topic: config.history_topic.clone(),
+            discovery_prefix: config.discovery.then(|| config.discovery_prefix.clone()),
+        })
+    }
+}
+
+impl Sink for MqttSink {
+    fn announce<'a>(&'a self, sources: &'a [Source]) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            let Some(ref prefix) = self.discovery_prefix else {
+                return Ok(());
+            };
+            for source in sources {
+                for (topic, payload) in discovery_messages(prefix, &self.state_topic, source) {
+                    // retained so Home Assistant finds the sensors when it restarts
+                    queued(self.client.try_publish(
+                        topic,
+                        self.qos,
+                        true,
+                        serde_json::to_vec(&payload)?,
+                    ))?;
+                }
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn publish<'a>(
+        &'a self,
+        kind: Kind,
+        reading: &'a Reading,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            let template = match kind {
+                Kind::Realtime => &self.state_topic,
+                Kind::History => &self.history_topic,
+            };
+            let payload = serde_json::to_vec(reading)?;
+            queued(self.client.try_publish(
+                topic_for(template, &reading.source),
+                self.qos,
+                self.retain,
+                payload,
+            ))
+        }
+        .boxed()
+    }
+
+    fn close(&self) -> BoxFuture<'_, anyhow::Result<()>> {
+        futures::future::ready(queued(self.client.try_disconnect())).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{discovery_messages, topic_for};
+    use crate::record::Source;
+
+    #[test]
+    fn should_fill_topic_placeholders() {
+        let mut source = Source {
+            address: "C4:7C:8D:6A:3E:1F".parse().unwrap(),
+            alias: None,
+        };
+        assert_eq!(
+            topic_for("miflora/{alias}/state", &source),
+            "miflora/C4:7C:8D:6A:3E:1F/state"
+        );
+        source.alias = Some("basil-kitchen".into());
+        assert_eq!(
+            topic_for("miflora/{alias}/{address}", &source),
+            "miflora/basil-kitchen/C4:7C:8D:6A:3E:1F"
+        );
+        // the wildcards and separators of the alias would change the subscriptions
+        source.alias = Some("kitchen/basil #2+".into());
+        assert_eq!(
+            topic_for("miflora/{alias}/state", &source),
+            "miflora/kitchen_basil _2_/state"
+        );
+    }
+
+    #[test]
+    fn should_describe_sensors_to_home_assistant() {
+        let source = Source {
+            address: "C4:7C:8D:6A:3E:1F".parse().unwrap(),
+            alias: Some("basil-kitchen".into()),
+        };
+        let messages = discovery_messages("homeassistant", "miflora/{alias}/state", &source);
+        assert_eq!(messages.len(), 5);
+        let (topic, payload) = &messages[0];
+        assert_eq!(
+            topic,
+            "homeassistant/sensor/miflora_c47c8d6a3e1f/temperature/config"
+        );
+        assert_eq!(payload["state_topic"], "miflora/basil-kitchen/state");
+        assert_eq!(payload["value_template"], "{{ value_json.temperature }}");
+        assert_eq!(payload["device"]["name"], "basil-kitchen");
+    }
+}
@@ -0,0 +1,171 @@
+use async_nats::{jetstream, Client, ConnectOptions};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use tokio::sync::OnceCell;
+
+use super::{Kind, Sink};
+use crate::config::NatsConfig;
+use crate::record::{Reading, Source};
+
+/// Publishes the readings as JSON to a NATS server, on a subject per device.
+pub struct NatsSink {
+    config: NatsConfig,
+    /// Established on first use, the configuration being loaded outside of the runtime
+    connection: OnceCell<Connection>,
+}
+
+struct Connection {
+    client: Client,
+    /// Set when the readings are persisted in a stream
+    jetstream: Option<jetstream::Context>,
+}
+
+/// Makes the value usable as a single token of a subject, the dots separating the tokens.
+fn subject_token(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '.' | '*' | '>' => '_',
+            c if c.is_whitespace() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Replaces the placeholders of the subject with the values of the device.
+fn subject_for(template: &str, source: &Source) -> String {
+    let address = source.address.to_string();
+    template
+        .replace(
+            "{alias}",
+            &subject_token(source.alias.as_deref().unwrap_or(&address)),
+        )
+        .replace("{address}", &address)
+}
+
+/// Subject matching the ones of all the devices, captured by the stream.
+fn wildcard_subject(template: &str) -> String {
+    template.replace("{alias}", "*").replace("{address}", "*")
+}
+
+impl NatsSink {
+    pub fn new(config: &NatsConfig) -> Self {
+        Self {
+            config: config.clone(),
+            connection: OnceCell::new(),
+        }
+    }
+
+    async fn connection(&self) -> anyhow::Result<&Connection> {
+        self.connection
+            .get_or_try_init(|| async {
+                let mut options = ConnectOptions::new().name("miflora");
+                if let Some(ref path) = self.config.credentials_file {
+                    options = options.credentials_file(path).await?;
+                }
+                if let Some(ref token) = self.config.token {
+                    options = options.token(token.clone());
+                }
+                if let Some(ref username) = self.config.username {
+                    let password = self.config.password.clone().unwrap_or_default();
+                    options = options.user_and_password(username.clone(), password);
+                }
+                let client = options.connect(self.config.url.as_str()).await?;
+                let jetstream = match self.config.jetstream {
+                    Some(ref stream) => {
+                        let context = jetstream::new(client.clone());
+                        context
+                            .get_or_create_stream(jetstream::stream::Config {
+                                name: stream.stream.clone(),
+                                subjects: vec![
+                                    wildcard_subject(&self.config.state_subject),
+                                    wildcard_subject(&self.config.history_subject),
+                                ],
+                                max_age: stream.max_age.unwrap_or_default(),
+                                ..Default::default()
+                            })
+                            .await?;
+                        Some(context)
+                    }
+                    None => None,
+                };
+                tracing::debug!(message = "connected to nats", url = %self.config.url);
+                Ok(Connection { client, jetstream })
+            })
+            .await
+    }
+}
+
+impl Sink for NatsSink {
+    fn announce<'a>(&'a self, _sources: &'a [Source]) -> BoxFuture<'a, anyhow::Result<()>> {
+        // connecting early reports a wrong configuration on startup
+        async move { self.connection().await.map(|_| ()) }.boxed()
+    }
+
+    fn publish<'a>(
+        &'a self,
+        kind: Kind,
+        reading: &'a Reading,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            let template = match kind {
+                Kind::Realtime => &self.config.state_subject,
+                Kind::History => &self.config.history_subject,
+            };
+            let subject = subject_for(template, &reading.source);
+            let payload = serde_json::to_vec(reading)?;
+            let connection = self.connection().await?;
+            match connection.jetstream {
+                // waits for the server to store the reading
+                Some(ref jetstream) => {
+                    jetstream.publish(subject, payload.into()).await?.await?;
+                }
+                None => connection.client.publish(subject, payload.into()).await?,
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn close(&self) -> BoxFuture<'_, anyhow::Result<()>> {
+        async move {
+            if let Some(connection) = self.connection.get() {
+                connection.client.flush().await?;
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{subject_for, wildcard_subject};
+    use crate::record::Source;
+
+    #[test]
+    fn should_fill_subject_placeholders() {
+        let mut source = Source {
+            address: "C4:7C:8D:6A:3E:1F".parse().unwrap(),
+            alias: None,
+        };
+        assert_eq!(
+            subject_for("miflora.{alias}.state", &source),
+            "miflora.C4:7C:8D:6A:3E:1F.state"
+        );
+        source.alias = Some("basil kitchen.v2".into());
+        assert_eq!(
+            subject_for("miflora.{alias}.{address}", &source),
+            "miflora.basil_kitchen_v2.C4:7C:8D:6A:3E:1F"
+        );
+    }
+
+    #[test]
+    fn should_capture_all_the_devices_in_the_stream() {
+        assert_eq!(
+            wildcard_subject("miflora.{alias}.history"),
+            "miflora.*.history"
+        );
+        assert_eq!(wildcard_subject("gateway.{address}.{alias}"), "gateway.*.*");
+    }
+}
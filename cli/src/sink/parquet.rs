@@ -0,0 +1,132 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use tokio::sync::{mpsc, oneshot};
+
+use super::{Kind, Sink};
+use crate::config::ParquetConfig;
+use crate::record::{now, Reading};
+
+/// Writes the readings to Parquet files, a new file of each kind being written on each
+/// rotation.
+pub struct ParquetSink {
+    sender: mpsc::UnboundedSender<Message>,
+}
+
+enum Message {
+    Reading(Kind, Reading),
+    /// Writes the pending readings right away, notifying once done.
+    Flush(oneshot::Sender<()>),
+}
+
+/// Readings received since the last file.
+#[derive(Default)]
+struct Pending {
+    realtime: Vec<Reading>,
+    history: Vec<Reading>,
+}
+
+impl Pending {
+    fn push(&mut self, kind: Kind, reading: Reading) {
+        match kind {
+            Kind::Realtime => self.realtime.push(reading),
+            Kind::History => self.history.push(reading),
+        }
+    }
+}
+
+struct Writer {
+    directory: PathBuf,
+}
+
+impl Writer {
+    async fn run(self, rotation: Duration, mut receiver: mpsc::UnboundedReceiver<Message>) {
+        let mut pending = Pending::default();
+        let mut interval = tokio::time::interval(rotation);
+        // the first tick completes right away
+        interval.tick().await;
+        loop {
+            tokio::select! {
+                message = receiver.recv() => match message {
+                    Some(Message::Reading(kind, reading)) => pending.push(kind, reading),
+                    Some(Message::Flush(done)) => {
+                        self.flush(&mut pending).await;
+                        let _ = done.send(());
+                    }
+                    None => {
+                        self.flush(&mut pending).await;
+                        return;
+                    }
+                },
+                _ = interval.tick() => self.flush(&mut pending).await,
+            }
+        }
+    }
+
+    async fn flush(&self, pending: &mut Pending) {
+        let timestamp = now();
+        for (name, readings) in [
+            ("realtime", std::mem::take(&mut pending.realtime)),
+            ("history", std::mem::take(&mut pending.history)),
+        ] {
+            if readings.is_empty() {
+                continue;
+            }
+            let path = self.directory.join(format!("{name}-{timestamp}.parquet"));
+            let written = tokio::task::spawn_blocking({
+                let path = path.clone();
+                let directory = self.directory.clone();
+                move || -> anyhow::Result<()> {
+                    std::fs::create_dir_all(directory)?;
+                    crate::columnar::write(std::fs::File::create(path)?, &readings)
+                }
+            })
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|result| result);
+            if let Err(err) = written {
+                tracing::warn!(message = "unable to write parquet file", path = %path.display(), error = %err);
+            }
+        }
+    }
+}
+
+impl ParquetSink {
+    pub fn new(config: &ParquetConfig) -> Self {
+        let writer = Writer {
+            directory: config.directory.clone(),
+        };
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(writer.run(config.rotation, receiver));
+        Self { sender }
+    }
+}
+
+impl Sink for ParquetSink {
+    fn publish<'a>(
+        &'a self,
+        kind: Kind,
+        reading: &'a Reading,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        let result = self
+            .sender
+            .send(Message::Reading(kind, reading.clone()))
+            .map_err(|_| anyhow::anyhow!("parquet writer stopped"));
+        futures::future::ready(result).boxed()
+    }
+
+    fn close(&self) -> BoxFuture<'_, anyhow::Result<()>> {
+        async move {
+            let (done, flushed) = oneshot::channel();
+            self.sender
+                .send(Message::Flush(done))
+                .map_err(|_| anyhow::anyhow!("parquet writer stopped"))?;
+            flushed
+                .await
+                .map_err(|_| anyhow::anyhow!("parquet writer stopped"))
+        }
+        .boxed()
+    }
+}
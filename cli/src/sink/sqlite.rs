@@ -0,0 +1,193 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use rusqlite::{params, Connection};
+
+use super::{Kind, Sink};
+use crate::record::Reading;
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS readings (
+    address TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    alias TEXT,
+    temperature REAL NOT NULL,
+    brightness INTEGER,
+    moisture INTEGER NOT NULL,
+    conductivity INTEGER NOT NULL,
+    battery INTEGER,
+    firmware TEXT,
+    PRIMARY KEY (address, timestamp, kind)
+)";
+
+/// Version of the schema, kept in the `user_version` of the database.
+const SCHEMA_VERSION: u32 = 1;
+
+/// Moves the readings of the databases created before the kind was part of the key, which
+/// ignored the realtime and historical readings recorded at the same time, once the table
+/// is renamed and created again.
+const MIGRATION_KIND_KEY: &str = "INSERT INTO readings
+    SELECT address, timestamp, kind, alias, temperature, brightness, moisture, conductivity, battery, firmware
+    FROM readings_v0;
+DROP TABLE readings_v0;";
+
+/// Stores the readings in a SQLite database, the readings already stored being ignored.
+///
+/// A reading is identified by the device, its timestamp and whether it's realtime or
+/// historical, both being possibly recorded at the same second.
+pub struct SqliteSink {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteSink {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        Self::new(Connection::open(path)?)
+    }
+
+    fn new(mut connection: Connection) -> anyhow::Result<Self> {
+        Self::migrate(&mut connection)?;
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// Creates the table, or updates the one of a previous version.
+    fn migrate(connection: &mut Connection) -> rusqlite::Result<()> {
+        let version: u32 = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version >= SCHEMA_VERSION {
+            return Ok(());
+        }
+        let transaction = connection.transaction()?;
+        let exists: bool = transaction.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'readings')",
+            [],
+            |row| row.get(0),
+        )?;
+        if exists {
+            tracing::info!("adding the kind to the key of the stored readings");
+            transaction.execute("ALTER TABLE readings RENAME TO readings_v0", [])?;
+        }
+        transaction.execute(SCHEMA, [])?;
+        if exists {
+            transaction.execute_batch(MIGRATION_KIND_KEY)?;
+        }
+        transaction.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        transaction.commit()
+    }
+
+    fn insert(connection: &Connection, kind: Kind, reading: &Reading) -> rusqlite::Result<usize> {
+        connection.execute(
+            "INSERT OR IGNORE INTO readings (address, timestamp, kind, alias, temperature, brightness, moisture, conductivity, battery, firmware)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                reading.source.address.to_string(),
+                reading.timestamp,
+                match kind {
+                    Kind::Realtime => "realtime",
+                    Kind::History => "history",
+                },
+                reading.source.alias,
+                reading.temperature,
+                reading.brightness,
+                reading.moisture,
+                reading.conductivity,
+                reading.battery,
+                reading.firmware,
+            ],
+        )
+    }
+}
+
+impl Sink for SqliteSink {
+    fn publish<'a>(
+        &'a self,
+        kind: Kind,
+        reading: &'a Reading,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        let connection = self.connection.clone();
+        let reading = reading.clone();
+        async move {
+            tokio::task::spawn_blocking(move || {
+                let connection = connection.lock().expect("sqlite lock poisoned");
+                Self::insert(&connection, kind, &reading)?;
+                Ok(())
+            })
+            .await?
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use super::SqliteSink;
+    use crate::record::{Reading, Source};
+    use crate::sink::{Kind, Sink};
+
+    fn reading() -> Reading {
+        Reading {
+            source: Source {
+                address: "C4:7C:8D:6A:3E:1F".parse().unwrap(),
+                alias: Some("basil-kitchen".into()),
+            },
+            timestamp: 1_700_000_000,
+            temperature: 21.5,
+            brightness: Some(300),
+            moisture: 25,
+            conductivity: 150,
+            battery: None,
+            firmware: None,
+        }
+    }
+
+    fn count(sink: &SqliteSink) -> u32 {
+        let connection = sink.connection.lock().unwrap();
+        connection
+            .query_row("SELECT COUNT(*) FROM readings", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_ignore_stored_readings() {
+        let sink = SqliteSink::new(Connection::open_in_memory().unwrap()).unwrap();
+        let reading = reading();
+        sink.publish(Kind::History, &reading).await.unwrap();
+        sink.publish(Kind::History, &reading).await.unwrap();
+        assert_eq!(count(&sink), 1);
+        // a realtime reading can be recorded at the same second
+        sink.publish(Kind::Realtime, &reading).await.unwrap();
+        assert_eq!(count(&sink), 2);
+    }
+
+    #[tokio::test]
+    async fn should_add_the_kind_to_the_key_of_previous_databases() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE readings (
+                    address TEXT NOT NULL,
+                    timestamp INTEGER NOT NULL,
+                    kind TEXT NOT NULL,
+                    alias TEXT,
+                    temperature REAL NOT NULL,
+                    brightness INTEGER,
+                    moisture INTEGER NOT NULL,
+                    conductivity INTEGER NOT NULL,
+                    battery INTEGER,
+                    firmware TEXT,
+                    PRIMARY KEY (address, timestamp)
+                );
+                INSERT INTO readings VALUES
+                    ('C4:7C:8D:6A:3E:1F', 1700000000, 'history', NULL, 21.5, 300, 25, 150, NULL, NULL);",
+            )
+            .unwrap();
+        let sink = SqliteSink::new(connection).unwrap();
+        assert_eq!(count(&sink), 1);
+        sink.publish(Kind::Realtime, &reading()).await.unwrap();
+        assert_eq!(count(&sink), 2);
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use bluer::Address;
+use serde::{Deserialize, Serialize};
+
+use crate::alert::Bound;
+
+/// Name of the file the states are written to, in the state directory.
+const STATE_FILE: &str = "state.json";
+
+/// Directory of the state when not configured, following the XDG base directories.
+pub fn default_directory() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_STATE_HOME") {
+        Some(value) if !value.is_empty() => PathBuf::from(value),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/state"),
+    };
+    Some(base.join("miflora"))
+}
+
+/// Value out of its range, already alerted.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Breach {
+    pub metric: String,
+    pub bound: Bound,
+}
+
+/// What is known about a device, kept across the restarts.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DeviceState {
+    /// Timestamp of the last historical entry read, the next reads starting after it
+    pub history_since: Option<u64>,
+    /// Timestamp of the last historical entry exported by the `history` command
+    pub exported_since: Option<u64>,
+    pub breached: Vec<Breach>,
+}
+
+/// States of the devices, written to the state directory after each change so a restart
+/// doesn't download the history again or repeat the alerts.
+///
+/// Without a directory, the states are only kept in memory.
+#[derive(Debug, Default)]
+pub struct State {
+    path: Option<PathBuf>,
+    devices: Mutex<BTreeMap<Address, DeviceState>>,
+}
+
+impl State {
+    /// Loads the states written in the directory, if any.
+    pub fn load(directory: &Path) -> anyhow::Result<Self> {
+        let path = directory.join(STATE_FILE);
+        let devices = match std::fs::read(&path) {
+            Ok(content) => serde_json::from_slice(&content)
+                .map_err(|err| anyhow::anyhow!("unable to read state {}: {err}", path.display()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Self {
+            path: Some(path),
+            devices: Mutex::new(devices),
+        })
+    }
+
+    pub fn get(&self, address: Address) -> DeviceState {
+        self.devices
+            .lock()
+            .expect("state poisoned")
+            .get(&address)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Changes the state of the device and writes all the states, only logging the
+    /// failures to keep the daemon running.
+    pub fn update<F: FnOnce(&mut DeviceState)>(&self, address: Address, func: F) {
+        let mut devices = self.devices.lock().expect("state poisoned");
+        let state = devices.entry(address).or_default();
+        let previous = state.clone();
+        func(state);
+        if *state == previous {
+            return;
+        }
+        let Some(ref path) = self.path else {
+            return;
+        };
+        if let Err(err) = write(path, &devices) {
+            tracing::warn!(message = "unable to write state", path = %path.display(), error = %err);
+        }
+    }
+}
+
+/// Writes to a temporary file first, so the state isn't lost when interrupted.
+fn write(path: &Path, devices: &BTreeMap<Address, DeviceState>) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let temporary = path.with_extension("json.tmp");
+    std::fs::write(&temporary, serde_json::to_vec_pretty(devices)?)?;
+    std::fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Breach, State};
+    use crate::alert::Bound;
+
+    #[test]
+    fn should_keep_states_across_loads() {
+        let directory = std::env::temp_dir().join(format!("miflora-state-{}", std::process::id()));
+        let address = "C4:7C:8D:6A:3E:1F".parse().unwrap();
+
+        let state = State::load(&directory).unwrap();
+        assert_eq!(state.get(address).history_since, None);
+        state.update(address, |device| {
+            device.history_since = Some(1_700_000_000);
+            device.exported_since = Some(1_700_003_600);
+            device.breached.push(Breach {
+                metric: "moisture".into(),
+                bound: Bound::Min,
+            });
+        });
+
+        let loaded = State::load(&directory).unwrap().get(address);
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(loaded, state.get(address));
+        assert_eq!(loaded.history_since, Some(1_700_000_000));
+    }
+}
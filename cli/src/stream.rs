@@ -0,0 +1,129 @@
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use bluer_miflora::advertisement::PassiveReading;
+use bluer_miflora::{Advertised, Registry};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::context::Context;
+use crate::record::{Reading, Source};
+use crate::sink::{Kind, Sink};
+
+/// Number of events kept for the clients too slow to receive them.
+const CAPACITY: usize = 256;
+
+/// Event pushed to the clients of the stream, as a JSON object.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event<'a> {
+    /// Reading collected by polling the device
+    Reading {
+        kind: Kind,
+        #[serde(flatten)]
+        reading: &'a Reading,
+    },
+    /// Single value advertised by the device, without connecting to it
+    Advertisement {
+        #[serde(flatten)]
+        source: Source,
+        timestamp: u64,
+        metric: &'static str,
+        value: f64,
+    },
+}
+
+impl Event<'_> {
+    fn advertisement(source: Source, advertised: &Advertised) -> Option<Self> {
+        let (metric, value) = match advertised.reading {
+            PassiveReading::Temperature(value) => ("temperature", f64::from(value) / 10.0),
+            PassiveReading::Brightness(value) => ("brightness", f64::from(value)),
+            PassiveReading::Moisture(value) => ("moisture", f64::from(value)),
+            PassiveReading::Conductivity(value) => ("conductivity", f64::from(value)),
+            PassiveReading::Battery(value) => ("battery", f64::from(value)),
+            PassiveReading::Unknown(..) => return None,
+        };
+        Some(Self::Advertisement {
+            source,
+            timestamp: advertised
+                .received
+                .duration_since(UNIX_EPOCH)
+                .map(|value| value.as_secs())
+                .unwrap_or_default(),
+            metric,
+            value,
+        })
+    }
+}
+
+/// Live events of the daemon, serialized once for all the clients of the stream.
+#[derive(Clone, Debug)]
+pub struct LiveStream {
+    sender: broadcast::Sender<Arc<str>>,
+}
+
+impl Default for LiveStream {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(CAPACITY).0,
+        }
+    }
+}
+
+impl LiveStream {
+    fn send(&self, event: &Event<'_>) {
+        match serde_json::to_string(event) {
+            // failing only without any client
+            Ok(json) => {
+                let _ = self.sender.send(json.into());
+            }
+            Err(err) => tracing::warn!(message = "unable to serialize event", error = %err),
+        }
+    }
+
+    /// Events sent from now on, the oldest ones being skipped when falling behind.
+    pub fn subscribe(&self) -> impl futures::Stream<Item = Arc<str>> + Send + 'static {
+        futures::stream::unfold(self.sender.subscribe(), |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some((event, receiver)),
+                    Err(broadcast::error::RecvError::Lagged(count)) => {
+                        tracing::debug!(message = "stream client lagging", skipped = count);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
+    /// Sends the values advertised by the devices discovered by the registry.
+    pub async fn forward_advertisements(&self, ctx: &Context, registry: &Registry) {
+        let mut receiver = registry.subscribe();
+        loop {
+            match receiver.recv().await {
+                Ok(advertised) => {
+                    if let Some(event) =
+                        Event::advertisement(ctx.source(advertised.address), &advertised)
+                    {
+                        self.send(&event);
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => return,
+            }
+        }
+    }
+}
+
+impl Sink for LiveStream {
+    fn publish<'a>(
+        &'a self,
+        kind: Kind,
+        reading: &'a Reading,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        self.send(&Event::Reading { kind, reading });
+        futures::future::ready(Ok(())).boxed()
+    }
+}
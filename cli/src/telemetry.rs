@@ -0,0 +1,71 @@
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing::level_filters::LevelFilter;
+use tracing::Subscriber;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Name of the service in the exported metrics and traces.
+const SERVICE_NAME: &str = "miflora";
+
+/// Exports the metrics of the communication with the devices and the spans of the logs to
+/// an OpenTelemetry collector, over OTLP/HTTP.
+pub struct Telemetry {
+    meters: SdkMeterProvider,
+    tracers: SdkTracerProvider,
+}
+
+impl Telemetry {
+    /// Sets the global meter provider, before any device is handled for the metrics to be
+    /// recorded.
+    pub fn new(endpoint: &str) -> anyhow::Result<Self> {
+        let endpoint = endpoint.trim_end_matches('/');
+        let resource = Resource::builder().with_service_name(SERVICE_NAME).build();
+        let metrics = MetricExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{endpoint}/v1/metrics"))
+            .build()?;
+        let meters = SdkMeterProvider::builder()
+            .with_periodic_exporter(metrics)
+            .with_resource(resource.clone())
+            .build();
+        opentelemetry::global::set_meter_provider(meters.clone());
+        let spans = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{endpoint}/v1/traces"))
+            .build()?;
+        let tracers = SdkTracerProvider::builder()
+            .with_batch_exporter(spans)
+            .with_resource(resource)
+            .build();
+        Ok(Self { meters, tracers })
+    }
+
+    /// Layer exporting the spans of the commands and of the library, whatever the log level.
+    pub fn layer<S>(&self) -> impl Layer<S>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        tracing_opentelemetry::layer()
+            .with_tracer(self.tracers.tracer(SERVICE_NAME))
+            .with_filter(
+                Targets::new()
+                    .with_target("miflora", LevelFilter::INFO)
+                    .with_target("bluer_miflora", LevelFilter::INFO),
+            )
+    }
+
+    /// Exports what's still buffered, blocking until done.
+    pub fn shutdown(self) {
+        if let Err(err) = self.tracers.shutdown() {
+            tracing::warn!(message = "unable to export the traces", error = %err);
+        }
+        if let Err(err) = self.meters.shutdown() {
+            tracing::warn!(message = "unable to export the metrics", error = %err);
+        }
+    }
+}
@@ -0,0 +1,14 @@
+#![no_main]
+
+use bluer_miflora::advertisement::{BindKey, MiBeacon};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: ([u8; 16], &[u8])| {
+    let (key, data) = input;
+    if let Ok(beacon) = MiBeacon::decode(data) {
+        let _ = beacon.model();
+        let _ = beacon.address();
+        let _ = beacon.reading();
+    }
+    let _ = MiBeacon::decode_with_key(data, &BindKey::from(key), None);
+});
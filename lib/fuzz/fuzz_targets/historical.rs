@@ -0,0 +1,17 @@
+#![no_main]
+
+use bluer_miflora::{EpochTime, HistoricalEntry, Model};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (u64, &[u8])| {
+    let (epoch, data) = input;
+    for model in [Model::FlowerCare, Model::Ropot, Model::GrowCareGarden] {
+        if let Ok(entry) = HistoricalEntry::try_new(data.to_vec(), model, EpochTime::exact(epoch)) {
+            let _ = entry.timestamp();
+            let _ = entry.temperature();
+            let _ = entry.brightness();
+            let _ = entry.moisture();
+            let _ = entry.conductivity();
+        }
+    }
+});
@@ -0,0 +1,15 @@
+#![no_main]
+
+use bluer_miflora::{Model, RealtimeEntry};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    for model in [Model::FlowerCare, Model::Ropot, Model::GrowCareGarden] {
+        if let Ok(entry) = RealtimeEntry::try_new(data.to_vec(), model) {
+            let _ = entry.temperature();
+            let _ = entry.brightness();
+            let _ = entry.moisture();
+            let _ = entry.conductivity();
+        }
+    }
+});
@@ -0,0 +1,12 @@
+#![no_main]
+
+use bluer_miflora::System;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(system) = System::try_from(data.to_vec()) {
+        let _ = system.battery();
+        let _ = system.firmware();
+        let _ = system.firmware_version();
+    }
+});
@@ -0,0 +1,128 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use bluer::{Adapter, Address, Session};
+use futures::future;
+use futures::stream::{self, Stream, StreamExt};
+
+use crate::{scan, Error, Miflora, Registry};
+
+/// Sightings older than this compared to the most recent one aren't used for the routing.
+const SIGHTING_MAX_AGE: Duration = Duration::from_secs(300);
+
+/// Finds the adapter with the given name, like `hci1`, or address, to pin the communication
+/// to one of the dongles of the machine.
+pub async fn select_adapter(session: &Session, name_or_address: &str) -> Result<Adapter, Error> {
+    let names = session
+        .adapter_names()
+        .await
+        .map_err(|cause| Error::CommandFailed { cause })?;
+    if names.iter().any(|name| name == name_or_address) {
+        return session
+            .adapter(name_or_address)
+            .map_err(|cause| Error::CommandFailed { cause });
+    }
+    if let Ok(address) = name_or_address.parse::<Address>() {
+        for name in names {
+            let adapter = session
+                .adapter(&name)
+                .map_err(|cause| Error::CommandFailed { cause })?;
+            if adapter.address().await.ok() == Some(address) {
+                return Ok(adapter);
+            }
+        }
+    }
+    Err(Error::AdapterNotFound {
+        adapter: name_or_address.to_string(),
+    })
+}
+
+/// Several adapters covering the same devices, to extend the range of a gateway.
+///
+/// Each adapter keeps its own [`Registry`] and the connections go through the adapter that
+/// recently saw the device with the best signal.
+#[derive(Clone, Debug)]
+pub struct AdapterSet {
+    registries: Vec<Registry>,
+}
+
+impl AdapterSet {
+    pub fn new<I: IntoIterator<Item = Adapter>>(adapters: I) -> Self {
+        Self {
+            registries: adapters.into_iter().map(Registry::new).collect(),
+        }
+    }
+
+    pub fn registries(&self) -> &[Registry] {
+        &self.registries
+    }
+
+    /// Runs the discovery on all the adapters until they all stop.
+    pub async fn run(&self) -> Result<(), Error> {
+        future::try_join_all(self.registries.iter().map(Registry::run)).await?;
+        Ok(())
+    }
+
+    /// Discovers the devices on all the adapters and yields each miflora once, through
+    /// the adapter that discovered it first.
+    pub fn scan(&self) -> impl Stream<Item = Result<Miflora, Error>> {
+        let mut seen = HashSet::new();
+        stream::select_all(
+            self.registries
+                .iter()
+                .map(|registry| scan(registry.adapter()).boxed()),
+        )
+        .filter(move |item| {
+            let keep = match item {
+                Ok(miflora) => seen.insert(miflora.address()),
+                Err(_) => true,
+            };
+            future::ready(keep)
+        })
+    }
+
+    /// Adapter that recently saw the device with the best signal.
+    ///
+    /// The sightings older than a few minutes compared to the most recent one are ignored,
+    /// since the device may have moved since.
+    pub fn best_adapter(&self, address: &Address) -> Option<&Adapter> {
+        let sightings: Vec<_> = self
+            .registries
+            .iter()
+            .filter_map(|registry| Some((registry, registry.get(address)?)))
+            .collect();
+        let most_recent = sightings.iter().map(|(_, state)| state.last_seen()).max()?;
+        sightings
+            .into_iter()
+            .filter(|(_, state)| {
+                most_recent
+                    .duration_since(state.last_seen())
+                    .is_ok_and(|age| age <= SIGHTING_MAX_AGE)
+            })
+            .max_by_key(|(_, state)| (state.rssi().unwrap_or(i16::MIN), state.last_seen()))
+            .map(|(registry, _)| registry.adapter())
+    }
+
+    /// Creates the miflora with the best adapter to reach it, or the first adapter if no
+    /// adapter saw it yet.
+    pub async fn miflora(&self, address: Address) -> Result<Miflora, Error> {
+        let adapter = self
+            .best_adapter(&address)
+            .or_else(|| self.registries.first().map(Registry::adapter))
+            .ok_or(Error::DeviceNotFound {
+                address,
+                cause: bluer::Error {
+                    kind: bluer::ErrorKind::NotFound,
+                    message: "no adapter available".into(),
+                },
+            })?;
+        Miflora::try_from_adapter(adapter, address).await
+    }
+
+    /// Records a successful connection to the device on every adapter that saw it.
+    pub fn record_connection(&self, address: Address) {
+        for registry in &self.registries {
+            registry.record_connection(address);
+        }
+    }
+}
@@ -0,0 +1,310 @@
+//! Decoding of the MiBeacon advertisements, to collect the sensor values without connecting.
+//!
+//! The devices broadcast one value at a time in the service data of the `fe95` service,
+//! alternating between them, so a full set of values requires listening for a while.
+//!
+//! The newer firmwares encrypt the values with the bind key of the device, which can be
+//! decrypted with the `encryption` feature.
+
+use bluer::{Address, Device};
+pub use miflora_protocol::advertisement::PassiveReading;
+use miflora_protocol::advertisement::{decode_object, Frame};
+use miflora_protocol::DEVICE_UUID_PREFIX;
+
+use crate::{Error, Model};
+
+#[cfg(feature = "encryption")]
+const ENCRYPTION_MIN_VERSION: u8 = 4;
+/// Length of the extended frame counter and of the message integrity check.
+#[cfg(feature = "encryption")]
+const ENCRYPTION_TRAILER_LENGTH: usize = 3 + 4;
+#[cfg(feature = "encryption")]
+const ENCRYPTION_ASSOCIATED_DATA: [u8; 1] = [0x11];
+
+/// Key shared with a device when binding it to a Xiaomi account, used to decrypt its
+/// advertisements.
+#[cfg(feature = "encryption")]
+#[derive(Clone, PartialEq, Eq)]
+pub struct BindKey([u8; 16]);
+
+#[cfg(feature = "encryption")]
+impl BindKey {
+    /// Parses the key from its 32 hexadecimal characters.
+    pub fn from_hex(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if value.len() != 32 || !value.is_ascii() {
+            return None;
+        }
+        let mut key = [0; 16];
+        for (index, byte) in key.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&value[index * 2..index * 2 + 2], 16).ok()?;
+        }
+        Some(Self(key))
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl From<[u8; 16]> for BindKey {
+    fn from(value: [u8; 16]) -> Self {
+        Self(value)
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl std::fmt::Debug for BindKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("BindKey(..)")
+    }
+}
+
+/// Frame broadcast by the device in the service data of the `fe95` service.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MiBeacon {
+    version: u8,
+    product_id: u16,
+    frame_counter: u8,
+    address: Option<Address>,
+    reading: Option<PassiveReading>,
+}
+
+impl MiBeacon {
+    /// Decodes the service data of the `fe95` service.
+    ///
+    /// Encrypted frames can't be decoded without the bind key of the device and return an
+    /// [`Error::EncryptedAdvertisement`].
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        Self::decode_frame(data, |_, _| Err(Error::EncryptedAdvertisement))
+    }
+
+    /// Decodes the service data of the `fe95` service, decrypting the value with the bind
+    /// key of the device if needed.
+    ///
+    /// The address of the device is part of the encryption, it's only needed when the frame
+    /// doesn't include it.
+    #[cfg(feature = "encryption")]
+    pub fn decode_with_key(
+        data: &[u8],
+        key: &BindKey,
+        address: Option<Address>,
+    ) -> Result<Self, Error> {
+        Self::decode_frame(data, |beacon, payload| {
+            let address = beacon
+                .address
+                .or(address)
+                .ok_or(Error::InvalidAdvertisement {
+                    reason: "address required to decrypt",
+                })?;
+            decrypt(beacon, address, key, payload)
+        })
+    }
+
+    fn decode_frame<F>(data: &[u8], decrypt: F) -> Result<Self, Error>
+    where
+        F: FnOnce(&Self, &[u8]) -> Result<Vec<u8>, Error>,
+    {
+        let frame = Frame::decode(data)?;
+        let mut beacon = Self {
+            version: frame.version(),
+            product_id: frame.product_id(),
+            frame_counter: frame.frame_counter(),
+            address: frame.address().map(Address::new),
+            reading: None,
+        };
+        if let Some(payload) = frame.object() {
+            beacon.reading = if frame.is_encrypted() {
+                Some(decode_object(&decrypt(&beacon, payload)?)?)
+            } else {
+                Some(decode_object(payload)?)
+            };
+        }
+        Ok(beacon)
+    }
+
+    /// Version of the MiBeacon protocol.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn product_id(&self) -> u16 {
+        self.product_id
+    }
+
+    pub fn model(&self) -> Option<Model> {
+        Model::from_product_id(self.product_id)
+    }
+
+    /// Counter incremented by the device for each new frame, to ignore the duplicates.
+    pub fn frame_counter(&self) -> u8 {
+        self.frame_counter
+    }
+
+    /// Address of the device, when included in the frame.
+    pub fn address(&self) -> Option<Address> {
+        self.address
+    }
+
+    /// Value broadcast in the frame, the devices sending frames without any value too.
+    pub fn reading(&self) -> Option<PassiveReading> {
+        self.reading
+    }
+}
+
+/// Decrypts the payload of a MiBeacon v4 or v5 frame, made of the encrypted objects, the
+/// extended frame counter on 3 bytes and the message integrity check on 4 bytes.
+#[cfg(feature = "encryption")]
+fn decrypt(
+    beacon: &MiBeacon,
+    address: Address,
+    key: &BindKey,
+    payload: &[u8],
+) -> Result<Vec<u8>, Error> {
+    use aes::Aes128;
+    use ccm::aead::generic_array::GenericArray;
+    use ccm::aead::{AeadInPlace, KeyInit};
+    use ccm::consts::{U12, U4};
+
+    if beacon.version() < ENCRYPTION_MIN_VERSION {
+        return Err(Error::InvalidAdvertisement {
+            reason: "encryption version not supported",
+        });
+    }
+    if payload.len() < ENCRYPTION_TRAILER_LENGTH {
+        return Err(Error::InvalidAdvertisement {
+            reason: "encrypted payload too short",
+        });
+    }
+    let (ciphertext, trailer) = payload.split_at(payload.len() - ENCRYPTION_TRAILER_LENGTH);
+    let (extended_counter, tag) = trailer.split_at(3);
+    // the nonce is made of the reversed address, the product id and the frame counters
+    let mut nonce = [0; 12];
+    let mut mac = address.0;
+    mac.reverse();
+    nonce[0..6].copy_from_slice(&mac);
+    nonce[6..8].copy_from_slice(&beacon.product_id.to_le_bytes());
+    nonce[8] = beacon.frame_counter;
+    nonce[9..12].copy_from_slice(extended_counter);
+
+    let cipher = ccm::Ccm::<Aes128, U4, U12>::new(GenericArray::from_slice(&key.0));
+    let mut buffer = ciphertext.to_vec();
+    cipher
+        .decrypt_in_place_detached(
+            GenericArray::from_slice(&nonce),
+            &ENCRYPTION_ASSOCIATED_DATA,
+            &mut buffer,
+            GenericArray::from_slice(tag),
+        )
+        .map_err(|_| Error::DecryptionFailed)?;
+    Ok(buffer)
+}
+
+/// Decodes the MiBeacon frame currently advertised by the device, if any.
+pub async fn read_advertisement(device: &Device) -> Result<Option<MiBeacon>, Error> {
+    let data = read_service_data(device).await?;
+    data.as_deref().map(MiBeacon::decode).transpose()
+}
+
+/// Decodes the MiBeacon frame currently advertised by the device, decrypting it with the
+/// bind key of the device if needed.
+#[cfg(feature = "encryption")]
+pub async fn read_advertisement_with_key(
+    device: &Device,
+    key: &BindKey,
+) -> Result<Option<MiBeacon>, Error> {
+    let data = read_service_data(device).await?;
+    data.as_deref()
+        .map(|data| MiBeacon::decode_with_key(data, key, Some(device.address())))
+        .transpose()
+}
+
+async fn read_service_data(device: &Device) -> Result<Option<Vec<u8>>, Error> {
+    let service_data = device
+        .service_data()
+        .await
+        .map_err(|err| Error::CommandFailed { cause: err })?;
+    let service_data = service_data.ok_or(Error::NoServiceData)?;
+    Ok(service_data
+        .into_iter()
+        .find(|(uuid, _)| uuid.as_fields().0 == DEVICE_UUID_PREFIX)
+        .map(|(_, data)| data))
+}
+
+#[cfg(all(test, feature = "encryption"))]
+mod tests {
+    use bluer::Address;
+
+    use super::{BindKey, MiBeacon, PassiveReading};
+    use crate::Error;
+
+    // no vector of the flower care being published, these frames were encrypted with an
+    // independent AES-CCM implementation following the MiBeacon v5 layout
+    const KEY: &str = "b853075158487ca39a5b5ea9e7fa4b8d";
+    const ADDRESS: &str = "C4:7C:8D:6A:3E:1F";
+    /// Temperature of -1.5 °C, with the frame counter 0x42 and the extended counter 0x030201
+    const FRAME_WITH_MAC: [u8; 23] = [
+        0x58, 0x50, 0x98, 0x00, 0x42, 0x1f, 0x3e, 0x6a, 0x8d, 0x7c, 0xc4, 0x0e, 0xbb, 0xe7, 0x97,
+        0x19, 0x01, 0x02, 0x03, 0x5a, 0x35, 0x9f, 0x29,
+    ];
+    /// Same object without the address of the device in the frame
+    const FRAME_WITHOUT_MAC: [u8; 17] = [
+        0x48, 0x50, 0x98, 0x00, 0x42, 0x0e, 0xbb, 0xe7, 0x97, 0x19, 0x01, 0x02, 0x03, 0x5a, 0x35,
+        0x9f, 0x29,
+    ];
+
+    fn key() -> BindKey {
+        BindKey::from_hex(KEY).unwrap()
+    }
+
+    #[test]
+    fn should_decrypt_frames() {
+        let beacon = MiBeacon::decode_with_key(&FRAME_WITH_MAC, &key(), None).unwrap();
+        assert_eq!(beacon.version(), 5);
+        assert_eq!(beacon.frame_counter(), 0x42);
+        assert_eq!(beacon.address(), Some(ADDRESS.parse().unwrap()));
+        assert_eq!(beacon.reading(), Some(PassiveReading::Temperature(-15)));
+    }
+
+    #[test]
+    fn should_decrypt_frames_with_the_address_of_the_device() {
+        let address: Address = ADDRESS.parse().unwrap();
+        let beacon = MiBeacon::decode_with_key(&FRAME_WITHOUT_MAC, &key(), Some(address)).unwrap();
+        assert_eq!(beacon.reading(), Some(PassiveReading::Temperature(-15)));
+        // the address is part of the nonce
+        let other: Address = "C4:7C:8D:6A:3E:20".parse().unwrap();
+        assert!(matches!(
+            MiBeacon::decode_with_key(&FRAME_WITHOUT_MAC, &key(), Some(other)),
+            Err(Error::DecryptionFailed)
+        ));
+        assert!(matches!(
+            MiBeacon::decode_with_key(&FRAME_WITHOUT_MAC, &key(), None),
+            Err(Error::InvalidAdvertisement { .. })
+        ));
+    }
+
+    #[test]
+    fn should_fail_with_a_wrong_key() {
+        let key = BindKey::from([0; 16]);
+        assert!(matches!(
+            MiBeacon::decode_with_key(&FRAME_WITH_MAC, &key, None),
+            Err(Error::DecryptionFailed)
+        ));
+    }
+
+    #[test]
+    fn should_fail_when_the_frame_is_altered() {
+        let mut frame = FRAME_WITH_MAC;
+        // the extended counter is part of the nonce
+        frame[16] ^= 0x01;
+        assert!(matches!(
+            MiBeacon::decode_with_key(&frame, &key(), None),
+            Err(Error::DecryptionFailed)
+        ));
+    }
+
+    #[test]
+    fn should_require_a_key_for_encrypted_frames() {
+        assert!(matches!(
+            MiBeacon::decode(&FRAME_WITH_MAC),
+            Err(Error::EncryptedAdvertisement)
+        ));
+    }
+}
@@ -0,0 +1,423 @@
+//! Aggregation of the history of the devices.
+//!
+//! The buckets are aligned on the unix epoch, so the days start at midnight UTC.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::HistoricalEntry;
+
+/// Duration covered by a bucket.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Period {
+    Hour,
+    Day,
+}
+
+impl Period {
+    /// Duration of the period in seconds.
+    pub fn seconds(self) -> u64 {
+        match self {
+            Self::Hour => 3600,
+            Self::Day => 86400,
+        }
+    }
+
+    /// Start of the period containing the timestamp.
+    pub fn start_of(self, timestamp: u64) -> u64 {
+        timestamp - timestamp % self.seconds()
+    }
+}
+
+/// Minimum, maximum and average of a metric over a bucket.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stats {
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Accumulator {
+    min: f64,
+    max: f64,
+    sum: f64,
+    count: usize,
+}
+
+impl Accumulator {
+    fn push(&mut self, value: f64) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn stats(&self) -> Option<Stats> {
+        (self.count > 0).then(|| Stats {
+            min: self.min,
+            max: self.max,
+            avg: self.sum / self.count as f64,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct BucketAccumulator {
+    count: usize,
+    temperature: Accumulator,
+    moisture: Accumulator,
+    conductivity: Accumulator,
+    brightness: Accumulator,
+}
+
+/// Statistics of the entries recorded during a period.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bucket {
+    /// Unix timestamp of the start of the period, in seconds
+    pub start: u64,
+    pub period: Period,
+    /// Number of entries recorded during the period
+    pub count: usize,
+    /// Temperature in °C
+    pub temperature: Stats,
+    /// Moisture in %
+    pub moisture: Stats,
+    /// Conductivity in µS/cm
+    pub conductivity: Stats,
+    /// Brightness in lux, if the device has a brightness sensor
+    pub brightness: Option<Stats>,
+}
+
+impl Bucket {
+    /// Unix timestamp of the end of the period, excluded.
+    pub fn end(&self) -> u64 {
+        self.start + self.period.seconds()
+    }
+}
+
+/// Groups the entries by period, the buckets being sorted by time and the periods without
+/// any entry being skipped.
+pub fn aggregate(entries: &[HistoricalEntry], period: Period) -> Vec<Bucket> {
+    let mut buckets: BTreeMap<u64, BucketAccumulator> = BTreeMap::new();
+    for entry in entries {
+        let bucket = buckets
+            .entry(period.start_of(entry.timestamp()))
+            .or_default();
+        bucket.count += 1;
+        bucket
+            .temperature
+            .push(f64::from(entry.temperature_celsius()));
+        bucket.moisture.push(f64::from(entry.moisture()));
+        bucket.conductivity.push(f64::from(entry.conductivity()));
+        if let Some(brightness) = entry.brightness() {
+            bucket.brightness.push(f64::from(brightness));
+        }
+    }
+    buckets
+        .into_iter()
+        .filter_map(|(start, bucket)| {
+            Some(Bucket {
+                start,
+                period,
+                count: bucket.count,
+                temperature: bucket.temperature.stats()?,
+                moisture: bucket.moisture.stats()?,
+                conductivity: bucket.conductivity.stats()?,
+                brightness: bucket.brightness.stats(),
+            })
+        })
+        .collect()
+}
+
+/// Groups the entries by hour, see [`aggregate`].
+pub fn hourly(entries: &[HistoricalEntry]) -> Vec<Bucket> {
+    aggregate(entries, Period::Hour)
+}
+
+/// Groups the entries by day, see [`aggregate`].
+pub fn daily(entries: &[HistoricalEntry]) -> Vec<Bucket> {
+    aggregate(entries, Period::Day)
+}
+
+/// Photosynthetic photon flux density of the sunlight for each lux, in µmol/m²/s.
+pub const SUNLIGHT_PPFD_PER_LUX: f64 = 0.0185;
+
+/// Light received by the plant during a day.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LightIntegral {
+    /// Unix timestamp of midnight UTC starting the day, in seconds
+    pub start: u64,
+    /// Daily light integral, in mol/m²/day
+    pub dli: f64,
+    /// Seconds of the day covered by the entries, the integral being underestimated when
+    /// it's less than a day
+    pub covered: u64,
+}
+
+/// Integrates the brightness of the entries into the daily light integral of each day,
+/// converting the lux to photon flux with [`SUNLIGHT_PPFD_PER_LUX`].
+///
+/// The brightness is interpolated linearly between consecutive entries, the ones more
+/// than `max_gap` apart being considered as a gap in the history that isn't integrated.
+/// The entries of the devices without brightness sensor are ignored.
+pub fn daily_light_integral(entries: &[HistoricalEntry], max_gap: Duration) -> Vec<LightIntegral> {
+    let mut samples: Vec<(u64, f64)> = entries
+        .iter()
+        .filter_map(|entry| Some((entry.timestamp(), f64::from(entry.brightness()?))))
+        .collect();
+    samples.sort_by_key(|(timestamp, _)| *timestamp);
+    samples.dedup_by_key(|(timestamp, _)| *timestamp);
+
+    let day = Period::Day.seconds();
+    let mut days: BTreeMap<u64, (f64, u64)> = BTreeMap::new();
+    for pair in samples.windows(2) {
+        let (mut from, mut from_lux) = pair[0];
+        let (to, to_lux) = pair[1];
+        if to - from > max_gap.as_secs() {
+            continue;
+        }
+        let slope = (to_lux - from_lux) / (to - from) as f64;
+        // splits the intervals spanning midnight between both days
+        while from < to {
+            let start = Period::Day.start_of(from);
+            let until = to.min(start + day);
+            let until_lux = from_lux + slope * (until - from) as f64;
+            let seconds = until - from;
+            let (integral, covered) = days.entry(start).or_default();
+            *integral += (from_lux + until_lux) / 2.0 * seconds as f64;
+            *covered += seconds;
+            from = until;
+            from_lux = until_lux;
+        }
+    }
+    days.into_iter()
+        .map(|(start, (lux_seconds, covered))| LightIntegral {
+            start,
+            dli: lux_seconds * SUNLIGHT_PPFD_PER_LUX / 1_000_000.0,
+            covered,
+        })
+        .collect()
+}
+
+/// Sharp increase of the moisture, when the plant got watered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WateringEvent {
+    /// Unix timestamp of the last entry before the increase, in seconds
+    pub start: u64,
+    /// Unix timestamp of the entry with the highest moisture, in seconds
+    pub end: u64,
+    /// Moisture before the watering, in %
+    pub before: u8,
+    /// Moisture after the watering, in %
+    pub after: u8,
+}
+
+impl WateringEvent {
+    /// Increase of the moisture, in %.
+    pub fn increase(&self) -> u8 {
+        self.after - self.before
+    }
+}
+
+/// Decrease of the moisture between two waterings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DryingPeriod {
+    /// Unix timestamp of the start of the period, in seconds
+    pub start: u64,
+    /// Unix timestamp of the end of the period, in seconds
+    pub end: u64,
+    /// Moisture at the start of the period, in %
+    pub from: u8,
+    /// Moisture at the end of the period, in %
+    pub to: u8,
+}
+
+impl DryingPeriod {
+    /// Moisture lost per day, in %.
+    pub fn rate(&self) -> f64 {
+        let days = (self.end - self.start) as f64 / Period::Day.seconds() as f64;
+        f64::from(self.from.saturating_sub(self.to)) / days
+    }
+}
+
+/// Waterings and drying periods found in the history.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WateringAnalysis {
+    pub waterings: Vec<WateringEvent>,
+    pub drying: Vec<DryingPeriod>,
+}
+
+/// Finds the waterings, as consecutive increases of the moisture adding up to at least
+/// `min_increase` %, and the periods the soil dried in between.
+pub fn detect_waterings(entries: &[HistoricalEntry], min_increase: u8) -> WateringAnalysis {
+    let mut samples: Vec<(u64, u8)> = entries
+        .iter()
+        .map(|entry| (entry.timestamp(), entry.moisture()))
+        .collect();
+    samples.sort_by_key(|(timestamp, _)| *timestamp);
+    samples.dedup_by_key(|(timestamp, _)| *timestamp);
+
+    let mut analysis = WateringAnalysis::default();
+    let mut dry_from = 0;
+    let mut index = 0;
+    while index + 1 < samples.len() {
+        let mut peak = index;
+        while peak + 1 < samples.len() && samples[peak + 1].1 > samples[peak].1 {
+            peak += 1;
+        }
+        let (start, before) = samples[index];
+        let (end, after) = samples[peak];
+        if after - before >= min_increase {
+            analysis.push_drying(samples[dry_from], samples[index]);
+            analysis.waterings.push(WateringEvent {
+                start,
+                end,
+                before,
+                after,
+            });
+            dry_from = peak;
+        }
+        index = peak.max(index + 1);
+    }
+    if let Some(last) = samples.last() {
+        analysis.push_drying(samples[dry_from], *last);
+    }
+    analysis
+}
+
+impl WateringAnalysis {
+    fn push_drying(&mut self, (start, from): (u64, u8), (end, to): (u64, u8)) {
+        if end > start && to < from {
+            self.drying.push(DryingPeriod {
+                start,
+                end,
+                from,
+                to,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{daily, daily_light_integral, detect_waterings, hourly, Period, Stats};
+    use crate::{EpochTime, HistoricalEntry, Model};
+
+    fn entry(
+        timestamp: u32,
+        temperature: i16,
+        brightness: u32,
+        moisture: u8,
+        conductivity: u16,
+    ) -> HistoricalEntry {
+        let mut payload = vec![0; 16];
+        payload[0..4].copy_from_slice(&timestamp.to_le_bytes());
+        payload[4..6].copy_from_slice(&temperature.to_le_bytes());
+        payload[7..10].copy_from_slice(&brightness.to_le_bytes()[..3]);
+        payload[11] = moisture;
+        payload[12..14].copy_from_slice(&conductivity.to_le_bytes());
+        HistoricalEntry::try_new(payload, Model::FlowerCare, EpochTime::exact(0)).unwrap()
+    }
+
+    #[test]
+    fn should_aggregate_by_hour() {
+        let entries = [
+            entry(3600, 200, 1000, 30, 400),
+            entry(5400, 220, 3000, 20, 600),
+            entry(7200, 180, 0, 40, 500),
+        ];
+        let buckets = hourly(&entries);
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].start, 3600);
+        assert_eq!(buckets[0].end(), 7200);
+        assert_eq!(buckets[0].count, 2);
+        assert_eq!(
+            buckets[0].moisture,
+            Stats {
+                min: 20.0,
+                max: 30.0,
+                avg: 25.0
+            }
+        );
+        assert_eq!(buckets[0].brightness.unwrap().avg, 2000.0);
+        assert!((buckets[0].temperature.avg - 21.0).abs() < 0.001);
+        assert_eq!(buckets[1].start, 7200);
+        assert_eq!(buckets[1].count, 1);
+    }
+
+    #[test]
+    fn should_aggregate_by_day() {
+        let entries = [
+            entry(86400 * 2 + 10, 200, 1000, 30, 400),
+            entry(10, 200, 1000, 30, 400),
+            entry(86399, 200, 1000, 50, 400),
+        ];
+        let buckets = daily(&entries);
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].start, 0);
+        assert_eq!(buckets[0].period, Period::Day);
+        assert_eq!(buckets[0].moisture.max, 50.0);
+        assert_eq!(buckets[1].start, 86400 * 2);
+    }
+
+    #[test]
+    fn should_integrate_daily_light() {
+        // 10000 lux during 12 hours around noon of the first day, then a
+        // sunrise split at midnight
+        let mut entries: Vec<_> = (6..=18)
+            .map(|hour| entry(hour * 3600, 200, 10000, 30, 400))
+            .collect();
+        entries.push(entry(86400 - 3600, 200, 0, 30, 400));
+        entries.push(entry(86400 + 3600, 200, 2000, 30, 400));
+        // gap of more than the maximum, not integrated
+        entries.push(entry(86400 * 2, 200, 50000, 30, 400));
+
+        let days = daily_light_integral(&entries, Duration::from_secs(3 * 3600));
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].start, 0);
+        assert_eq!(days[0].covered, 12 * 3600 + 3600);
+        let expected = (10000.0 * 12.0 + 500.0) * 3600.0 * 0.0185 / 1e6;
+        assert!((days[0].dli - expected).abs() < 0.001);
+        assert_eq!(days[1].start, 86400);
+        assert_eq!(days[1].covered, 3600);
+        assert!((days[1].dli - 1500.0 * 3600.0 * 0.0185 / 1e6).abs() < 0.001);
+    }
+
+    #[test]
+    fn should_detect_waterings() {
+        let moistures = [40, 38, 37, 35, 36, 34, 20, 18, 30, 45, 50, 49, 47, 46, 44];
+        let entries: Vec<_> = moistures
+            .iter()
+            .enumerate()
+            .map(|(index, moisture)| entry(index as u32 * 43200, 200, 0, *moisture, 400))
+            .collect();
+
+        let analysis = detect_waterings(&entries, 10);
+        assert_eq!(analysis.waterings.len(), 1);
+        let watering = analysis.waterings[0];
+        assert_eq!((watering.start, watering.end), (7 * 43200, 10 * 43200));
+        assert_eq!((watering.before, watering.after), (18, 50));
+        assert_eq!(watering.increase(), 32);
+
+        assert_eq!(analysis.drying.len(), 2);
+        assert_eq!((analysis.drying[0].from, analysis.drying[0].to), (40, 18));
+        assert!((analysis.drying[0].rate() - 22.0 / 3.5).abs() < 0.001);
+        assert_eq!((analysis.drying[1].from, analysis.drying[1].to), (50, 44));
+        assert!((analysis.drying[1].rate() - 3.0).abs() < 0.001);
+    }
+}
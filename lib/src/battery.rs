@@ -0,0 +1,83 @@
+/// State of the battery of a device, from its level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BatteryState {
+    /// At or below the critical threshold, the device may stop anytime
+    Critical,
+    /// At or below the low threshold, the battery should be replaced soon
+    Low,
+    Ok,
+}
+
+impl std::fmt::Display for BatteryState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Critical => "critical",
+            Self::Low => "low",
+            Self::Ok => "ok",
+        })
+    }
+}
+
+/// Battery levels, in %, below which the battery is considered low or critical.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BatteryThresholds {
+    low: u8,
+    critical: u8,
+}
+
+impl Default for BatteryThresholds {
+    fn default() -> Self {
+        Self {
+            low: 20,
+            critical: 10,
+        }
+    }
+}
+
+impl BatteryThresholds {
+    pub fn with_low(mut self, value: u8) -> Self {
+        self.low = value;
+        self
+    }
+
+    pub fn with_critical(mut self, value: u8) -> Self {
+        self.critical = value;
+        self
+    }
+
+    pub fn low(&self) -> u8 {
+        self.low
+    }
+
+    pub fn critical(&self) -> u8 {
+        self.critical
+    }
+
+    /// State of a battery with the given level in %.
+    pub fn state(&self, level: u8) -> BatteryState {
+        if level <= self.critical {
+            BatteryState::Critical
+        } else if level <= self.low {
+            BatteryState::Low
+        } else {
+            BatteryState::Ok
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BatteryState, BatteryThresholds};
+
+    #[test]
+    fn should_classify_battery_level() {
+        let thresholds = BatteryThresholds::default();
+        assert_eq!(thresholds.state(100), BatteryState::Ok);
+        assert_eq!(thresholds.state(20), BatteryState::Low);
+        assert_eq!(thresholds.state(10), BatteryState::Critical);
+        let thresholds = thresholds.with_low(50).with_critical(5);
+        assert_eq!(thresholds.state(20), BatteryState::Low);
+        assert_eq!(thresholds.state(5), BatteryState::Critical);
+    }
+}
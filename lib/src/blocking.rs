@@ -0,0 +1,275 @@
+//! Blocking API, running the async one on an internal runtime, for the applications and
+//! scripts that aren't async.
+//!
+//! ```no_run
+//! use bluer_miflora::blocking::Session;
+//!
+//! let session = Session::new()?;
+//! let miflora = session.miflora("C4:7C:8D:6A:3E:1F".parse().unwrap())?;
+//! let values = miflora.with_connection(|miflora| miflora.read_realtime_values())?;
+//! println!("temperature: {}", values.temperature_celsius());
+//! # Ok::<(), bluer_miflora::Error>(())
+//! ```
+
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use bluer::{Adapter, Address};
+use futures::{pin_mut, StreamExt};
+use tokio::runtime::Runtime;
+
+use crate::{
+    BluerClient, DeviceInfo, Error, FirmwareVersion, GattClient, HistoricalEntry, HistorySet,
+    Model, RealtimeEntry, Snapshot, System,
+};
+
+fn runtime() -> Arc<Runtime> {
+    Arc::new(
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("unable to create the runtime"),
+    )
+}
+
+/// Connection to BlueZ through an adapter, the mifloras created from it sharing its runtime.
+#[derive(Clone, Debug)]
+pub struct Session {
+    runtime: Arc<Runtime>,
+    adapter: Adapter,
+}
+
+impl Session {
+    /// Opens a session on the default adapter.
+    pub fn new() -> Result<Self, Error> {
+        Self::open(None)
+    }
+
+    /// Opens a session on the adapter with the given name, like `hci0`, or address.
+    pub fn with_adapter(name: &str) -> Result<Self, Error> {
+        Self::open(Some(name))
+    }
+
+    fn open(name: Option<&str>) -> Result<Self, Error> {
+        let runtime = runtime();
+        let adapter = runtime.block_on(async {
+            let session = bluer::Session::new()
+                .await
+                .map_err(|err| Error::CommandFailed { cause: err })?;
+            let adapter = match name {
+                Some(name) => crate::select_adapter(&session, name).await?,
+                None => session
+                    .default_adapter()
+                    .await
+                    .map_err(|err| Error::CommandFailed { cause: err })?,
+            };
+            adapter
+                .set_powered(true)
+                .await
+                .map_err(|err| Error::CommandFailed { cause: err })?;
+            Ok::<_, Error>(adapter)
+        })?;
+        Ok(Self { runtime, adapter })
+    }
+
+    pub fn adapter(&self) -> &Adapter {
+        &self.adapter
+    }
+
+    /// Finds the miflora with the given address, discovered by the adapter.
+    pub fn miflora(&self, address: Address) -> Result<Miflora, Error> {
+        let inner = self
+            .runtime
+            .block_on(crate::Miflora::try_from_adapter(&self.adapter, address))?;
+        Ok(Miflora {
+            runtime: self.runtime.clone(),
+            inner,
+        })
+    }
+
+    /// Discovers the mifloras around for the given duration.
+    pub fn scan(&self, duration: Duration) -> Result<Vec<Miflora>, Error> {
+        let found = self.runtime.block_on(async {
+            let deadline = tokio::time::Instant::now() + duration;
+            let devices = crate::scan(&self.adapter);
+            pin_mut!(devices);
+            let mut found = Vec::new();
+            while let Ok(Some(item)) = tokio::time::timeout_at(deadline, devices.next()).await {
+                found.push(item?);
+            }
+            Ok::<_, Error>(found)
+        })?;
+        Ok(found
+            .into_iter()
+            .map(|inner| Miflora {
+                runtime: self.runtime.clone(),
+                inner,
+            })
+            .collect())
+    }
+}
+
+/// Blocking version of [`crate::Miflora`], see [`crate::Miflora::blocking`].
+#[derive(Clone, Debug)]
+pub struct Miflora<G: GattClient = BluerClient> {
+    runtime: Arc<Runtime>,
+    inner: crate::Miflora<G>,
+}
+
+impl<G: GattClient> Miflora<G> {
+    pub(crate) fn new(inner: crate::Miflora<G>) -> Self {
+        Self {
+            runtime: runtime(),
+            inner,
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        self.inner.address()
+    }
+
+    pub fn model(&self) -> Model {
+        self.inner.model()
+    }
+
+    /// Async miflora wrapped by this one.
+    pub fn inner(&self) -> &crate::Miflora<G> {
+        &self.inner
+    }
+
+    pub fn is_connected(&self) -> Result<bool, Error> {
+        self.runtime.block_on(self.inner.is_connected())
+    }
+
+    /// Connects to the device, retrying with the retry policy, see
+    /// [`crate::Miflora::try_connect`].
+    pub fn connect(&self) -> Result<(), Error> {
+        self.runtime.block_on(self.inner.try_connect())
+    }
+
+    pub fn disconnect(&self) -> Result<(), Error> {
+        self.runtime.block_on(self.inner.try_disconnect())
+    }
+
+    /// Connects to the device, runs the function and disconnects, even when it fails.
+    pub fn with_connection<F, T>(&self, func: F) -> Result<T, Error>
+    where
+        F: FnOnce(&Self) -> Result<T, Error>,
+    {
+        self.connect()?;
+        let result = func(self);
+        let disconnected = self.disconnect();
+        let value = result?;
+        disconnected?;
+        Ok(value)
+    }
+
+    pub fn read_system(&self) -> Result<System, Error> {
+        self.runtime.block_on(self.inner.read_system())
+    }
+
+    pub fn read_battery(&self) -> Result<u8, Error> {
+        self.runtime.block_on(self.inner.read_battery())
+    }
+
+    pub fn read_firmware(&self) -> Result<FirmwareVersion, Error> {
+        self.runtime.block_on(self.inner.read_firmware())
+    }
+
+    pub fn read_device_info(&self) -> Result<DeviceInfo, Error> {
+        self.runtime.block_on(self.inner.read_device_info())
+    }
+
+    pub fn read_device_name(&self) -> Result<Option<String>, Error> {
+        self.runtime.block_on(self.inner.read_device_name())
+    }
+
+    pub fn set_device_name(&self, name: &str) -> Result<(), Error> {
+        self.runtime.block_on(self.inner.set_device_name(name))
+    }
+
+    pub fn read_realtime_values(&self) -> Result<RealtimeEntry, Error> {
+        self.runtime.block_on(self.inner.read_realtime_values())
+    }
+
+    pub fn read_realtime_values_once(&self) -> Result<RealtimeEntry, Error> {
+        self.runtime
+            .block_on(self.inner.read_realtime_values_once())
+    }
+
+    pub fn read_all(&self, with_history_count: bool) -> Result<Snapshot, Error> {
+        self.runtime
+            .block_on(self.inner.read_all(with_history_count))
+    }
+
+    pub fn read_epoch_time(&self) -> Result<u64, Error> {
+        self.runtime.block_on(self.inner.read_epoch_time())
+    }
+
+    /// Sets the device clock, see [`crate::Miflora::set_device_time`].
+    pub fn set_device_time(&self, time: SystemTime) -> Result<(), Error> {
+        self.runtime.block_on(self.inner.set_device_time(time))
+    }
+
+    pub fn history_count(&self) -> Result<u32, Error> {
+        self.runtime.block_on(self.inner.history_count())
+    }
+
+    pub fn read_historical_values(&self) -> Result<Vec<HistoricalEntry>, Error> {
+        self.runtime.block_on(self.inner.read_historical_values())
+    }
+
+    pub fn read_history_set(&self) -> Result<HistorySet, Error> {
+        self.runtime.block_on(self.inner.read_history_set())
+    }
+
+    pub fn read_historical_values_since(
+        &self,
+        timestamp: u64,
+    ) -> Result<Vec<HistoricalEntry>, Error> {
+        self.runtime
+            .block_on(self.inner.read_historical_values_since(timestamp))
+    }
+
+    /// Clears the history of the device, see [`crate::Miflora::force_clear_history`].
+    pub fn force_clear_history(&self) -> Result<(), Error> {
+        self.runtime.block_on(self.inner.force_clear_history())
+    }
+
+    pub fn blink_led(&self) -> Result<(), Error> {
+        self.runtime.block_on(self.inner.blink_led())
+    }
+
+    pub fn set_realtime_data_mode(&self, enabled: bool) -> Result<(), Error> {
+        self.runtime
+            .block_on(self.inner.set_realtime_data_mode(enabled))
+    }
+}
+
+impl Miflora {
+    /// Signal strength of the last advertisement received, in dBm.
+    pub fn rssi(&self) -> Result<Option<i16>, Error> {
+        self.runtime.block_on(self.inner.rssi())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::FakeMiflora;
+
+    #[test]
+    fn should_read_without_runtime() {
+        let fake = FakeMiflora::default()
+            .with_battery(42)
+            .with_realtime(190, 300, 25, 150);
+        let miflora = fake.miflora().blocking();
+        let (battery, values) = miflora
+            .with_connection(|miflora| {
+                Ok((miflora.read_battery()?, miflora.read_realtime_values()?))
+            })
+            .unwrap();
+        assert_eq!(battery, 42);
+        assert_eq!(values.moisture(), 25);
+        assert!(!fake.is_connected_now());
+    }
+}
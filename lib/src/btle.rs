@@ -0,0 +1,164 @@
+use bluer::{Address, Uuid};
+use btleplug::api::{Characteristic, Peripheral as _, WriteType};
+use btleplug::platform::Peripheral;
+use futures::stream::{BoxStream, StreamExt};
+
+use crate::{Error, GattClient, Miflora, MifloraBuilder};
+
+/// Client communicating with the devices through btleplug, which supports the native
+/// bluetooth stacks of macOS and Windows.
+///
+/// On macOS, the address of the peripherals isn't exposed and is reported as
+/// `00:00:00:00:00:00`.
+#[derive(Clone, Debug)]
+pub struct BtleplugClient {
+    peripheral: Peripheral,
+}
+
+impl BtleplugClient {
+    pub fn new(peripheral: Peripheral) -> Self {
+        Self { peripheral }
+    }
+
+    pub fn peripheral(&self) -> &Peripheral {
+        &self.peripheral
+    }
+
+    /// Finds the characteristic, discovering the services if not done yet.
+    async fn characteristic(
+        &self,
+        service_id: Uuid,
+        char_id: Uuid,
+    ) -> Result<Characteristic, Error> {
+        let find = |peripheral: &Peripheral| {
+            peripheral
+                .characteristics()
+                .into_iter()
+                .find(|item| item.service_uuid == service_id && item.uuid == char_id)
+        };
+        if let Some(found) = find(&self.peripheral) {
+            return Ok(found);
+        }
+        self.peripheral
+            .discover_services()
+            .await
+            .map_err(|err| Error::CommandFailed {
+                cause: bluer_error(err),
+            })?;
+        find(&self.peripheral).ok_or_else(|| Error::CharacteristicNotFound {
+            characteristic_id: char_id,
+            service_id,
+            cause: bluer::Error {
+                kind: bluer::ErrorKind::NotFound,
+                message: "characteristic not found".into(),
+            },
+        })
+    }
+}
+
+/// Converts the error to the bluer errors carried by [`Error`], keeping the
+/// disconnections detectable by [`Error::is_not_connected`].
+fn bluer_error(err: btleplug::Error) -> bluer::Error {
+    let kind = match err {
+        btleplug::Error::PermissionDenied => bluer::ErrorKind::NotPermitted,
+        btleplug::Error::DeviceNotFound | btleplug::Error::NoSuchCharacteristic => {
+            bluer::ErrorKind::NotFound
+        }
+        btleplug::Error::NotSupported(_) => bluer::ErrorKind::NotSupported,
+        _ => bluer::ErrorKind::Failed,
+    };
+    bluer::Error {
+        kind,
+        message: err.to_string(),
+    }
+}
+
+impl GattClient for BtleplugClient {
+    fn address(&self) -> Address {
+        Address::new(self.peripheral.address().into_inner())
+    }
+
+    async fn is_connected(&self) -> Result<bool, Error> {
+        self.peripheral
+            .is_connected()
+            .await
+            .map_err(|err| Error::CommandFailed {
+                cause: bluer_error(err),
+            })
+    }
+
+    async fn connect(&self) -> Result<(), Error> {
+        self.peripheral
+            .connect()
+            .await
+            .map_err(|err| Error::CommandFailed {
+                cause: bluer_error(err),
+            })
+    }
+
+    async fn disconnect(&self) -> Result<(), Error> {
+        self.peripheral
+            .disconnect()
+            .await
+            .map_err(|err| Error::CommandFailed {
+                cause: bluer_error(err),
+            })
+    }
+
+    async fn read(&self, service_id: Uuid, char_id: Uuid) -> Result<Vec<u8>, Error> {
+        let char = self.characteristic(service_id, char_id).await?;
+        self.peripheral
+            .read(&char)
+            .await
+            .map_err(|err| Error::UnableToRead {
+                characteristic_id: char_id,
+                service_id,
+                cause: bluer_error(err),
+            })
+    }
+
+    async fn write(&self, service_id: Uuid, char_id: Uuid, payload: &[u8]) -> Result<(), Error> {
+        let char = self.characteristic(service_id, char_id).await?;
+        self.peripheral
+            .write(&char, payload, WriteType::WithResponse)
+            .await
+            .map_err(|err| Error::UnableToWrite {
+                characteristic_id: char_id,
+                service_id,
+                cause: bluer_error(err),
+            })
+    }
+
+    async fn subscribe(
+        &self,
+        service_id: Uuid,
+        char_id: Uuid,
+    ) -> Result<BoxStream<'static, Vec<u8>>, Error> {
+        let char = self.characteristic(service_id, char_id).await?;
+        let subscribe_error = |err| Error::UnableToSubscribe {
+            characteristic_id: char_id,
+            service_id,
+            cause: bluer_error(err),
+        };
+        // the notifications of all the characteristics go through the same stream
+        let notifications = self
+            .peripheral
+            .notifications()
+            .await
+            .map_err(subscribe_error)?;
+        self.peripheral
+            .subscribe(&char)
+            .await
+            .map_err(subscribe_error)?;
+        Ok(notifications
+            .filter(move |notification| futures::future::ready(notification.uuid == char_id))
+            .map(|notification| notification.value)
+            .boxed())
+    }
+}
+
+impl From<Peripheral> for Miflora<BtleplugClient> {
+    fn from(value: Peripheral) -> Self {
+        MifloraBuilder::from_client(BtleplugClient::new(value)).build()
+    }
+}
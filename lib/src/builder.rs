@@ -0,0 +1,180 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bluer::Device;
+
+use crate::dump::RawDump;
+use crate::observer::NoObserver;
+use crate::{
+    BluerClient, Clock, GattClient, GattOptions, Miflora, Model, Observer, RetryPolicy, SystemClock,
+};
+
+/// Whether the values written to the device are read back to be checked.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum WriteVerification {
+    /// Reads the value back and fails when it doesn't match
+    #[default]
+    Always,
+    /// Doesn't read the value back, saving a round trip
+    Never,
+    /// Reads the value back and only logs a warning when it doesn't match, for the clones
+    /// that don't echo the written values
+    Lenient,
+}
+
+impl WriteVerification {
+    pub(crate) fn is_enabled(self) -> bool {
+        self != Self::Never
+    }
+}
+
+/// Builder to configure how a [`Miflora`] communicates with the device.
+#[derive(Debug)]
+pub struct MifloraBuilder<G: GattClient = BluerClient> {
+    client: G,
+    model: Model,
+    retry_policy: RetryPolicy,
+    gatt_retry_policy: RetryPolicy,
+    operation_timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    write_verification: WriteVerification,
+    auto_disable_realtime: bool,
+    reject_implausible: bool,
+    clock: Arc<dyn Clock>,
+    raw_dump: Option<PathBuf>,
+    observer: Arc<dyn Observer>,
+}
+
+impl MifloraBuilder {
+    pub fn new(device: Device) -> Self {
+        Self::from_client(BluerClient::new(device))
+    }
+}
+
+impl<G: GattClient> MifloraBuilder<G> {
+    /// Communicates with the device through the given client instead of BlueZ.
+    pub fn from_client(client: G) -> Self {
+        Self {
+            client,
+            model: Model::default(),
+            retry_policy: RetryPolicy::default(),
+            gatt_retry_policy: RetryPolicy::none(),
+            operation_timeout: None,
+            connect_timeout: None,
+            write_verification: WriteVerification::default(),
+            auto_disable_realtime: false,
+            reject_implausible: false,
+            clock: Arc::new(SystemClock),
+            raw_dump: None,
+            observer: Arc::new(NoObserver),
+        }
+    }
+
+    /// Model of the device, detected from its advertisement by [`Miflora::try_from_device`].
+    pub fn with_model(mut self, value: Model) -> Self {
+        self.model = value;
+        self
+    }
+
+    /// Policy used when connecting to and disconnecting from the device.
+    pub fn with_retry_policy(mut self, value: RetryPolicy) -> Self {
+        self.retry_policy = value;
+        self
+    }
+
+    /// Policy used for each read or write of a characteristic, no retry by default.
+    pub fn with_gatt_retry_policy(mut self, value: RetryPolicy) -> Self {
+        self.gatt_retry_policy = value;
+        self
+    }
+
+    /// Maximum duration of each connection attempt and each read or write of a
+    /// characteristic, no timeout by default.
+    pub fn with_operation_timeout(mut self, value: Duration) -> Self {
+        self.operation_timeout = Some(value);
+        self
+    }
+
+    /// Maximum duration of each connection attempt, the operation timeout by default.
+    ///
+    /// Connecting usually takes longer than reading a characteristic, especially when the
+    /// device is far from the adapter.
+    pub fn with_connect_timeout(mut self, value: Duration) -> Self {
+        self.connect_timeout = Some(value);
+        self
+    }
+
+    /// Whether the values written to the device are read back to be checked, enabled by
+    /// default, see [`Self::with_write_verification`].
+    pub fn with_verify_writes(self, value: bool) -> Self {
+        self.with_write_verification(if value {
+            WriteVerification::Always
+        } else {
+            WriteVerification::Never
+        })
+    }
+
+    /// How the values written to the device are checked, [`WriteVerification::Always`] by
+    /// default.
+    pub fn with_write_verification(mut self, value: WriteVerification) -> Self {
+        self.write_verification = value;
+        self
+    }
+
+    /// Whether the realtime mode is disabled after reading the realtime values, disabled
+    /// by default.
+    pub fn with_auto_disable_realtime(mut self, value: bool) -> Self {
+        self.auto_disable_realtime = value;
+        self
+    }
+
+    /// Whether the realtime values out of the ranges the sensor can measure are read
+    /// again, according to the retry policy, disabled by default.
+    pub fn with_reject_implausible(mut self, value: bool) -> Self {
+        self.reject_implausible = value;
+        self
+    }
+
+    /// Clock used to compute the timestamps of the historical entries.
+    pub fn with_clock<C: Clock + 'static>(mut self, value: C) -> Self {
+        self.clock = Arc::new(value);
+        self
+    }
+
+    /// Notifies the observer of the connections, reads, errors and history progress.
+    pub fn with_observer<O: Observer + 'static>(mut self, value: O) -> Self {
+        self.observer = Arc::new(value);
+        self
+    }
+
+    /// Directory each payload read from the device is written to, in its own file, to
+    /// capture the exchanges with an unusual firmware.
+    pub fn with_raw_dump<P: Into<PathBuf>>(mut self, directory: P) -> Self {
+        self.raw_dump = Some(directory.into());
+        self
+    }
+
+    pub fn build(self) -> Miflora<G> {
+        Miflora {
+            client: self.client,
+            model: self.model,
+            retry_policy: self.retry_policy,
+            gatt: GattOptions {
+                retry_policy: self.gatt_retry_policy,
+                timeout: self.operation_timeout,
+                dump: self
+                    .raw_dump
+                    .map(|directory| Arc::new(RawDump::new(directory, self.clock.clone()))),
+                observer: self.observer,
+            },
+            connect_timeout: self.connect_timeout.or(self.operation_timeout),
+            write_verification: self.write_verification,
+            auto_disable_realtime: self.auto_disable_realtime,
+            reject_implausible: self.reject_implausible,
+            clock: self.clock,
+            epoch: Default::default(),
+            history_open: Default::default(),
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use std::time::SystemTime;
+
+/// Source of the current time, used to compute the timestamps of the historical entries.
+pub trait Clock: std::fmt::Debug + Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+/// Clock relying on the system time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
@@ -0,0 +1,52 @@
+use miflora_protocol::PnpId;
+
+/// Values of the standard device information service, each of them being optional
+/// since the firmwares don't expose all the characteristics.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceInfo {
+    pub(crate) manufacturer: Option<String>,
+    pub(crate) model_number: Option<String>,
+    pub(crate) serial_number: Option<String>,
+    pub(crate) hardware_revision: Option<String>,
+    pub(crate) firmware_revision: Option<String>,
+    pub(crate) software_revision: Option<String>,
+    pub(crate) pnp_id: Option<PnpId>,
+}
+
+impl DeviceInfo {
+    pub fn manufacturer(&self) -> Option<&str> {
+        self.manufacturer.as_deref()
+    }
+
+    pub fn model_number(&self) -> Option<&str> {
+        self.model_number.as_deref()
+    }
+
+    pub fn serial_number(&self) -> Option<&str> {
+        self.serial_number.as_deref()
+    }
+
+    pub fn hardware_revision(&self) -> Option<&str> {
+        self.hardware_revision.as_deref()
+    }
+
+    pub fn firmware_revision(&self) -> Option<&str> {
+        self.firmware_revision.as_deref()
+    }
+
+    pub fn software_revision(&self) -> Option<&str> {
+        self.software_revision.as_deref()
+    }
+
+    pub fn pnp_id(&self) -> Option<&PnpId> {
+        self.pnp_id.as_ref()
+    }
+}
+
+/// Decodes a string characteristic, the devices pad some of them with null bytes.
+pub(crate) fn decode_string(data: &[u8]) -> Option<String> {
+    let value = String::from_utf8_lossy(data);
+    let value = value.trim_end_matches('\0').trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
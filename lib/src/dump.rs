@@ -0,0 +1,359 @@
+use std::fmt;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use bluer::{Address, Uuid};
+use miflora_protocol::{
+    decode_history_length, decode_uptime, CHARACTERISTIC_DATA_UUID,
+    CHARACTERISTIC_DEVICE_NAME_UUID, CHARACTERISTIC_FIRMWARE_UUID,
+    CHARACTERISTIC_HISTORY_CTRL_UUID, CHARACTERISTIC_HISTORY_READ_UUID,
+    CHARACTERISTIC_HISTORY_TIME_UUID, CHARACTERISTIC_MODE_UUID, CMD_HISTORY_READ_INIT,
+    HISTORY_PAYLOAD_LENGTH,
+};
+
+use crate::device_info::decode_string;
+use crate::{Clock, EpochTime, Error, Hex, HistoricalEntry, Model, RealtimeEntry, System};
+
+/// Characteristic a raw payload was read from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PayloadKind {
+    /// Battery level and firmware version
+    System,
+    /// Realtime values, read or notified
+    Realtime,
+    /// Mode of the device
+    Mode,
+    /// Entry of the history, or its length when dumped by the previous versions and not
+    /// 16 bytes long
+    History,
+    /// Length of the history, read after the init command
+    HistoryHeader,
+    /// Uptime of the device
+    Time,
+    DeviceName,
+}
+
+impl PayloadKind {
+    /// Kind of the payloads of the characteristic, if worth dumping.
+    pub fn from_characteristic(char_id: Uuid) -> Option<Self> {
+        match char_id {
+            CHARACTERISTIC_FIRMWARE_UUID => Some(Self::System),
+            CHARACTERISTIC_DATA_UUID => Some(Self::Realtime),
+            CHARACTERISTIC_MODE_UUID => Some(Self::Mode),
+            CHARACTERISTIC_HISTORY_READ_UUID => Some(Self::History),
+            CHARACTERISTIC_HISTORY_TIME_UUID => Some(Self::Time),
+            CHARACTERISTIC_DEVICE_NAME_UUID => Some(Self::DeviceName),
+            _ => None,
+        }
+    }
+
+    /// Name used in the file names.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::System => "system",
+            Self::Realtime => "realtime",
+            Self::Mode => "mode",
+            Self::History => "history",
+            Self::HistoryHeader => "history-header",
+            Self::Time => "time",
+            Self::DeviceName => "device-name",
+        }
+    }
+
+    pub fn from_name(value: &str) -> Option<Self> {
+        [
+            Self::System,
+            Self::Realtime,
+            Self::Mode,
+            Self::History,
+            Self::HistoryHeader,
+            Self::Time,
+            Self::DeviceName,
+        ]
+        .into_iter()
+        .find(|kind| kind.name() == value)
+    }
+}
+
+/// Payload loaded from a file written with [`MifloraBuilder::with_raw_dump`](crate::MifloraBuilder::with_raw_dump).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawPayload {
+    pub address: Address,
+    /// Time the payload was read, in milliseconds since the unix epoch
+    pub timestamp: u64,
+    /// Position of the payload among the ones dumped by the same device
+    pub sequence: u64,
+    pub kind: PayloadKind,
+    pub payload: Vec<u8>,
+}
+
+impl RawPayload {
+    /// Loads the payload, its details coming from the name of the file.
+    pub fn load<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let invalid = |reason: &str| {
+            IoError::new(
+                IoErrorKind::InvalidData,
+                format!("{}: {reason}", path.display()),
+            )
+        };
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".bin"))
+            .ok_or_else(|| invalid("not a dumped payload"))?;
+        let mut parts = name.splitn(4, '_');
+        let mut next = |reason| parts.next().ok_or_else(|| invalid(reason));
+        let address = next("missing address")?
+            .replace('-', ":")
+            .parse()
+            .map_err(|_| invalid("invalid address"))?;
+        let timestamp = next("missing timestamp")?
+            .parse()
+            .map_err(|_| invalid("invalid timestamp"))?;
+        let sequence = next("missing sequence")?
+            .parse()
+            .map_err(|_| invalid("invalid sequence"))?;
+        let kind =
+            PayloadKind::from_name(next("missing kind")?).ok_or_else(|| invalid("unknown kind"))?;
+        Ok(Self {
+            address,
+            timestamp,
+            sequence,
+            kind,
+            payload: std::fs::read(path)?,
+        })
+    }
+
+    /// Loads the payloads dumped in the directory, in the order they were read, ignoring
+    /// the other files.
+    pub fn load_dir<P: AsRef<Path>>(directory: P) -> std::io::Result<Vec<Self>> {
+        let mut payloads = Vec::new();
+        for entry in std::fs::read_dir(directory)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "bin") {
+                payloads.push(Self::load(path)?);
+            }
+        }
+        payloads.sort_by_key(|payload| (payload.timestamp, payload.sequence));
+        Ok(payloads)
+    }
+
+    /// Decodes the payload with the parsers used when communicating with the devices, the
+    /// history entries being timestamped with the given boot time.
+    pub fn decode(&self, model: Model, epoch: EpochTime) -> Result<DecodedPayload, Error> {
+        let payload = self.payload.clone();
+        Ok(match self.kind {
+            PayloadKind::System => DecodedPayload::System(System::try_from(payload)?),
+            PayloadKind::Realtime => {
+                DecodedPayload::Realtime(RealtimeEntry::try_new(payload, model)?)
+            }
+            PayloadKind::Mode => DecodedPayload::Mode(payload),
+            PayloadKind::History if payload.len() == HISTORY_PAYLOAD_LENGTH => {
+                DecodedPayload::HistoryEntry(HistoricalEntry::try_new(payload, model, epoch)?)
+            }
+            PayloadKind::History | PayloadKind::HistoryHeader => {
+                DecodedPayload::HistoryLength(decode_history_length(&payload, model)?)
+            }
+            PayloadKind::Time => DecodedPayload::Uptime(decode_uptime(&payload)?),
+            PayloadKind::DeviceName => {
+                DecodedPayload::DeviceName(decode_string(&payload).unwrap_or_default())
+            }
+        })
+    }
+
+    /// Boot time of the device, when the payload is its uptime.
+    pub fn boot_time(&self) -> Option<EpochTime> {
+        match self.kind {
+            PayloadKind::Time => {
+                let uptime = decode_uptime(&self.payload).ok()?;
+                Some(EpochTime::exact(
+                    (self.timestamp / 1000).saturating_sub(uptime as u64),
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Values of a [`RawPayload`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum DecodedPayload {
+    System(System),
+    Realtime(RealtimeEntry),
+    /// Mode as written by the last command
+    Mode(Vec<u8>),
+    /// Number of entries in the history
+    HistoryLength(u32),
+    HistoryEntry(HistoricalEntry),
+    /// Seconds elapsed since the device booted
+    Uptime(u32),
+    DeviceName(String),
+}
+
+impl fmt::Display for DecodedPayload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::System(system) => system.fmt(f),
+            Self::Realtime(entry) => entry.fmt(f),
+            Self::Mode(mode) => write!(f, "mode {}", Hex(mode)),
+            Self::HistoryLength(length) => write!(f, "{length} entries in the history"),
+            Self::HistoryEntry(entry) => entry.fmt(f),
+            Self::Uptime(uptime) => write!(f, "uptime {uptime}s"),
+            Self::DeviceName(name) => write!(f, "name {name:?}"),
+        }
+    }
+}
+
+/// Writes each payload read from the devices to its own file, to capture the exchanges with
+/// an unusual firmware.
+///
+/// The files are named `{address}_{timestamp}_{sequence}_{kind}.bin`, with the address
+/// separated by dashes, the timestamp in milliseconds since the unix epoch and the sequence
+/// keeping the order of the payloads read during the same millisecond.
+#[derive(Debug)]
+pub(crate) struct RawDump {
+    directory: PathBuf,
+    clock: Arc<dyn Clock>,
+    sequence: AtomicU64,
+    /// Whether the last history command is the init one, the header and the entries being
+    /// read from the same characteristic
+    history_header: AtomicBool,
+}
+
+impl RawDump {
+    pub(crate) fn new(directory: PathBuf, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            directory,
+            clock,
+            sequence: AtomicU64::new(0),
+            history_header: AtomicBool::new(false),
+        }
+    }
+
+    /// Keeps track of the history commands, to tell the header from the entries.
+    pub(crate) fn written(&self, char_id: Uuid, payload: &[u8]) {
+        if char_id == CHARACTERISTIC_HISTORY_CTRL_UUID {
+            // the init command, also selecting the page of the paged history
+            let header = payload.first() == Some(&CMD_HISTORY_READ_INIT[0]);
+            self.history_header.store(header, Ordering::Relaxed);
+        }
+    }
+
+    /// Writes the payload when read from a known characteristic, only logging the failures
+    /// to leave the communication with the device unaffected.
+    pub(crate) fn write(&self, address: Address, char_id: Uuid, payload: &[u8]) {
+        let kind = match PayloadKind::from_characteristic(char_id) {
+            Some(PayloadKind::History) if self.history_header.load(Ordering::Relaxed) => {
+                PayloadKind::HistoryHeader
+            }
+            Some(kind) => kind,
+            None => return,
+        };
+        let timestamp = self
+            .clock
+            .now()
+            .duration_since(UNIX_EPOCH)
+            .map(|value| value.as_millis())
+            .unwrap_or_default();
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        let address = address.to_string().replace(':', "-");
+        let path = self.directory.join(format!(
+            "{address}_{timestamp}_{sequence:06}_{}.bin",
+            kind.name()
+        ));
+        let written =
+            std::fs::create_dir_all(&self.directory).and_then(|_| std::fs::write(&path, payload));
+        if let Err(err) = written {
+            tracing::warn!(message = "unable to dump payload", path = %path.display(), cause = %err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DecodedPayload, PayloadKind, RawPayload};
+    use crate::testing::FakeMiflora;
+    use crate::{EpochTime, MifloraBuilder, Model};
+
+    #[tokio::test]
+    async fn should_dump_raw_payloads() {
+        let directory = std::env::temp_dir().join(format!("miflora-dump-{}", std::process::id()));
+        let miflora = MifloraBuilder::from_client(FakeMiflora::default())
+            .with_raw_dump(&directory)
+            .build();
+        miflora.read_system().await.unwrap();
+        miflora.read_realtime_values().await.unwrap();
+
+        let mut names: Vec<_> = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        let payloads = RawPayload::load_dir(&directory).unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+        let kinds: Vec<_> = names
+            .iter()
+            .map(|name| name.rsplit('_').next().unwrap())
+            .collect();
+        assert_eq!(kinds, ["system.bin", "mode.bin", "realtime.bin"]);
+        let address = miflora.address().to_string().replace(':', "-");
+        assert!(names.iter().all(|name| name.starts_with(&address)));
+
+        assert_eq!(payloads.len(), 3);
+        assert_eq!(payloads[0].address, miflora.address());
+        let decoded = payloads[0]
+            .decode(Model::FlowerCare, EpochTime::exact(0))
+            .unwrap();
+        assert!(matches!(decoded, DecodedPayload::System(_)));
+        let decoded = payloads[2]
+            .decode(Model::FlowerCare, EpochTime::exact(0))
+            .unwrap();
+        assert!(matches!(decoded, DecodedPayload::Realtime(_)));
+    }
+
+    #[tokio::test]
+    async fn should_tell_history_header_from_entries() {
+        let directory =
+            std::env::temp_dir().join(format!("miflora-dump-history-{}", std::process::id()));
+        let fake = FakeMiflora::default()
+            .with_uptime(7200)
+            .with_history_entry(3600, 180, 500, 30, 200)
+            .with_history_entry(7200, -15, 0, 31, 210);
+        let miflora = MifloraBuilder::from_client(fake)
+            .with_raw_dump(&directory)
+            .build();
+        miflora.connect().await.unwrap();
+        miflora.read_historical_values().await.unwrap();
+
+        let payloads = RawPayload::load_dir(&directory).unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+        let decoded: Vec<_> = payloads
+            .iter()
+            .filter(|payload| {
+                matches!(
+                    payload.kind,
+                    PayloadKind::History | PayloadKind::HistoryHeader
+                )
+            })
+            .map(|payload| {
+                payload
+                    .decode(Model::FlowerCare, EpochTime::exact(1_700_000_000))
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(decoded.len(), 3);
+        assert_eq!(decoded[0], DecodedPayload::HistoryLength(2));
+        let temperatures: Vec<_> = decoded[1..]
+            .iter()
+            .map(|decoded| match decoded {
+                DecodedPayload::HistoryEntry(entry) => entry.temperature(),
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        assert_eq!(temperatures, [180, -15]);
+    }
+}
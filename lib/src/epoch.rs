@@ -0,0 +1,141 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Number of reads of the device clock kept to estimate the epoch time.
+const MAX_SAMPLES: usize = 32;
+/// Minimum uptime covered by the reads, in seconds, before estimating the drift.
+const MIN_DRIFT_SPAN: f64 = 3600.0;
+/// Resolution of the device clock, in seconds.
+const DEVICE_CLOCK_RESOLUTION: f64 = 1.0;
+
+/// Estimation of the time the device booted, which the timestamps of its history count from.
+///
+/// The device clock drifts from the wall clock, so the estimation is averaged over the
+/// reads made with the same [`Miflora`](crate::Miflora), and the drift is estimated once
+/// the reads span at least one hour.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EpochTime {
+    timestamp: f64,
+    accuracy: Duration,
+    drift: f64,
+    samples: usize,
+}
+
+impl EpochTime {
+    /// Epoch time known without error, from the boot time in seconds since the unix epoch.
+    pub fn exact(timestamp: u64) -> Self {
+        Self {
+            timestamp: timestamp as f64,
+            accuracy: Duration::ZERO,
+            drift: 0.0,
+            samples: 0,
+        }
+    }
+
+    /// Boot time of the device, in seconds since the unix epoch.
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp.round().max(0.0) as u64
+    }
+
+    /// Boot time of the device.
+    #[cfg(feature = "chrono")]
+    pub fn datetime(&self) -> chrono::DateTime<chrono::Utc> {
+        crate::timestamp_to_datetime(self.timestamp())
+    }
+
+    /// Maximum error of the timestamp, in both directions.
+    ///
+    /// Each read is off by at most half of its round trip plus the one second resolution
+    /// of the device clock, the bound is the average of these over the reads. It doesn't
+    /// account for a drift that isn't estimated yet.
+    pub fn accuracy(&self) -> Duration {
+        self.accuracy
+    }
+
+    /// Seconds lost by the device clock for each second elapsed, positive when the device
+    /// clock runs slow and zero until the reads span at least one hour.
+    pub fn drift(&self) -> f64 {
+        self.drift
+    }
+
+    /// Number of reads of the device clock the estimation is based on.
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// Wall clock time, in seconds since the unix epoch, of the given number of seconds
+    /// since the device booted, corrected with the drift.
+    pub fn timestamp_at(&self, uptime: u32) -> u64 {
+        let uptime = f64::from(uptime);
+        (self.timestamp + uptime * (1.0 + self.drift))
+            .round()
+            .max(0.0) as u64
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Sample {
+    uptime: f64,
+    epoch: f64,
+    uncertainty: f64,
+}
+
+/// Keeps the last reads of the device clock to estimate the epoch time.
+#[derive(Debug, Default)]
+pub(crate) struct EpochEstimator {
+    samples: VecDeque<Sample>,
+}
+
+impl EpochEstimator {
+    /// Adds a read of the device clock, with the wall time in the middle of the read and
+    /// the duration of the read, both in seconds.
+    pub(crate) fn push(&mut self, uptime: u32, wall_time: f64, round_trip: f64) -> EpochTime {
+        let uptime = f64::from(uptime);
+        if self.samples.back().is_some_and(|last| uptime < last.uptime) {
+            tracing::debug!("device clock went backward, the device rebooted");
+            self.samples.clear();
+        }
+        if self.samples.len() == MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(Sample {
+            uptime,
+            epoch: wall_time - uptime,
+            uncertainty: round_trip / 2.0 + DEVICE_CLOCK_RESOLUTION,
+        });
+        self.estimate().expect("at least one sample")
+    }
+
+    /// Forgets the previous reads, after the device clock has been set.
+    pub(crate) fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Fits the epoch and the drift on the reads with a linear regression.
+    pub(crate) fn estimate(&self) -> Option<EpochTime> {
+        let first = self.samples.front()?;
+        let last = self.samples.back()?;
+        let count = self.samples.len() as f64;
+        let mean_uptime = self.samples.iter().map(|s| s.uptime).sum::<f64>() / count;
+        let mean_epoch = self.samples.iter().map(|s| s.epoch).sum::<f64>() / count;
+        let (covariance, variance) =
+            self.samples
+                .iter()
+                .fold((0.0, 0.0), |(covariance, variance), s| {
+                    let dx = s.uptime - mean_uptime;
+                    (covariance + dx * (s.epoch - mean_epoch), variance + dx * dx)
+                });
+        let drift = if last.uptime - first.uptime >= MIN_DRIFT_SPAN && variance > 0.0 {
+            covariance / variance
+        } else {
+            0.0
+        };
+        let accuracy = self.samples.iter().map(|s| s.uncertainty).sum::<f64>() / count;
+        Some(EpochTime {
+            timestamp: mean_epoch - drift * mean_uptime,
+            accuracy: Duration::from_secs_f64(accuracy),
+            drift,
+            samples: self.samples.len(),
+        })
+    }
+}
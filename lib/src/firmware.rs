@@ -0,0 +1,171 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// Version of the firmware running on the device, like `3.2.2`.
+///
+/// The versions are ordered by their numeric components, missing or invalid ones being
+/// read as 0, so the capabilities can be checked with `version >= FirmwareVersion::new(3, 1, 9)`.
+/// The text sent by the device is only kept for display, `3.2` being equal to `3.2.0`.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "String", from = "String")
+)]
+pub struct FirmwareVersion {
+    major: u16,
+    minor: u16,
+    patch: u16,
+    raw: String,
+}
+
+impl FirmwareVersion {
+    pub fn new(major: u16, minor: u16, patch: u16) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+            raw: format!("{major}.{minor}.{patch}"),
+        }
+    }
+
+    /// Decodes the version from the bytes sent by the device, which can be padded with
+    /// null bytes.
+    pub(crate) fn decode(data: &[u8]) -> Self {
+        let raw = String::from_utf8_lossy(data);
+        Self::from(raw.trim_end_matches('\0').trim())
+    }
+
+    pub fn major(&self) -> u16 {
+        self.major
+    }
+
+    pub fn minor(&self) -> u16 {
+        self.minor
+    }
+
+    pub fn patch(&self) -> u16 {
+        self.patch
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    fn components(&self) -> (u16, u16, u16) {
+        (self.major, self.minor, self.patch)
+    }
+}
+
+impl PartialEq for FirmwareVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.components() == other.components()
+    }
+}
+
+impl Eq for FirmwareVersion {}
+
+impl PartialOrd for FirmwareVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FirmwareVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.components().cmp(&other.components())
+    }
+}
+
+impl Hash for FirmwareVersion {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.components().hash(state);
+    }
+}
+
+impl From<&str> for FirmwareVersion {
+    fn from(value: &str) -> Self {
+        let mut parts = value
+            .split('.')
+            .map(|part| part.trim().parse::<u16>().unwrap_or_default());
+        Self {
+            major: parts.next().unwrap_or_default(),
+            minor: parts.next().unwrap_or_default(),
+            patch: parts.next().unwrap_or_default(),
+            raw: value.to_string(),
+        }
+    }
+}
+
+impl From<String> for FirmwareVersion {
+    fn from(value: String) -> Self {
+        Self::from(value.as_str())
+    }
+}
+
+impl From<FirmwareVersion> for String {
+    fn from(value: FirmwareVersion) -> Self {
+        value.raw
+    }
+}
+
+impl fmt::Display for FirmwareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::FirmwareVersion;
+
+    #[test]
+    fn should_parse_versions() {
+        let version = FirmwareVersion::decode(b"3.2.2\0\0");
+        assert_eq!(
+            (version.major(), version.minor(), version.patch()),
+            (3, 2, 2)
+        );
+        assert_eq!(version.as_str(), "3.2.2");
+        let version = FirmwareVersion::from("3.1");
+        assert_eq!(
+            (version.major(), version.minor(), version.patch()),
+            (3, 1, 0)
+        );
+        let version = FirmwareVersion::from("2.x.9");
+        assert_eq!(
+            (version.major(), version.minor(), version.patch()),
+            (2, 0, 9)
+        );
+        assert_eq!(version.to_string(), "2.x.9");
+    }
+
+    #[test]
+    fn should_compare_the_numeric_components() {
+        let required = FirmwareVersion::new(3, 1, 9);
+        assert!(FirmwareVersion::from("3.1.9") >= required);
+        assert!(FirmwareVersion::from("3.1.10") >= required);
+        assert!(FirmwareVersion::from("3.2.0") >= required);
+        assert!(FirmwareVersion::from("10.0.0") >= required);
+        assert!(FirmwareVersion::from("3.1.8") < required);
+        assert!(FirmwareVersion::from("2.9.99") < required);
+        assert!(FirmwareVersion::from("3.1") < required);
+    }
+
+    #[test]
+    fn should_ignore_the_text_sent_by_the_device() {
+        assert_eq!(FirmwareVersion::from("3.2"), FirmwareVersion::new(3, 2, 0));
+        assert_eq!(
+            FirmwareVersion::from(" 3.2.0 "),
+            FirmwareVersion::from("3.2.0")
+        );
+        let versions: HashSet<_> = ["3.2", "3.2.0", "3.02.0"]
+            .into_iter()
+            .map(FirmwareVersion::from)
+            .collect();
+        assert_eq!(versions.len(), 1);
+    }
+}
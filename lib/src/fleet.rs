@@ -0,0 +1,308 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bluer::{Adapter, AdapterEvent, Address};
+use futures::stream::{self, Stream, StreamExt};
+use futures::FutureExt;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::advertisement::{read_advertisement, PassiveReading};
+use crate::{Error, HistoricalEntry, Miflora, Observer, Operation, Snapshot};
+
+/// How a [`MifloraFleet`] collects the values of its devices.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CollectionStrategy {
+    /// Connects to every device at each interval to read all its values.
+    Connected { interval: Duration },
+    /// Listens to the advertisements for the realtime values, checked at each
+    /// `scan_interval`, and only connects to each device once per `connect_interval` to
+    /// read its battery and history.
+    Hybrid {
+        scan_interval: Duration,
+        connect_interval: Duration,
+    },
+}
+
+impl Default for CollectionStrategy {
+    fn default() -> Self {
+        Self::Connected {
+            interval: Duration::from_secs(600),
+        }
+    }
+}
+
+impl CollectionStrategy {
+    fn round_interval(&self) -> Duration {
+        match self {
+            Self::Connected { interval } => *interval,
+            Self::Hybrid { scan_interval, .. } => *scan_interval,
+        }
+    }
+}
+
+/// Values collected from a device, see [`MifloraFleet::collect`].
+#[derive(Clone, Debug)]
+pub enum Collected {
+    /// Value broadcast by the device in its advertisements
+    Passive(PassiveReading),
+    /// Values read while connected to the device
+    Snapshot(Snapshot),
+    /// Battery level and historical entries read while connected to the device
+    History {
+        battery: u8,
+        entries: Vec<HistoricalEntry>,
+    },
+}
+
+type DiscoveryStream = Pin<Box<dyn Stream<Item = AdapterEvent> + Send>>;
+
+/// State kept between the rounds of a collection.
+#[derive(Default)]
+struct CollectState {
+    pending: VecDeque<(Address, Result<Collected, Error>)>,
+    /// Keeps the discovery running so the advertisements are received
+    discovery: Option<DiscoveryStream>,
+    frame_counters: HashMap<Address, u8>,
+    connections: HashMap<Address, Instant>,
+    next_round: Option<Instant>,
+}
+
+/// Set of devices reachable through the same adapter, polled together.
+///
+/// The devices can be polled concurrently, but the connections are established one at a
+/// time since concurrent connection attempts through a single adapter tend to fail.
+#[derive(Debug)]
+pub struct MifloraFleet {
+    adapter: Adapter,
+    devices: BTreeMap<Address, Miflora>,
+    concurrency: usize,
+    strategy: CollectionStrategy,
+    connect_lock: Mutex<()>,
+    observer: Option<Arc<dyn Observer>>,
+}
+
+impl MifloraFleet {
+    pub fn new(adapter: Adapter) -> Self {
+        Self {
+            adapter,
+            devices: BTreeMap::new(),
+            concurrency: 1,
+            strategy: CollectionStrategy::default(),
+            connect_lock: Mutex::new(()),
+            observer: None,
+        }
+    }
+
+    /// Maximum number of devices polled at the same time, `1` by default to poll them
+    /// sequentially.
+    pub fn with_concurrency(mut self, value: usize) -> Self {
+        self.concurrency = value.max(1);
+        self
+    }
+
+    /// How the values are collected by [`MifloraFleet::collect`].
+    pub fn with_strategy(mut self, value: CollectionStrategy) -> Self {
+        self.strategy = value;
+        self
+    }
+
+    /// Observer given to the devices added with [`MifloraFleet::add`], also notified of
+    /// the advertisements that can't be read.
+    pub fn with_observer<O: Observer + 'static>(mut self, value: O) -> Self {
+        self.observer = Some(Arc::new(value));
+        self
+    }
+
+    pub fn adapter(&self) -> &Adapter {
+        &self.adapter
+    }
+
+    /// Adds the device with the given address, checking it's a miflora.
+    pub async fn add(&mut self, address: Address) -> Result<(), Error> {
+        let mut miflora = Miflora::try_from_adapter(&self.adapter, address).await?;
+        if let Some(ref observer) = self.observer {
+            miflora = Miflora::builder(miflora.client().device().clone())
+                .with_model(miflora.model())
+                .with_observer(observer.clone())
+                .build();
+        }
+        self.insert(miflora);
+        Ok(())
+    }
+
+    /// Adds an already configured device, replacing the one with the same address.
+    pub fn insert(&mut self, miflora: Miflora) -> Option<Miflora> {
+        self.devices.insert(miflora.address(), miflora)
+    }
+
+    pub fn remove(&mut self, address: &Address) -> Option<Miflora> {
+        self.devices.remove(address)
+    }
+
+    pub fn get(&self, address: &Address) -> Option<&Miflora> {
+        self.devices.get(address)
+    }
+
+    pub fn addresses(&self) -> impl Iterator<Item = &Address> {
+        self.devices.keys()
+    }
+
+    pub fn len(&self) -> usize {
+        self.devices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+
+    /// Connects to every device, runs the given function and disconnects, returning the
+    /// result for each device.
+    pub async fn poll<F, Fut, T>(&self, func: F) -> Vec<(Address, Result<T, Error>)>
+    where
+        F: Fn(Miflora) -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        self.poll_devices(self.devices.values(), &func).await
+    }
+
+    async fn poll_devices<'a, I, F, Fut, T>(
+        &self,
+        devices: I,
+        func: &F,
+    ) -> Vec<(Address, Result<T, Error>)>
+    where
+        I: Iterator<Item = &'a Miflora>,
+        F: Fn(Miflora) -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        stream::iter(devices)
+            .map(|miflora| async {
+                let result = self.poll_device(miflora, func).await;
+                if let Err(ref err) = result {
+                    tracing::warn!(message = "unable to poll device", address = %miflora.address(), cause = %err);
+                }
+                (miflora.address(), result)
+            })
+            .buffer_unordered(self.concurrency)
+            .collect()
+            .await
+    }
+
+    async fn poll_device<F, Fut, T>(&self, miflora: &Miflora, func: &F) -> Result<T, Error>
+    where
+        F: Fn(Miflora) -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        {
+            let _guard = self.connect_lock.lock().await;
+            miflora.try_connect().await?;
+        }
+        // already connected, only takes care of the disconnection
+        miflora.with_connection(func).await
+    }
+    /// Collects the values of the devices forever, following the [`CollectionStrategy`].
+    ///
+    /// With the hybrid strategy, the adapter keeps discovering devices while the stream is
+    /// alive and a failed connection is attempted again at the next round.
+    pub fn collect(&self) -> impl Stream<Item = (Address, Result<Collected, Error>)> + '_ {
+        stream::unfold(CollectState::default(), move |mut state| async move {
+            loop {
+                if let Some(item) = state.pending.pop_front() {
+                    return Some((item, state));
+                }
+                if let Some(next_round) = state.next_round {
+                    tokio::time::sleep_until(next_round).await;
+                }
+                state.next_round = Some(Instant::now() + self.strategy.round_interval());
+                self.collect_round(&mut state).await;
+            }
+        })
+    }
+
+    async fn collect_round(&self, state: &mut CollectState) {
+        let connect_interval = match self.strategy {
+            CollectionStrategy::Connected { .. } => {
+                let results = self
+                    .poll_devices(self.devices.values(), &|miflora: Miflora| async move {
+                        miflora.read_all(false).await.map(Collected::Snapshot)
+                    })
+                    .await;
+                state.pending.extend(results);
+                return;
+            }
+            CollectionStrategy::Hybrid {
+                connect_interval, ..
+            } => connect_interval,
+        };
+
+        self.keep_discovering(state).await;
+        let now = Instant::now();
+        let (due, listened): (Vec<_>, Vec<_>) = self.devices.values().partition(|miflora| {
+            state
+                .connections
+                .get(&miflora.address())
+                .is_none_or(|last| now.duration_since(*last) >= connect_interval)
+        });
+        for miflora in listened {
+            let started = Instant::now();
+            match read_advertisement(miflora.client().device()).await {
+                Ok(Some(beacon)) => {
+                    let previous = state
+                        .frame_counters
+                        .insert(miflora.address(), beacon.frame_counter());
+                    if previous == Some(beacon.frame_counter()) {
+                        continue;
+                    }
+                    if let Some(reading) = beacon.reading() {
+                        state
+                            .pending
+                            .push_back((miflora.address(), Ok(Collected::Passive(reading))));
+                    }
+                }
+                Ok(None) | Err(Error::NoServiceData) => {
+                    tracing::debug!(message = "no advertisement received", address = %miflora.address());
+                }
+                Err(err) => {
+                    if let Some(ref observer) = self.observer {
+                        observer.on_error(
+                            miflora.address(),
+                            Operation::Advertisement,
+                            &err,
+                            started.elapsed(),
+                        );
+                    }
+                    state.pending.push_back((miflora.address(), Err(err)));
+                }
+            }
+        }
+        let results = self
+            .poll_devices(due.into_iter(), &|miflora: Miflora| async move {
+                let battery = miflora.read_battery().await?;
+                let entries = miflora.read_historical_values().await?;
+                Ok(Collected::History { battery, entries })
+            })
+            .await;
+        for (address, result) in results {
+            if result.is_ok() {
+                state.connections.insert(address, now);
+            }
+            state.pending.push_back((address, result));
+        }
+    }
+
+    /// Starts the discovery if needed and drops the events received since the last round.
+    async fn keep_discovering(&self, state: &mut CollectState) {
+        if let Some(ref mut discovery) = state.discovery {
+            while let Some(Some(_)) = discovery.next().now_or_never() {}
+            return;
+        }
+        match self.adapter.discover_devices().await {
+            Ok(discovery) => state.discovery = Some(Box::pin(discovery)),
+            Err(err) => tracing::warn!(message = "unable to start discovery", cause = %err),
+        }
+    }
+}
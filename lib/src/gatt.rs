@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use bluer::gatt::remote::{Characteristic, CharacteristicWriteRequest, Service};
+use bluer::gatt::WriteOp;
+use bluer::{Address, Device, Uuid};
+use futures::stream::{BoxStream, StreamExt};
+
+use crate::Error;
+
+const WRITE_OPTS: CharacteristicWriteRequest = CharacteristicWriteRequest {
+    offset: 0,
+    op_type: WriteOp::Request,
+    prepare_authorize: false,
+    _non_exhaustive: (),
+};
+
+/// Transport used to communicate with a device, the characteristics being addressed by
+/// their service and characteristic UUIDs.
+///
+/// [`BluerClient`] talks to the devices through BlueZ, other implementations allow to
+/// test the protocol without a device or to use another stack.
+pub trait GattClient: Clone + Debug + Send + Sync + 'static {
+    fn address(&self) -> Address;
+
+    fn is_connected(&self) -> impl Future<Output = Result<bool, Error>> + Send;
+
+    fn connect(&self) -> impl Future<Output = Result<(), Error>> + Send;
+
+    fn disconnect(&self) -> impl Future<Output = Result<(), Error>> + Send;
+
+    fn read(
+        &self,
+        service_id: Uuid,
+        char_id: Uuid,
+    ) -> impl Future<Output = Result<Vec<u8>, Error>> + Send;
+
+    fn write(
+        &self,
+        service_id: Uuid,
+        char_id: Uuid,
+        payload: &[u8],
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Streams the values notified by the characteristic, until the stream is dropped.
+    fn subscribe(
+        &self,
+        service_id: Uuid,
+        char_id: Uuid,
+    ) -> impl Future<Output = Result<BoxStream<'static, Vec<u8>>, Error>> + Send;
+
+    /// Prepares the access to the given characteristics, before using them in a row.
+    fn resolve(
+        &self,
+        characteristics: &[(Uuid, Uuid)],
+    ) -> impl Future<Output = Result<(), Error>> + Send {
+        let _ = characteristics;
+        async { Ok(()) }
+    }
+}
+
+type CharacteristicCache = Arc<Mutex<HashMap<(Uuid, Uuid), Characteristic>>>;
+
+/// Client communicating with the devices through BlueZ.
+#[derive(Clone, Debug)]
+pub struct BluerClient {
+    device: Device,
+    /// Characteristics already resolved, cleared on connection and disconnection
+    characteristics: CharacteristicCache,
+}
+
+impl BluerClient {
+    pub fn new(device: Device) -> Self {
+        Self {
+            device,
+            characteristics: Default::default(),
+        }
+    }
+
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    async fn services(&self) -> Result<Vec<Service>, Error> {
+        self.device
+            .services()
+            .await
+            .map_err(|err| Error::CommandFailed { cause: err })
+    }
+
+    async fn find_characteristic(
+        services: &[Service],
+        service_id: Uuid,
+        char_id: Uuid,
+    ) -> Result<Characteristic, Error> {
+        let mut found = None;
+        for service in services {
+            let uuid = service
+                .uuid()
+                .await
+                .map_err(|err| Error::CommandFailed { cause: err })?;
+            if uuid == service_id {
+                found = Some(service);
+                break;
+            }
+        }
+        let service = found.ok_or_else(|| Error::ServiceNotFound {
+            service_id,
+            cause: bluer::Error {
+                kind: bluer::ErrorKind::NotFound,
+                message: "service not found".into(),
+            },
+        })?;
+        let characteristics = service
+            .characteristics()
+            .await
+            .map_err(|err| Error::CommandFailed { cause: err })?;
+        for characteristic in characteristics {
+            let uuid = characteristic
+                .uuid()
+                .await
+                .map_err(|err| Error::CommandFailed { cause: err })?;
+            if uuid == char_id {
+                return Ok(characteristic);
+            }
+        }
+        Err(Error::CharacteristicNotFound {
+            characteristic_id: char_id,
+            service_id,
+            cause: bluer::Error {
+                kind: bluer::ErrorKind::NotFound,
+                message: "characteristic not found".into(),
+            },
+        })
+    }
+
+    fn resolver(&self) -> CharacteristicResolver<'_> {
+        CharacteristicResolver {
+            client: self,
+            services: None,
+        }
+    }
+
+    fn cached_characteristic(&self, service_id: Uuid, char_id: Uuid) -> Option<Characteristic> {
+        self.characteristics
+            .lock()
+            .expect("characteristic cache poisoned")
+            .get(&(service_id, char_id))
+            .cloned()
+    }
+
+    fn clear_characteristic_cache(&self) {
+        self.characteristics
+            .lock()
+            .expect("characteristic cache poisoned")
+            .clear();
+    }
+
+    async fn characteristic(
+        &self,
+        service_id: Uuid,
+        char_id: Uuid,
+    ) -> Result<Characteristic, Error> {
+        self.resolver().get(service_id, char_id).await
+    }
+}
+
+impl GattClient for BluerClient {
+    fn address(&self) -> Address {
+        self.device.address()
+    }
+
+    async fn is_connected(&self) -> Result<bool, Error> {
+        self.device
+            .is_connected()
+            .await
+            .map_err(|err| Error::CommandFailed { cause: err })
+    }
+
+    async fn connect(&self) -> Result<(), Error> {
+        self.clear_characteristic_cache();
+        self.device
+            .connect()
+            .await
+            .map_err(|err| Error::CommandFailed { cause: err })
+    }
+
+    async fn disconnect(&self) -> Result<(), Error> {
+        self.clear_characteristic_cache();
+        self.device
+            .disconnect()
+            .await
+            .map_err(|err| Error::CommandFailed { cause: err })
+    }
+
+    async fn read(&self, service_id: Uuid, char_id: Uuid) -> Result<Vec<u8>, Error> {
+        let char = self.characteristic(service_id, char_id).await?;
+        char.read().await.map_err(|err| Error::UnableToRead {
+            characteristic_id: char_id,
+            service_id,
+            cause: err,
+        })
+    }
+
+    async fn write(&self, service_id: Uuid, char_id: Uuid, payload: &[u8]) -> Result<(), Error> {
+        let char = self.characteristic(service_id, char_id).await?;
+        char.write_ext(payload, &WRITE_OPTS)
+            .await
+            .map_err(|err| Error::UnableToWrite {
+                characteristic_id: char_id,
+                service_id,
+                cause: err,
+            })
+    }
+
+    async fn subscribe(
+        &self,
+        service_id: Uuid,
+        char_id: Uuid,
+    ) -> Result<BoxStream<'static, Vec<u8>>, Error> {
+        let char = self.characteristic(service_id, char_id).await?;
+        let notifications = char
+            .notify()
+            .await
+            .map_err(|err| Error::UnableToSubscribe {
+                characteristic_id: char_id,
+                service_id,
+                cause: err,
+            })?;
+        Ok(notifications.boxed())
+    }
+
+    /// Resolves the characteristics listing the services at most once.
+    async fn resolve(&self, characteristics: &[(Uuid, Uuid)]) -> Result<(), Error> {
+        let mut resolver = self.resolver();
+        for (service_id, char_id) in characteristics {
+            resolver.get(*service_id, *char_id).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Resolves characteristics from the cache of the client first and lists the services
+/// at most once otherwise.
+struct CharacteristicResolver<'a> {
+    client: &'a BluerClient,
+    services: Option<Vec<Service>>,
+}
+
+impl CharacteristicResolver<'_> {
+    async fn get(&mut self, service_id: Uuid, char_id: Uuid) -> Result<Characteristic, Error> {
+        if let Some(found) = self.client.cached_characteristic(service_id, char_id) {
+            return Ok(found);
+        }
+        let services = match self.services {
+            Some(ref services) => services,
+            None => self.services.insert(self.client.services().await?),
+        };
+        let found = BluerClient::find_characteristic(services, service_id, char_id).await?;
+        self.client
+            .characteristics
+            .lock()
+            .expect("characteristic cache poisoned")
+            .insert((service_id, char_id), found.clone());
+        Ok(found)
+    }
+}
@@ -0,0 +1,243 @@
+use std::time::Duration;
+
+use crate::HistoricalEntry;
+
+/// Period without any entry in the history.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Gap {
+    /// Unix timestamp of the last entry before the gap, in seconds
+    pub start: u64,
+    /// Unix timestamp of the first entry after the gap, in seconds
+    pub end: u64,
+}
+
+impl Gap {
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.end - self.start)
+    }
+}
+
+/// Whether the timestamp of an entry can be trusted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TimestampConfidence {
+    /// Recorded since the last boot of the device
+    #[default]
+    Reliable,
+    /// Recorded before a reboot of the device, the timestamp being computed from the
+    /// uptime of a previous boot
+    BeforeReboot,
+}
+
+/// Point of the history where the device rebooted, its uptime starting over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RebootBoundary {
+    /// Uptime of the last entry recorded before the reboot, in seconds
+    pub uptime_before: u32,
+    /// Uptime of the first entry recorded after the reboot, in seconds
+    pub uptime_after: u32,
+}
+
+/// Historical entries sorted by timestamp, without duplicates, with the gaps and reboots
+/// found in the history.
+#[derive(Clone, Debug)]
+pub struct HistorySet {
+    entries: Vec<HistoricalEntry>,
+    gaps: Vec<Gap>,
+    reboots: Vec<RebootBoundary>,
+}
+
+impl HistorySet {
+    /// Longest interval between two entries not considered as a gap, the devices
+    /// recording an entry every hour.
+    pub const DEFAULT_MAX_INTERVAL: Duration = Duration::from_secs(2 * 3600);
+
+    /// Processes the entries in the order they were read from the device, the intervals
+    /// longer than `max_interval` being reported as gaps.
+    pub fn new(mut entries: Vec<HistoricalEntry>, max_interval: Duration) -> Self {
+        let reboots = find_reboots(&entries);
+        entries.sort_by_key(HistoricalEntry::timestamp);
+        entries.dedup_by_key(|entry| entry.timestamp());
+        let gaps = entries
+            .windows(2)
+            .map(|pair| Gap {
+                start: pair[0].timestamp(),
+                end: pair[1].timestamp(),
+            })
+            .filter(|gap| gap.duration() > max_interval)
+            .collect();
+        Self {
+            entries,
+            gaps,
+            reboots,
+        }
+    }
+
+    pub fn entries(&self) -> &[HistoricalEntry] {
+        &self.entries
+    }
+
+    pub fn into_entries(self) -> Vec<HistoricalEntry> {
+        self.entries
+    }
+
+    pub fn gaps(&self) -> &[Gap] {
+        &self.gaps
+    }
+
+    /// Reboots of the device between the entries, in the order they happened.
+    pub fn reboot_boundaries(&self) -> &[RebootBoundary] {
+        &self.reboots
+    }
+}
+
+impl From<Vec<HistoricalEntry>> for HistorySet {
+    fn from(value: Vec<HistoricalEntry>) -> Self {
+        Self::new(value, Self::DEFAULT_MAX_INTERVAL)
+    }
+}
+
+/// Whether the entries are stored oldest first, the devices storing them in the order
+/// they were recorded, oldest or newest first.
+fn is_ascending(entries: &[HistoricalEntry]) -> bool {
+    entries
+        .windows(2)
+        .filter(|pair| pair[0].uptime() <= pair[1].uptime())
+        .count()
+        * 2
+        >= entries.len().saturating_sub(1)
+}
+
+/// Marks the entries of a download recorded before the last reboot of the device, where
+/// the uptime goes backwards.
+pub(crate) fn mark_before_reboot(entries: &mut [HistoricalEntry]) {
+    let older = if is_ascending(entries) {
+        match entries
+            .windows(2)
+            .rposition(|pair| pair[1].uptime() < pair[0].uptime())
+        {
+            Some(index) => 0..=index,
+            None => return,
+        }
+    } else {
+        match entries
+            .windows(2)
+            .position(|pair| pair[0].uptime() < pair[1].uptime())
+        {
+            Some(index) => index + 1..=entries.len() - 1,
+            None => return,
+        }
+    };
+    for entry in &mut entries[older] {
+        entry.confidence = TimestampConfidence::BeforeReboot;
+    }
+}
+
+/// Finds where the uptime goes backwards.
+fn find_reboots(entries: &[HistoricalEntry]) -> Vec<RebootBoundary> {
+    let ascending = is_ascending(entries);
+    let mut reboots: Vec<_> = entries
+        .windows(2)
+        .filter_map(|pair| {
+            let (older, newer) = if ascending {
+                (&pair[0], &pair[1])
+            } else {
+                (&pair[1], &pair[0])
+            };
+            (newer.uptime() < older.uptime()).then(|| RebootBoundary {
+                uptime_before: older.uptime(),
+                uptime_after: newer.uptime(),
+            })
+        })
+        .collect();
+    if !ascending {
+        reboots.reverse();
+    }
+    reboots
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{mark_before_reboot, Gap, HistorySet, RebootBoundary, TimestampConfidence};
+    use crate::{EpochTime, HistoricalEntry, Model};
+
+    fn entry(uptime: u32) -> HistoricalEntry {
+        let mut payload = vec![0; 16];
+        payload[0..4].copy_from_slice(&uptime.to_le_bytes());
+        payload[11] = 30;
+        HistoricalEntry::try_new(payload, Model::FlowerCare, EpochTime::exact(1_000_000)).unwrap()
+    }
+
+    #[test]
+    fn should_sort_and_deduplicate_entries() {
+        let set = HistorySet::from(vec![entry(7200), entry(3600), entry(7200), entry(10800)]);
+        let uptimes: Vec<_> = set.entries().iter().map(|entry| entry.uptime()).collect();
+        assert_eq!(uptimes, [3600, 7200, 10800]);
+        assert!(set.gaps().is_empty());
+    }
+
+    #[test]
+    fn should_detect_gaps_and_reboots() {
+        let set = HistorySet::new(
+            vec![
+                entry(3600),
+                entry(7200),
+                entry(36000),
+                entry(100),
+                entry(3700),
+            ],
+            Duration::from_secs(3 * 3600),
+        );
+        assert_eq!(
+            set.gaps(),
+            &[Gap {
+                start: 1_007_200,
+                end: 1_036_000
+            }]
+        );
+        assert_eq!(
+            set.reboot_boundaries(),
+            &[RebootBoundary {
+                uptime_before: 36000,
+                uptime_after: 100
+            }]
+        );
+    }
+
+    #[test]
+    fn should_compare_entries() {
+        let mut entries = vec![entry(7200), entry(3600), entry(7200)];
+        assert_eq!(entries[0], entries[2]);
+        assert_ne!(entries[0], entries[1]);
+        entries.sort();
+        assert_eq!(entries[0].uptime(), 3600);
+        let unique: std::collections::HashSet<_> = entries.into_iter().collect();
+        assert_eq!(unique.len(), 2);
+    }
+
+    #[test]
+    fn should_mark_entries_before_reboot() {
+        let confidences = |entries: &[HistoricalEntry]| {
+            entries
+                .iter()
+                .map(|entry| entry.timestamp_confidence() == TimestampConfidence::Reliable)
+                .collect::<Vec<_>>()
+        };
+
+        let mut entries = vec![entry(3600), entry(7200), entry(100), entry(3700)];
+        mark_before_reboot(&mut entries);
+        assert_eq!(confidences(&entries), [false, false, true, true]);
+
+        let mut entries = vec![entry(3700), entry(100), entry(7200), entry(3600)];
+        mark_before_reboot(&mut entries);
+        assert_eq!(confidences(&entries), [true, true, false, false]);
+
+        let mut entries = vec![entry(3600), entry(7200)];
+        mark_before_reboot(&mut entries);
+        assert_eq!(confidences(&entries), [true, true]);
+    }
+}
@@ -1,66 +1,201 @@
 use std::borrow::Cow;
-use std::time::{SystemTime, UNIX_EPOCH};
-
-use bluer::gatt::remote::{Characteristic, CharacteristicWriteRequest};
-use bluer::gatt::WriteOp;
-use bluer::{Adapter, Address, Device};
-
-// These are the services/characteristics available on a miflora
-// service=58 characteristic=64
-// service=58 characteristic=59
-// service=58 characteristic=61
-// service=49 characteristic=55
-// service=49 characteristic=52
-// service=49 characteristic=50
-// service=12 characteristic=13
-// service=35 characteristic=38
-// service=35 characteristic=42
-// service=35 characteristic=40
-// service=35 characteristic=36
-// service=35 characteristic=44
-// service=35 characteristic=46
-// service=16 characteristic=28
-// service=16 characteristic=20
-// service=16 characteristic=26
-// service=16 characteristic=17
-// service=16 characteristic=32
-// service=16 characteristic=24
-// service=16 characteristic=22
-// service=16 characteristic=30
-
-/// Device UUID prefix of miflora service
-const DEVICE_UUID_PREFIX: u32 = 0xfe95;
-const SERVICE_DATA_ID: u16 = 49;
-const CHARACTERISTIC_MODE_ID: u16 = 50;
-const CHARACTERISTIC_DATA_ID: u16 = 52;
-const CHARACTERISTIC_FIRMWARE_ID: u16 = 0x37;
-
-const SERVICE_HISTORY_ID: u16 = 58;
-const CHARACTERISTIC_HISTORY_CTRL_ID: u16 = 61; // 0x3d; // 0x3e
-const CHARACTERISTIC_HISTORY_READ_ID: u16 = 59; // 0x3b; // 0x3c
-const CHARACTERISTIC_HISTORY_TIME_ID: u16 = 64;
-
-// const CMD_BLINK_LED: [u8; 2] = [0xfd, 0xff];
-const CMD_HISTORY_READ_INIT: [u8; 3] = [0xa0, 0x00, 0x00];
-const CMD_HISTORY_READ_SUCCESS: [u8; 3] = [0xa2, 0x00, 0x00];
-// const CMD_HISTORY_READ_FAILED: [u8; 3] = [0xa3, 0x00, 0x00];
-const CMD_REALTIME_DISABLE: [u8; 2] = [0xc0, 0x1f];
-const CMD_REALTIME_ENABLE: [u8; 2] = [0xa0, 0x1f];
-
-const WRITE_OPTS: CharacteristicWriteRequest = CharacteristicWriteRequest {
-    offset: 0,
-    op_type: WriteOp::Request,
-    prepare_authorize: false,
-    _non_exhaustive: (),
+use std::future::Future;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use bluer::{Adapter, Address, Device, Uuid};
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+
+mod adapters;
+pub mod advertisement;
+pub mod analytics;
+mod battery;
+pub mod blocking;
+#[cfg(feature = "btleplug")]
+mod btle;
+mod builder;
+mod clock;
+mod device_info;
+mod dump;
+mod epoch;
+mod firmware;
+mod fleet;
+mod gatt;
+mod history;
+mod observer;
+pub mod plants;
+mod reading;
+mod recording;
+mod registry;
+mod retry;
+mod scan;
+mod signal;
+mod telemetry;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+#[cfg(feature = "serde")]
+pub mod view;
+
+pub use adapters::{select_adapter, AdapterSet};
+pub use battery::{BatteryState, BatteryThresholds};
+#[cfg(feature = "btleplug")]
+pub use btle::BtleplugClient;
+pub use builder::{MifloraBuilder, WriteVerification};
+pub use clock::{Clock, SystemClock};
+pub use device_info::DeviceInfo;
+pub use dump::{DecodedPayload, PayloadKind, RawPayload};
+pub use epoch::EpochTime;
+pub use firmware::FirmwareVersion;
+pub use fleet::{Collected, CollectionStrategy, MifloraFleet};
+pub use gatt::{BluerClient, GattClient};
+pub use history::{Gap, HistorySet, RebootBoundary, TimestampConfidence};
+use miflora_protocol::{
+    decode_history_length, decode_uptime, history_entry_command, history_page_command,
+    HistoryPayload, RealtimePayload, SystemPayload, CHARACTERISTIC_DATA_UUID,
+    CHARACTERISTIC_DEVICE_NAME_UUID, CHARACTERISTIC_FIRMWARE_REVISION_UUID,
+    CHARACTERISTIC_FIRMWARE_UUID, CHARACTERISTIC_HARDWARE_REVISION_UUID,
+    CHARACTERISTIC_HISTORY_CTRL_UUID, CHARACTERISTIC_HISTORY_READ_UUID,
+    CHARACTERISTIC_HISTORY_TIME_UUID, CHARACTERISTIC_MANUFACTURER_UUID,
+    CHARACTERISTIC_MODEL_NUMBER_UUID, CHARACTERISTIC_MODE_UUID, CHARACTERISTIC_PNP_ID_UUID,
+    CHARACTERISTIC_SERIAL_NUMBER_UUID, CHARACTERISTIC_SOFTWARE_REVISION_UUID, CMD_BLINK_LED,
+    CMD_HISTORY_READ_FAILED, CMD_HISTORY_READ_INIT, CMD_HISTORY_READ_SUCCESS, CMD_REALTIME_DISABLE,
+    CMD_REALTIME_ENABLE, DEVICE_NAME_MAX_LENGTH, DEVICE_UUID_PREFIX, HISTORY_PAGE_SIZE,
+    SERVICE_DATA_UUID, SERVICE_DEVICE_INFO_UUID, SERVICE_GENERIC_ACCESS_UUID, SERVICE_HISTORY_UUID,
+    XIAOMI_OUI,
 };
+pub use miflora_protocol::{Model, PnpId};
+pub use observer::{Observer, Operation};
+pub use reading::SensorReading;
+use recording::Hex;
+pub use recording::{Exchange, Recorder, Recording, Replay};
+pub use registry::{Advertised, DeviceState, Registry};
+pub use retry::RetryPolicy;
+pub use scan::{scan, scan_with_cooldown};
+pub use signal::SignalQuality;
+
+/// Seconds the device clock may advance between setting it and reading it back.
+const DEVICE_TIME_TOLERANCE: u32 = 5;
 
-fn now() -> f64 {
-    SystemTime::now()
+fn unix_time(clock: &dyn Clock) -> f64 {
+    clock
+        .now()
         .duration_since(UNIX_EPOCH)
         .expect("went back in time")
         .as_secs_f64()
 }
 
+#[cfg(feature = "chrono")]
+fn timestamp_to_datetime(timestamp: u64) -> chrono::DateTime<chrono::Utc> {
+    i64::try_from(timestamp)
+        .ok()
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+        .unwrap_or(chrono::DateTime::<chrono::Utc>::MAX_UTC)
+}
+
+/// Options applied to each read or write of a characteristic.
+#[derive(Clone, Debug)]
+struct GattOptions {
+    retry_policy: RetryPolicy,
+    timeout: Option<Duration>,
+    dump: Option<Arc<dump::RawDump>>,
+    observer: Arc<dyn Observer>,
+}
+
+impl GattOptions {
+    /// Runs the operation with the timeout and retry policy, recording each attempt.
+    async fn run<T, F, Fut>(
+        &self,
+        address: Address,
+        operation: Operation,
+        func: F,
+    ) -> Result<T, Error>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let first_attempt = Instant::now();
+        self.retry_policy
+            .run(|| async {
+                let started = Instant::now();
+                let result = with_timeout(self.timeout, func()).await;
+                telemetry::gatt_operation(
+                    address,
+                    operation.as_str(),
+                    started.elapsed(),
+                    result.as_ref().err(),
+                );
+                result
+            })
+            .await
+            .map_err(|(_, err)| {
+                self.observer
+                    .on_error(address, operation, &err, first_attempt.elapsed());
+                err
+            })
+    }
+
+    async fn read<G: GattClient>(
+        &self,
+        client: &G,
+        service_id: Uuid,
+        char_id: Uuid,
+    ) -> Result<Vec<u8>, Error> {
+        tracing::trace!(
+            message = "reading",
+            service = %service_id,
+            characteristic = %char_id
+        );
+        let started = Instant::now();
+        let payload = self
+            .run(client.address(), Operation::Read, || {
+                client.read(service_id, char_id)
+            })
+            .await?;
+        if let Some(ref dump) = self.dump {
+            dump.write(client.address(), char_id, &payload);
+        }
+        self.observer
+            .on_read(client.address(), char_id, &payload, started.elapsed());
+        Ok(payload)
+    }
+
+    async fn write<G: GattClient>(
+        &self,
+        client: &G,
+        service_id: Uuid,
+        char_id: Uuid,
+        payload: &[u8],
+    ) -> Result<(), Error> {
+        tracing::trace!(
+            message = "writing",
+            service = %service_id,
+            characteristic = %char_id
+        );
+        self.run(client.address(), Operation::Write, || {
+            client.write(service_id, char_id, payload)
+        })
+        .await?;
+        if let Some(ref dump) = self.dump {
+            dump.written(char_id, payload);
+        }
+        Ok(())
+    }
+}
+
+async fn with_timeout<T, F>(timeout: Option<Duration>, future: F) -> Result<T, Error>
+where
+    F: Future<Output = Result<T, Error>>,
+{
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, future)
+            .await
+            .map_err(|_| Error::Timeout { timeout })?,
+        None => future.await,
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("unable to find device with address {address}")]
@@ -69,37 +204,48 @@ pub enum Error {
         #[source]
         cause: bluer::Error,
     },
+    #[error("unable to find bluetooth adapter {adapter}")]
+    AdapterNotFound { adapter: String },
     #[error("unable to find service {service_id}")]
     ServiceNotFound {
-        service_id: u16,
+        service_id: Uuid,
         #[source]
         cause: bluer::Error,
     },
     #[error("unable to find characteristic {characteristic_id} for service {service_id}")]
     CharacteristicNotFound {
-        characteristic_id: u16,
-        service_id: u16,
+        characteristic_id: Uuid,
+        service_id: Uuid,
         #[source]
         cause: bluer::Error,
     },
     #[error("unable to read from service {service_id} and characteristic {characteristic_id}")]
     UnableToRead {
-        characteristic_id: u16,
-        service_id: u16,
+        characteristic_id: Uuid,
+        service_id: Uuid,
         #[source]
         cause: bluer::Error,
     },
     #[error("unable to write to service {service_id} and characteristic {characteristic_id}")]
     UnableToWrite {
-        characteristic_id: u16,
-        service_id: u16,
+        characteristic_id: Uuid,
+        service_id: Uuid,
+        #[source]
+        cause: bluer::Error,
+    },
+    #[error("unable to subscribe to service {service_id} and characteristic {characteristic_id}")]
+    UnableToSubscribe {
+        characteristic_id: Uuid,
+        service_id: Uuid,
         #[source]
         cause: bluer::Error,
     },
-    #[error("the payload was not correctly written")]
-    InvalidWrittenValue {
-        characteristic_id: u16,
-        service_id: u16,
+    #[error("value read back from characteristic {characteristic_id} doesn't match the written one, expected {expected:02x?} but got {actual:02x?}")]
+    WriteMismatch {
+        characteristic_id: Uuid,
+        service_id: Uuid,
+        expected: Vec<u8>,
+        actual: Vec<u8>,
     },
     #[error("unable to execute command with bluer")]
     CommandFailed {
@@ -108,34 +254,147 @@ pub enum Error {
     },
     #[error("too many retries")]
     TooManyRetries {
-        retries: u8,
+        retries: u16,
         #[source]
-        cause: bluer::Error,
+        cause: Box<Error>,
+    },
+    #[error("operation timed out after {timeout:?}")]
+    Timeout { timeout: Duration },
+    #[error("invalid payload length for characteristic {characteristic_id}, expected {expected} bytes but got {actual}")]
+    InvalidPayloadLength {
+        expected: usize,
+        actual: usize,
+        characteristic_id: Uuid,
+    },
+    #[error("history download interrupted at entry {}", .cursor.index())]
+    HistoryInterrupted {
+        /// Position to resume the download from
+        cursor: HistoryCursor,
+        /// Entries read before the interruption
+        entries: Vec<HistoricalEntry>,
+        #[source]
+        cause: Box<Error>,
     },
     #[error("no service data provided")]
     NoServiceData,
+    #[error("invalid advertisement: {reason}")]
+    InvalidAdvertisement { reason: &'static str },
+    #[error("implausible reading: {reason}")]
+    ImplausibleReading { reason: &'static str },
+    #[error("invalid device name: {reason}")]
+    InvalidDeviceName { reason: &'static str },
+    #[error("the advertisement is encrypted")]
+    EncryptedAdvertisement,
+    #[error("unable to decrypt the advertisement with the bind key")]
+    DecryptionFailed,
     #[error("the provided device is not supported")]
     DeviceNotSupported,
+    #[error("invalid recording at line {line}: {reason}")]
+    InvalidRecording { line: usize, reason: &'static str },
+    #[error("operation doesn't match the exchange {index} of the recording")]
+    ReplayMismatch { index: usize },
+}
+
+impl From<miflora_protocol::Error> for Error {
+    fn from(value: miflora_protocol::Error) -> Self {
+        match value {
+            miflora_protocol::Error::InvalidPayloadLength {
+                expected,
+                actual,
+                characteristic_id,
+            } => Self::InvalidPayloadLength {
+                expected,
+                actual,
+                characteristic_id,
+            },
+            miflora_protocol::Error::InvalidAdvertisement { reason } => {
+                Self::InvalidAdvertisement { reason }
+            }
+        }
+    }
 }
 
 #[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "view::SystemView", from = "view::SystemView")
+)]
 pub struct System {
     inner: Vec<u8>,
 }
 
-impl From<Vec<u8>> for System {
-    fn from(inner: Vec<u8>) -> Self {
-        Self { inner }
+impl TryFrom<Vec<u8>> for System {
+    type Error = Error;
+
+    fn try_from(inner: Vec<u8>) -> Result<Self, Self::Error> {
+        SystemPayload::decode(&inner)?;
+        Ok(Self { inner })
     }
 }
 
 impl System {
+    fn payload(&self) -> SystemPayload<'_> {
+        SystemPayload::decode(&self.inner).expect("payload checked on creation")
+    }
+
     pub fn battery(&self) -> u8 {
-        self.inner[0]
+        self.payload().battery()
+    }
+
+    /// State of the battery with the default thresholds, low at 20% and critical at 10%.
+    pub fn battery_state(&self) -> BatteryState {
+        self.battery_state_with(&BatteryThresholds::default())
+    }
+
+    /// State of the battery with the given thresholds.
+    pub fn battery_state_with(&self, thresholds: &BatteryThresholds) -> BatteryState {
+        thresholds.state(self.battery())
+    }
+
+    fn key(&self) -> (u8, &[u8]) {
+        let payload = self.payload();
+        (payload.battery(), payload.firmware())
     }
 
     pub fn firmware(&self) -> Cow<'_, str> {
-        String::from_utf8_lossy(&self.inner[2..])
+        String::from_utf8_lossy(self.payload().firmware())
+    }
+
+    pub fn firmware_version(&self) -> FirmwareVersion {
+        FirmwareVersion::decode(self.payload().firmware())
+    }
+
+    /// Payload as sent by the device.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.inner
+    }
+}
+
+/// Compares the decoded values.
+impl PartialEq for System {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for System {}
+
+impl std::hash::Hash for System {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+/// Formats the system information like `battery 87%, firmware 3.2.2`.
+impl std::fmt::Display for System {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "battery {}%, firmware {}",
+            self.battery(),
+            self.firmware()
+        )
     }
 }
 
@@ -154,7 +413,7 @@ impl std::fmt::Debug for System {
 /// It's unclear what the meaning of these bytes is beyond what is decoded in this method.
 ///
 /// Semantics of the data (in little endian encoding):
-/// bytes   0-1: temperature in 0.1 °C
+/// bytes   0-1: temperature in 0.1 °C (signed)
 /// byte      2: unknown
 /// bytes   3-6: brightness in lux
 /// byte      7: moisture in %
@@ -163,42 +422,123 @@ impl std::fmt::Debug for System {
 ///
 /// (source https://github.com/vrachieru/xiaomi-flower-care-api/blob/master/flowercare/reader.py#L138)
 #[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "view::RealtimeEntryView", from = "view::RealtimeEntryView")
+)]
 pub struct RealtimeEntry {
+    model: Model,
     inner: Vec<u8>,
 }
 
-impl From<Vec<u8>> for RealtimeEntry {
-    fn from(inner: Vec<u8>) -> Self {
-        Self { inner }
+impl TryFrom<Vec<u8>> for RealtimeEntry {
+    type Error = Error;
+
+    fn try_from(inner: Vec<u8>) -> Result<Self, Self::Error> {
+        Self::try_new(inner, Model::default())
     }
 }
 
 impl RealtimeEntry {
-    pub fn temperature(&self) -> u16 {
-        u16::from_le_bytes([self.inner[0], self.inner[1]])
+    /// Decodes the payload sent by the given model of device.
+    pub fn try_new(inner: Vec<u8>, model: Model) -> Result<Self, Error> {
+        RealtimePayload::decode(&inner, model)?;
+        Ok(Self { model, inner })
+    }
+
+    fn payload(&self) -> RealtimePayload<'_> {
+        RealtimePayload::decode(&self.inner, self.model).expect("payload checked on creation")
+    }
+
+    /// Temperature in 0.1 °C, negative below freezing.
+    pub fn temperature(&self) -> i16 {
+        self.payload().temperature()
+    }
+
+    /// Temperature in °C.
+    pub fn temperature_celsius(&self) -> f32 {
+        self.temperature() as f32 / 10.0
     }
 
-    pub fn brightness(&self) -> u32 {
-        u32::from_le_bytes([self.inner[3], self.inner[4], self.inner[5], self.inner[6]])
+    /// Brightness in lux, if the device has a brightness sensor.
+    pub fn brightness(&self) -> Option<u32> {
+        self.payload().brightness()
     }
 
     pub fn moisture(&self) -> u8 {
-        self.inner[7]
+        self.payload().moisture()
     }
 
     pub fn conductivity(&self) -> u16 {
-        u16::from_le_bytes([self.inner[8], self.inner[9]])
+        self.payload().conductivity()
+    }
+
+    /// Payload as sent by the device.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.inner
+    }
+
+    /// Decoded values, identifying the entry.
+    fn key(&self) -> (i16, Option<u32>, u8, u16) {
+        (
+            self.temperature(),
+            self.brightness(),
+            self.moisture(),
+            self.conductivity(),
+        )
+    }
+
+    /// Byte 2 of the payload, of unknown meaning.
+    pub fn unknown_2(&self) -> u8 {
+        self.payload().unknown_2()
+    }
+
+    /// Bytes 10 to 15 of the payload, of unknown meaning.
+    pub fn unknown_10_15(&self) -> &[u8] {
+        self.payload().unknown_10_15()
+    }
+
+    /// Checks the values are in the ranges the sensor can measure, see
+    /// [`SensorReading::validate`].
+    pub fn validate(&self) -> Result<(), Error> {
+        SensorReading::validate(self)
+    }
+
+    pub fn is_plausible(&self) -> bool {
+        SensorReading::is_plausible(self)
+    }
+}
+
+/// Compares the decoded values, regardless of the unknown bytes.
+impl PartialEq for RealtimeEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
     }
 }
 
+impl Eq for RealtimeEntry {}
+
+impl std::hash::Hash for RealtimeEntry {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+/// The alternate format, `{:#?}`, also prints the payload in hexadecimal.
 impl std::fmt::Debug for RealtimeEntry {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct(stringify!(RealTimeEntry))
+        let alternate = f.alternate();
+        let mut debug = f.debug_struct(stringify!(RealTimeEntry));
+        debug
             .field("temperature", &self.temperature())
             .field("brightness", &self.brightness())
             .field("moisture", &self.moisture())
-            .field("conductivity", &self.conductivity())
-            .finish()
+            .field("conductivity", &self.conductivity());
+        if alternate {
+            debug.field("raw", &format_args!("{}", Hex(&self.inner)));
+        }
+        debug.finish()
     }
 }
 
@@ -209,7 +549,7 @@ impl std::fmt::Debug for RealtimeEntry {
 ///
 /// Semantics of the data (in little endian encoding):
 /// bytes   0-3: timestamp, seconds since boot
-/// bytes   4-5: temperature in 0.1 °C
+/// bytes   4-5: temperature in 0.1 °C (signed)
 /// byte      6: unknown
 /// bytes   7-9: brightness in lux
 /// byte     10: unknown
@@ -219,75 +559,576 @@ impl std::fmt::Debug for RealtimeEntry {
 ///
 /// (source https://github.com/vrachieru/xiaomi-flower-care-api/blob/master/flowercare/reader.py#L160)
 #[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "view::HistoricalEntryView", from = "view::HistoricalEntryView")
+)]
 pub struct HistoricalEntry {
-    epoch_time: u64,
+    model: Model,
+    epoch: EpochTime,
+    confidence: TimestampConfidence,
     inner: Vec<u8>,
 }
 
 impl HistoricalEntry {
-    fn new(inner: Vec<u8>, epoch_time: u64) -> Self {
-        Self { epoch_time, inner }
+    /// Decodes an entry sent by the given model of device, timestamped from its epoch time.
+    pub fn try_new(inner: Vec<u8>, model: Model, epoch: EpochTime) -> Result<Self, Error> {
+        HistoryPayload::decode(&inner, model)?;
+        Ok(Self {
+            model,
+            epoch,
+            confidence: TimestampConfidence::Reliable,
+            inner,
+        })
+    }
+
+    fn payload(&self) -> HistoryPayload<'_> {
+        HistoryPayload::decode(&self.inner, self.model).expect("payload checked on creation")
+    }
+
+    fn is_padding(&self) -> bool {
+        self.payload().is_padding()
+    }
+
+    /// Seconds elapsed since the device booted when the entry was recorded.
+    pub fn uptime(&self) -> u32 {
+        self.payload().uptime()
     }
 
+    /// Unix timestamp in seconds, corrected with the drift of the device clock.
+    ///
+    /// It's wrong for the entries recorded before a reboot of the device, see
+    /// [`Self::timestamp_confidence`].
     pub fn timestamp(&self) -> u64 {
-        let offset =
-            u32::from_le_bytes([self.inner[0], self.inner[1], self.inner[2], self.inner[3]]);
-        self.epoch_time + offset as u64
+        self.epoch.timestamp_at(self.uptime())
+    }
+
+    /// Whether the timestamp can be trusted, the uptime of the entries recorded before a
+    /// reboot counting from a previous boot of the device.
+    pub fn timestamp_confidence(&self) -> TimestampConfidence {
+        self.confidence
+    }
+
+    /// Date and time when the entry was recorded, corrected with the drift of the device clock.
+    #[cfg(feature = "chrono")]
+    pub fn datetime(&self) -> chrono::DateTime<chrono::Utc> {
+        timestamp_to_datetime(self.timestamp())
+    }
+
+    /// Temperature in 0.1 °C, negative below freezing.
+    pub fn temperature(&self) -> i16 {
+        self.payload().temperature()
     }
 
-    pub fn temperature(&self) -> u16 {
-        u16::from_le_bytes([self.inner[4], self.inner[5]])
+    /// Temperature in °C.
+    pub fn temperature_celsius(&self) -> f32 {
+        self.temperature() as f32 / 10.0
     }
 
-    pub fn brightness(&self) -> u32 {
-        u32::from_le_bytes([self.inner[7], self.inner[8], self.inner[9], 0])
+    /// Brightness in lux, if the device has a brightness sensor.
+    pub fn brightness(&self) -> Option<u32> {
+        self.payload().brightness()
     }
 
     pub fn moisture(&self) -> u8 {
-        self.inner[11]
+        self.payload().moisture()
     }
 
     pub fn conductivity(&self) -> u16 {
-        u16::from_le_bytes([self.inner[12], self.inner[13]])
+        self.payload().conductivity()
+    }
+
+    /// Payload as sent by the device.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.inner
+    }
+
+    /// Timestamp and decoded values, identifying the entry.
+    fn key(&self) -> (u64, i16, Option<u32>, u8, u16) {
+        (
+            self.timestamp(),
+            self.temperature(),
+            self.brightness(),
+            self.moisture(),
+            self.conductivity(),
+        )
+    }
+
+    /// Byte 6 of the payload, of unknown meaning.
+    pub fn unknown_6(&self) -> u8 {
+        self.payload().unknown_6()
+    }
+
+    /// Byte 10 of the payload, of unknown meaning.
+    pub fn unknown_10(&self) -> u8 {
+        self.payload().unknown_10()
+    }
+
+    /// Bytes 14 and 15 of the payload, of unknown meaning.
+    pub fn unknown_14_15(&self) -> &[u8] {
+        self.payload().unknown_14_15()
     }
 }
 
+/// Compares the timestamps and the decoded values, regardless of the unknown bytes.
+impl PartialEq for HistoricalEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for HistoricalEntry {}
+
+impl std::hash::Hash for HistoricalEntry {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+/// Orders the entries by timestamp, then by decoded values.
+impl Ord for HistoricalEntry {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl PartialOrd for HistoricalEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The alternate format, `{:#?}`, also prints the payload in hexadecimal.
 impl std::fmt::Debug for HistoricalEntry {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct(stringify!(HistoricalEntry))
+        let alternate = f.alternate();
+        let mut debug = f.debug_struct(stringify!(HistoricalEntry));
+        debug
             .field("timestamp", &self.timestamp())
             .field("temperature", &self.temperature())
             .field("brightness", &self.brightness())
             .field("moisture", &self.moisture())
-            .field("conductivity", &self.conductivity())
-            .finish()
+            .field("conductivity", &self.conductivity());
+        if alternate {
+            debug.field("raw", &format_args!("{}", Hex(&self.inner)));
+        }
+        debug.finish()
     }
 }
 
+/// Values read from a device in a single pass, see [`Miflora::read_all`].
 #[derive(Clone, Debug)]
-pub struct Miflora {
-    device: Device,
+pub struct Snapshot {
+    system: System,
+    realtime: RealtimeEntry,
+    history_count: Option<u32>,
+}
+
+impl Snapshot {
+    pub fn system(&self) -> &System {
+        &self.system
+    }
+
+    pub fn realtime(&self) -> &RealtimeEntry {
+        &self.realtime
+    }
+
+    /// Number of historical entries, if requested.
+    pub fn history_count(&self) -> Option<u32> {
+        self.history_count
+    }
+}
+
+/// Position in the history of a device, used to resume an interrupted download.
+///
+/// The cursor points to the next entry to read.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HistoryCursor {
+    index: u32,
+}
+
+impl HistoryCursor {
+    pub fn new(index: u32) -> Self {
+        Self { index }
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+}
+
+/// Historical entries read from a device, waiting to be acknowledged.
+///
+/// Committing the session tells the device the transfer succeeded, which clears its
+/// history. Aborting it tells the device the transfer failed and keeps the entries.
+#[must_use = "the session should be committed or aborted"]
+#[derive(Debug)]
+pub struct HistorySession<'a, G: GattClient = BluerClient> {
+    miflora: &'a Miflora<G>,
+    entries: Vec<HistoricalEntry>,
+}
+
+impl<G: GattClient> HistorySession<'_, G> {
+    pub fn entries(&self) -> &[HistoricalEntry] {
+        &self.entries
+    }
+
+    /// Acknowledges the transfer and clears the history of the device.
+    pub async fn commit(self) -> Result<(), Error> {
+        self.miflora
+            .send_history_command(&CMD_HISTORY_READ_SUCCESS)
+            .await
+    }
+
+    /// Notifies the device the transfer failed, the history is kept.
+    pub async fn abort(self) -> Result<(), Error> {
+        self.miflora.abort_history_read().await
+    }
+}
+
+/// State of an ongoing history download.
+///
+/// The classic devices address their entries directly, while the ones with a larger
+/// history split them in pages of [`HISTORY_PAGE_SIZE`] entries, the page being selected
+/// with the init command followed by its number.
+struct HistoryReader<G> {
+    gatt: GattOptions,
+    client: G,
+    model: Model,
+    epoch: EpochTime,
+    /// Page currently selected on the device, the first one after the init command
+    page: u16,
+    length: u32,
+    index: u32,
+    /// Cleared once all the entries are read, see [`Miflora::is_history_open`]
+    open: Arc<AtomicBool>,
+}
+
+impl<G: GattClient> HistoryReader<G> {
+    /// Page and offset in the page of the entry, the classic history having a single page.
+    fn locate(&self, index: u32) -> (u16, u16) {
+        if self.model.has_paged_history() {
+            (
+                (index / HISTORY_PAGE_SIZE) as u16,
+                (index % HISTORY_PAGE_SIZE) as u16,
+            )
+        } else {
+            (0, index as u16)
+        }
+    }
+
+    /// Reads the next entry, skipping the padding frames.
+    async fn next(&mut self) -> Result<Option<HistoricalEntry>, Error> {
+        while self.index < self.length {
+            let entry = self.read_entry(self.index).await?;
+            self.index += 1;
+            self.gatt
+                .observer
+                .on_history_progress(self.client.address(), self.index, self.length);
+            if entry.is_padding() {
+                tracing::trace!("skipping padding entry {}", self.index - 1);
+            } else {
+                return Ok(Some(entry));
+            }
+        }
+        self.finish();
+        Ok(None)
+    }
+
+    /// Marks the download as done, the entries being all read.
+    fn finish(&self) {
+        self.open.store(false, Ordering::Relaxed);
+    }
+
+    async fn read_entry(&mut self, index: u32) -> Result<HistoricalEntry, Error> {
+        tracing::debug!("loading entry {index}");
+        let (page, offset) = self.locate(index);
+        if page != self.page {
+            tracing::debug!("selecting history page {page}");
+            self.gatt
+                .write(
+                    &self.client,
+                    SERVICE_HISTORY_UUID,
+                    CHARACTERISTIC_HISTORY_CTRL_UUID,
+                    &history_page_command(page),
+                )
+                .await?;
+            self.page = page;
+        }
+        let payload = history_entry_command(offset);
+        self.gatt
+            .write(
+                &self.client,
+                SERVICE_HISTORY_UUID,
+                CHARACTERISTIC_HISTORY_CTRL_UUID,
+                &payload,
+            )
+            .await?;
+        let data = self
+            .gatt
+            .read(
+                &self.client,
+                SERVICE_HISTORY_UUID,
+                CHARACTERISTIC_HISTORY_READ_UUID,
+            )
+            .await?;
+        telemetry::history_entry(self.client.address());
+        HistoricalEntry::try_new(data, self.model, self.epoch)
+    }
+
+    /// Reads the entries at the given indexes until one isn't a padding frame.
+    async fn read_measure<I>(&mut self, indexes: I) -> Result<Option<(u32, HistoricalEntry)>, Error>
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        for index in indexes {
+            let entry = self.read_entry(index).await?;
+            if entry.is_padding() {
+                tracing::trace!("skipping padding entry {index}");
+            } else {
+                return Ok(Some((index, entry)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Locates the entries recorded after the timestamp with a binary search, the padding
+    /// frames being skipped.
+    ///
+    /// Returns `None` when the entries read along the way aren't sorted by timestamp, like
+    /// when the device rebooted, the search being unreliable then.
+    async fn locate_since(&mut self, timestamp: u64) -> Result<Option<Range<u32>>, Error> {
+        let Some((first_index, first)) = self.read_measure(0..self.length).await? else {
+            return Ok(Some(0..0));
+        };
+        let (last_index, last) = self
+            .read_measure((first_index..self.length).rev())
+            .await?
+            .expect("first entry found");
+        let ascending = first.timestamp() <= last.timestamp();
+        let mut probes = vec![
+            (first_index, first.timestamp()),
+            (last_index, last.timestamp()),
+        ];
+        // looking for the boundary between the entries before and after the cutoff
+        let (mut low, mut high) = (0, self.length);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            match self.read_measure(middle..high).await? {
+                Some((index, entry)) => {
+                    probes.push((index, entry.timestamp()));
+                    if (entry.timestamp() > timestamp) == ascending {
+                        high = middle;
+                    } else {
+                        low = index + 1;
+                    }
+                }
+                // only padding up to the upper bound, which doesn't move the boundary
+                None => high = middle,
+            }
+        }
+        probes.sort_unstable();
+        let sorted = probes.windows(2).all(|pair| {
+            if ascending {
+                pair[0].1 <= pair[1].1
+            } else {
+                pair[0].1 >= pair[1].1
+            }
+        });
+        Ok(sorted.then_some(if ascending { low..self.length } else { 0..low }))
+    }
+}
+
+/// Communicates with a device, through BlueZ by default.
+#[derive(Clone, Debug)]
+pub struct Miflora<G: GattClient = BluerClient> {
+    client: G,
+    model: Model,
+    retry_policy: RetryPolicy,
+    gatt: GattOptions,
+    connect_timeout: Option<Duration>,
+    write_verification: WriteVerification,
+    auto_disable_realtime: bool,
+    reject_implausible: bool,
+    clock: Arc<dyn Clock>,
+    /// Reads of the device clock, shared between the clones
+    epoch: Arc<Mutex<epoch::EpochEstimator>>,
+    /// Whether a history download or session is in progress, shared between the clones
+    history_open: Arc<AtomicBool>,
 }
 
 impl From<Device> for Miflora {
     fn from(device: Device) -> Self {
-        Self { device }
+        MifloraBuilder::new(device).build()
+    }
+}
+
+/// Category of an [`Error`], to decide whether an operation is worth retrying.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// Temporary failure while communicating with the device, worth retrying
+    Transient,
+    /// The device, service or characteristic doesn't exist
+    NotFound,
+    /// The device doesn't behave as expected, retrying is unlikely to help
+    Protocol,
+    /// The operation took too long
+    Timeout,
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::DeviceNotFound { .. }
+            | Self::AdapterNotFound { .. }
+            | Self::ServiceNotFound { .. }
+            | Self::CharacteristicNotFound { .. }
+            | Self::NoServiceData => ErrorKind::NotFound,
+            Self::UnableToRead { cause, .. }
+            | Self::UnableToWrite { cause, .. }
+            | Self::UnableToSubscribe { cause, .. }
+            | Self::CommandFailed { cause } => bluer_error_kind(cause),
+            Self::ImplausibleReading { .. } => ErrorKind::Transient,
+            Self::WriteMismatch { .. }
+            | Self::InvalidPayloadLength { .. }
+            | Self::InvalidAdvertisement { .. }
+            | Self::EncryptedAdvertisement
+            | Self::DecryptionFailed
+            | Self::DeviceNotSupported
+            | Self::InvalidDeviceName { .. }
+            | Self::InvalidRecording { .. }
+            | Self::ReplayMismatch { .. } => ErrorKind::Protocol,
+            Self::Timeout { .. } => ErrorKind::Timeout,
+            Self::TooManyRetries { cause, .. } | Self::HistoryInterrupted { cause, .. } => {
+                cause.kind()
+            }
+        }
+    }
+
+    /// Whether retrying the operation that failed may succeed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self.kind(), ErrorKind::Transient | ErrorKind::Timeout)
+    }
+
+    /// Whether the error is caused by the device not being connected anymore.
+    pub fn is_not_connected(&self) -> bool {
+        match self {
+            Self::UnableToRead { cause, .. }
+            | Self::UnableToWrite { cause, .. }
+            | Self::UnableToSubscribe { cause, .. }
+            | Self::CommandFailed { cause } => is_not_connected(cause),
+            _ => false,
+        }
+    }
+}
+
+fn bluer_error_kind(err: &bluer::Error) -> ErrorKind {
+    use bluer::ErrorKind as Kind;
+
+    match err.kind {
+        Kind::NotFound | Kind::DoesNotExist => ErrorKind::NotFound,
+        Kind::AuthenticationTimeout => ErrorKind::Timeout,
+        Kind::InvalidArguments
+        | Kind::InvalidLength
+        | Kind::InvalidOffset
+        | Kind::NotSupported
+        | Kind::NotPermitted
+        | Kind::NotAuthorized => ErrorKind::Protocol,
+        _ => ErrorKind::Transient,
+    }
+}
+
+fn is_not_connected(err: &bluer::Error) -> bool {
+    use bluer::{ErrorKind, InternalErrorKind};
+
+    match err.kind {
+        ErrorKind::Internal(InternalErrorKind::DBus(ref name)) => name.ends_with(".NotConnected"),
+        ErrorKind::Failed => err.message.eq_ignore_ascii_case("not connected"),
+        _ => false,
+    }
+}
+
+/// Disconnects the device in the background when dropped while still holding it, which
+/// happens when the surrounding future panics or is cancelled.
+struct DisconnectGuard<G: GattClient>(Option<Miflora<G>>);
+
+impl<G: GattClient> Drop for DisconnectGuard<G> {
+    fn drop(&mut self) {
+        let Some(miflora) = self.0.take() else {
+            return;
+        };
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            tracing::warn!("no runtime available to disconnect the device");
+            return;
+        };
+        handle.spawn(async move {
+            if let Err(err) = miflora.disconnect().await {
+                tracing::warn!(message = "unable to disconnect", cause = %err);
+            }
+        });
     }
 }
 
 pub async fn is_miflora_device(device: &Device) -> Result<bool, Error> {
+    Ok(detect_model(device).await?.is_some())
+}
+
+/// Finds the model of the device from the product id it advertises.
+///
+/// When the device isn't advertising, like a device known to BlueZ but out of a scan, the
+/// model is guessed from its name, its services and its address.
+pub async fn detect_model(device: &Device) -> Result<Option<Model>, Error> {
     let service_data = device
         .service_data()
         .await
         .map_err(|err| Error::CommandFailed { cause: err })?;
-    let service_data = service_data.ok_or(Error::NoServiceData)?;
-    Ok(service_data.iter().any(|(uuid, _data)| {
-        let (id, _, _, _) = uuid.as_fields();
-        id == DEVICE_UUID_PREFIX
-    }))
+    if let Some(data) = service_data.as_ref().and_then(|service_data| {
+        service_data
+            .iter()
+            .find(|(uuid, _)| uuid.as_fields().0 == DEVICE_UUID_PREFIX)
+    }) {
+        return Ok(Model::from_service_data(data.1));
+    }
+    if let Some(model) = guess_model(device).await? {
+        tracing::debug!(message = "model guessed without service data", address = %device.address(), model = ?model);
+        return Ok(Some(model));
+    }
+    if service_data.is_some() {
+        Ok(None)
+    } else {
+        Err(Error::NoServiceData)
+    }
+}
+
+async fn guess_model(device: &Device) -> Result<Option<Model>, Error> {
+    let name = device
+        .name()
+        .await
+        .map_err(|err| Error::CommandFailed { cause: err })?;
+    if let Some(model) = name.as_deref().and_then(Model::from_name) {
+        return Ok(Some(model));
+    }
+    let uuids = device
+        .uuids()
+        .await
+        .map_err(|err| Error::CommandFailed { cause: err })?
+        .unwrap_or_default();
+    let has_services = uuids.contains(&SERVICE_DATA_UUID) && uuids.contains(&SERVICE_HISTORY_UUID);
+    let address = device.address();
+    if has_services || address.0[0..3] == XIAOMI_OUI {
+        Ok(Some(Model::default()))
+    } else {
+        Ok(None)
+    }
 }
 
 impl Miflora {
+    pub fn builder(device: Device) -> MifloraBuilder {
+        MifloraBuilder::new(device)
+    }
+
     pub async fn try_from_adapter(adapter: &Adapter, address: Address) -> Result<Self, Error> {
         let device = adapter
             .device(address)
@@ -299,275 +1140,736 @@ impl Miflora {
     }
 
     pub async fn try_from_device(device: Device) -> Result<Self, Error> {
-        if is_miflora_device(&device).await? {
-            Ok(Self { device })
-        } else {
-            Err(Error::DeviceNotSupported)
+        match detect_model(&device).await? {
+            Some(model) => Ok(Self::builder(device).with_model(model).build()),
+            None => Err(Error::DeviceNotSupported),
         }
     }
 
-    async fn characteristic(&self, service_id: u16, char_id: u16) -> Result<Characteristic, Error> {
-        let services = self
-            .device
-            .services()
+    /// Signal strength of the last advertisement received, in dBm, only known while
+    /// discovering.
+    pub async fn rssi(&self) -> Result<Option<i16>, Error> {
+        self.client
+            .device()
+            .rssi()
             .await
-            .map_err(|err| Error::CommandFailed { cause: err })?;
-        let service = services
-            .into_iter()
-            .find(|s| s.id() == service_id)
-            .ok_or_else(|| Error::ServiceNotFound {
-                service_id,
-                cause: bluer::Error {
-                    kind: bluer::ErrorKind::NotFound,
-                    message: "service not found".into(),
-                },
-            })?;
-        let characteristics = service
-            .characteristics()
+            .map_err(|err| Error::CommandFailed { cause: err })
+    }
+
+    /// Transmission power advertised by the device, in dBm.
+    pub async fn tx_power(&self) -> Result<Option<i16>, Error> {
+        self.client
+            .device()
+            .tx_power()
             .await
-            .map_err(|err| Error::CommandFailed { cause: err })?;
-        characteristics
-            .into_iter()
-            .find(|c| c.id() == char_id)
-            .ok_or_else(|| Error::CharacteristicNotFound {
-                characteristic_id: char_id,
-                service_id,
-                cause: bluer::Error {
-                    kind: bluer::ErrorKind::NotFound,
-                    message: "characteristic not found".into(),
-                },
-            })
+            .map_err(|err| Error::CommandFailed { cause: err })
     }
 
-    async fn read(&self, service_id: u16, char_id: u16) -> Result<Vec<u8>, Error> {
-        let char = self.characteristic(service_id, char_id).await?;
-        tracing::trace!(
-            message = "reading",
-            service = service_id,
-            characteristic = char_id
-        );
-        char.read().await.map_err(|err| Error::UnableToRead {
-            characteristic_id: char_id,
-            service_id,
-            cause: err,
-        })
+    /// Estimates whether the device is close enough to connect, from its signal strength.
+    pub async fn signal_quality(&self) -> Result<SignalQuality, Error> {
+        Ok(SignalQuality::from_rssi(self.rssi().await?))
     }
+}
 
-    #[tracing::instrument(skip(self), fields(address = %self.device.address()))]
-    pub async fn is_connected(&self) -> Result<bool, Error> {
-        self.device
-            .is_connected()
+impl<G: GattClient> Miflora<G> {
+    pub fn address(&self) -> Address {
+        self.client.address()
+    }
+
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    pub fn client(&self) -> &G {
+        &self.client
+    }
+
+    /// Wraps the miflora in the blocking API, running the operations on an internal runtime.
+    ///
+    /// The D-Bus connection of bluer runs on the runtime it was created on, the devices being
+    /// better created with a [`blocking::Session`] when there is no other runtime.
+    pub fn blocking(self) -> blocking::Miflora<G> {
+        blocking::Miflora::new(self)
+    }
+
+    /// Runs the operation and, if it failed because the device got disconnected,
+    /// reconnects and runs it once more.
+    async fn reconnecting<T, F, Fut>(&self, operation: F) -> Result<T, Error>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        match operation().await {
+            Err(err) if err.is_not_connected() => {
+                tracing::warn!(message = "device disconnected, reconnecting", cause = %err);
+                self.try_connect().await?;
+                operation().await
+            }
+            other => other,
+        }
+    }
+
+    async fn read(&self, service_id: Uuid, char_id: Uuid) -> Result<Vec<u8>, Error> {
+        self.reconnecting(|| self.gatt.read(&self.client, service_id, char_id))
             .await
-            .map_err(|err| Error::CommandFailed { cause: err })
     }
 
-    #[tracing::instrument(skip(self), fields(address = %self.device.address()))]
-    pub async fn connect(&self) -> Result<(), Error> {
-        self.device
-            .connect()
+    /// Reads a characteristic that the device may not expose, nor its service.
+    async fn read_optional(
+        &self,
+        service_id: Uuid,
+        char_id: Uuid,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        match self.read(service_id, char_id).await {
+            Ok(data) => Ok(Some(data)),
+            Err(Error::ServiceNotFound { .. } | Error::CharacteristicNotFound { .. }) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn write(&self, service_id: Uuid, char_id: Uuid, payload: &[u8]) -> Result<(), Error> {
+        self.reconnecting(|| self.gatt.write(&self.client, service_id, char_id, payload))
             .await
-            .map_err(|err| Error::CommandFailed { cause: err })
     }
 
-    #[tracing::instrument(skip(self), fields(address = %self.device.address()))]
-    pub async fn try_connect(&self, retry: u8) -> Result<(), Error> {
-        let mut count = 0;
-        loop {
-            if self.is_connected().await? {
-                tracing::debug!("already connected");
-                return Ok(());
-            }
-            match self.device.connect().await {
-                Ok(_) => {
-                    tracing::info!("device connected");
-                    return Ok(());
-                }
-                Err(err) => {
-                    count += 1;
-                    tracing::warn!(message = "unable to connect", tries = count, cause = %err);
-                    if count > retry {
-                        return Err(Error::TooManyRetries {
-                            retries: count,
-                            cause: err,
-                        });
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn is_connected(&self) -> Result<bool, Error> {
+        self.client.is_connected().await
+    }
+
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn connect(&self) -> Result<(), Error> {
+        self.client.connect().await
+    }
+
+    /// Connects to the device, retrying according to the retry policy.
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn try_connect(&self) -> Result<(), Error> {
+        let started = Instant::now();
+        let result = self
+            .retry_policy
+            .run(|| async {
+                let result = with_timeout(self.connect_timeout, async {
+                    if self.is_connected().await? {
+                        tracing::debug!("already connected");
+                    } else {
+                        self.connect().await?;
+                        tracing::info!("device connected");
                     }
-                }
-            }
+                    Ok(())
+                })
+                .await;
+                telemetry::connection_attempt(self.client.address(), result.as_ref().err());
+                result
+            })
+            .await
+            .map_err(|(retries, cause)| Error::TooManyRetries {
+                retries,
+                cause: Box::new(cause),
+            });
+        let observer = &self.gatt.observer;
+        match result {
+            Ok(()) => observer.on_connect(self.client.address(), started.elapsed()),
+            Err(ref err) => observer.on_error(
+                self.client.address(),
+                Operation::Connect,
+                err,
+                started.elapsed(),
+            ),
         }
+        result
     }
 
-    #[tracing::instrument(skip(self), fields(address = %self.device.address()))]
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
     pub async fn disconnect(&self) -> Result<(), Error> {
-        self.device
-            .disconnect()
+        self.client.disconnect().await
+    }
+
+    /// Disconnects from the device, retrying according to the retry policy.
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn try_disconnect(&self) -> Result<(), Error> {
+        let started = Instant::now();
+        let result = self
+            .retry_policy
+            .run(|| {
+                with_timeout(self.gatt.timeout, async {
+                    if !self.is_connected().await? {
+                        tracing::debug!("already disconnected");
+                    } else {
+                        self.client.disconnect().await?;
+                        tracing::info!("device disconnected");
+                    }
+                    Ok(())
+                })
+            })
             .await
-            .map_err(|err| Error::CommandFailed { cause: err })
+            .map_err(|(retries, cause)| Error::TooManyRetries {
+                retries,
+                cause: Box::new(cause),
+            });
+        let observer = &self.gatt.observer;
+        match result {
+            Ok(()) => observer.on_disconnect(self.client.address(), started.elapsed()),
+            Err(ref err) => observer.on_error(
+                self.client.address(),
+                Operation::Disconnect,
+                err,
+                started.elapsed(),
+            ),
+        }
+        result
+    }
+
+    /// Connects to the device, runs the given function and disconnects.
+    ///
+    /// The disconnection is attempted whatever the outcome of the function, and also if it
+    /// panics or if the returned future is dropped before completion.
+    #[tracing::instrument(skip(self, func), fields(address = %self.client.address()))]
+    pub async fn with_connection<F, Fut, T>(&self, func: F) -> Result<T, Error>
+    where
+        F: FnOnce(Miflora<G>) -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        self.try_connect().await?;
+        let mut guard = DisconnectGuard(Some(self.clone()));
+        let result = func(self.clone()).await;
+        guard.0 = None;
+        let disconnected = self.try_disconnect().await;
+        if let (Err(_), Err(err)) = (&result, &disconnected) {
+            tracing::warn!(message = "unable to disconnect", cause = %err);
+        }
+        let value = result?;
+        disconnected?;
+        Ok(value)
+    }
+
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn read_system(&self) -> Result<System, Error> {
+        let data = self
+            .read(SERVICE_DATA_UUID, CHARACTERISTIC_FIRMWARE_UUID)
+            .await?;
+        System::try_from(data)
+    }
+
+    /// Reads the battery level in %, without decoding the rest of the system information.
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn read_battery(&self) -> Result<u8, Error> {
+        let data = self.read_system_payload().await?;
+        Ok(SystemPayload::decode(&data)?.battery())
+    }
+
+    /// Reads the version of the firmware running on the device.
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn read_firmware(&self) -> Result<FirmwareVersion, Error> {
+        let data = self.read_system_payload().await?;
+        Ok(FirmwareVersion::decode(
+            SystemPayload::decode(&data)?.firmware(),
+        ))
+    }
+
+    async fn read_system_payload(&self) -> Result<Vec<u8>, Error> {
+        self.read(SERVICE_DATA_UUID, CHARACTERISTIC_FIRMWARE_UUID)
+            .await
+    }
+
+    /// Reads the standard device information service, the characteristics not exposed by
+    /// the device are left empty.
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn read_device_info(&self) -> Result<DeviceInfo, Error> {
+        let string = |char_id| async move {
+            Ok::<_, Error>(
+                self.read_optional(SERVICE_DEVICE_INFO_UUID, char_id)
+                    .await?
+                    .and_then(|data| device_info::decode_string(&data)),
+            )
+        };
+        Ok(DeviceInfo {
+            manufacturer: string(CHARACTERISTIC_MANUFACTURER_UUID).await?,
+            model_number: string(CHARACTERISTIC_MODEL_NUMBER_UUID).await?,
+            serial_number: string(CHARACTERISTIC_SERIAL_NUMBER_UUID).await?,
+            hardware_revision: string(CHARACTERISTIC_HARDWARE_REVISION_UUID).await?,
+            firmware_revision: string(CHARACTERISTIC_FIRMWARE_REVISION_UUID).await?,
+            software_revision: string(CHARACTERISTIC_SOFTWARE_REVISION_UUID).await?,
+            pnp_id: self
+                .read_optional(SERVICE_DEVICE_INFO_UUID, CHARACTERISTIC_PNP_ID_UUID)
+                .await?
+                .and_then(|data| PnpId::decode(&data)),
+        })
+    }
+
+    /// Reads the name of the device from the generic access service, if exposed.
+    ///
+    /// BlueZ handles the generic access service itself without exposing it, so this returns
+    /// `None` through it, the advertised name being available with [`bluer::Device::name`].
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn read_device_name(&self) -> Result<Option<String>, Error> {
+        Ok(self
+            .read_optional(SERVICE_GENERIC_ACCESS_UUID, CHARACTERISTIC_DEVICE_NAME_UUID)
+            .await?
+            .and_then(|data| device_info::decode_string(&data)))
+    }
+
+    /// Renames the device, where the firmware permits it, and reads the name back to
+    /// check it was applied.
+    ///
+    /// The name must not be empty nor longer than [`DEVICE_NAME_MAX_LENGTH`] bytes. Like
+    /// [`Self::read_device_name`], it requires a client exposing the generic access service,
+    /// failing with [`Error::ServiceNotFound`] through BlueZ.
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn set_device_name(&self, name: &str) -> Result<(), Error> {
+        if name.trim().is_empty() {
+            return Err(Error::InvalidDeviceName {
+                reason: "the name is empty",
+            });
+        }
+        if name.len() > DEVICE_NAME_MAX_LENGTH {
+            return Err(Error::InvalidDeviceName {
+                reason: "the name is too long",
+            });
+        }
+        self.write(
+            SERVICE_GENERIC_ACCESS_UUID,
+            CHARACTERISTIC_DEVICE_NAME_UUID,
+            name.as_bytes(),
+        )
+        .await?;
+        if !self.write_verification.is_enabled() {
+            return Ok(());
+        }
+        let data = self
+            .read(SERVICE_GENERIC_ACCESS_UUID, CHARACTERISTIC_DEVICE_NAME_UUID)
+            .await?;
+        if device_info::decode_string(&data).as_deref() == Some(name.trim()) {
+            Ok(())
+        } else {
+            self.write_mismatch(
+                SERVICE_GENERIC_ACCESS_UUID,
+                CHARACTERISTIC_DEVICE_NAME_UUID,
+                name.as_bytes(),
+                data,
+            )
+        }
     }
 
-    #[tracing::instrument(skip(self), fields(address = %self.device.address()))]
-    pub async fn try_disconnect(&self, retry: u8) -> Result<(), Error> {
-        let mut count = 0;
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn read_realtime_values(&self) -> Result<RealtimeEntry, Error> {
+        let entry = self.read_realtime().await?;
+        if self.auto_disable_realtime {
+            self.set_realtime_data_mode(false).await?;
+        }
+        Ok(entry)
+    }
+
+    async fn read_realtime(&self) -> Result<RealtimeEntry, Error> {
+        self.set_realtime_data_mode(true).await?;
+
+        let mut retries: u16 = 0;
         loop {
-            if !self.is_connected().await? {
-                tracing::debug!("already disconnected");
-                return Ok(());
-            }
-            match self.device.disconnect().await {
-                Ok(_) => {
-                    tracing::info!("device disconnected");
-                    return Ok(());
-                }
-                Err(err) => {
-                    count += 1;
-                    tracing::warn!(message = "unable to disconnect", tries = count, cause = %err);
-                    if count > retry {
+            let data = self
+                .read(SERVICE_DATA_UUID, CHARACTERISTIC_DATA_UUID)
+                .await?;
+            let entry = RealtimeEntry::try_new(data, self.model)?;
+            match entry.validate() {
+                Err(err) if self.reject_implausible => {
+                    retries += 1;
+                    let Some(delay) = self.retry_policy.next_delay(retries) else {
                         return Err(Error::TooManyRetries {
-                            retries: count,
-                            cause: err,
+                            retries,
+                            cause: Box::new(err),
                         });
-                    }
+                    };
+                    tracing::warn!(message = "implausible reading, reading again", delay = ?delay, cause = %err);
+                    tokio::time::sleep(delay).await;
                 }
+                _ => return Ok(entry),
             }
         }
     }
 
-    #[tracing::instrument(skip(self), fields(address = %self.device.address()))]
-    pub async fn read_system(&self) -> Result<System, Error> {
-        let data = self
-            .read(SERVICE_DATA_ID, CHARACTERISTIC_FIRMWARE_ID)
+    /// Enables the realtime mode and streams the values notified by the device.
+    ///
+    /// The notifications stop when the stream is dropped, the realtime mode is left enabled.
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn subscribe_realtime(
+        &self,
+    ) -> Result<impl Stream<Item = Result<RealtimeEntry, Error>>, Error> {
+        self.set_realtime_data_mode(true).await?;
+
+        let notifications = self
+            .reconnecting(|| {
+                tracing::trace!(
+                    message = "subscribing",
+                    service = %SERVICE_DATA_UUID,
+                    characteristic = %CHARACTERISTIC_DATA_UUID
+                );
+                self.client
+                    .subscribe(SERVICE_DATA_UUID, CHARACTERISTIC_DATA_UUID)
+            })
             .await?;
-        Ok(System::from(data))
+        let model = self.model;
+        let (address, dump) = (self.client.address(), self.gatt.dump.clone());
+        Ok(notifications.map(move |data| {
+            if let Some(ref dump) = dump {
+                dump.write(address, CHARACTERISTIC_DATA_UUID, &data);
+            }
+            RealtimeEntry::try_new(data, model)
+        }))
     }
 
-    #[tracing::instrument(skip(self), fields(address = %self.device.address()))]
-    pub async fn read_realtime_values(&self) -> Result<RealtimeEntry, Error> {
-        self.set_realtime_data_mode(true).await?;
+    /// Reads the realtime values and disables the realtime mode afterwards, to save the
+    /// battery of the device.
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn read_realtime_values_once(&self) -> Result<RealtimeEntry, Error> {
+        let entry = self.read_realtime().await?;
+        self.set_realtime_data_mode(false).await?;
+        Ok(entry)
+    }
+
+    /// Reads the system information, the realtime values and optionally the number of
+    /// historical entries, listing the services of the device at most once.
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn read_all(&self, with_history_count: bool) -> Result<Snapshot, Error> {
+        // resolving the characteristics in one go, the following operations hit the cache
+        let mut characteristics = vec![
+            (SERVICE_DATA_UUID, CHARACTERISTIC_FIRMWARE_UUID),
+            (SERVICE_DATA_UUID, CHARACTERISTIC_MODE_UUID),
+            (SERVICE_DATA_UUID, CHARACTERISTIC_DATA_UUID),
+        ];
+        if with_history_count {
+            characteristics.push((SERVICE_HISTORY_UUID, CHARACTERISTIC_HISTORY_CTRL_UUID));
+            characteristics.push((SERVICE_HISTORY_UUID, CHARACTERISTIC_HISTORY_READ_UUID));
+        }
+        self.client.resolve(&characteristics).await?;
 
-        let data = self.read(SERVICE_DATA_ID, CHARACTERISTIC_DATA_ID).await?;
-        Ok(RealtimeEntry::from(data))
+        let system = self.read_system().await?;
+        let realtime = self.read_realtime_values().await?;
+        let history_count = if with_history_count {
+            Some(self.read_history_length().await?)
+        } else {
+            None
+        };
+
+        Ok(Snapshot {
+            system,
+            realtime,
+            history_count,
+        })
     }
 
-    #[tracing::instrument(skip(self), fields(address = %self.device.address()))]
+    /// Reads the device clock and returns the boot time of the device, in seconds since
+    /// the unix epoch, averaged with the previous reads.
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
     pub async fn read_epoch_time(&self) -> Result<u64, Error> {
-        let start = now();
-        let char = self
-            .characteristic(SERVICE_HISTORY_ID, CHARACTERISTIC_HISTORY_TIME_ID)
-            .await?;
-        tracing::trace!(
-            message = "reading",
-            service = SERVICE_HISTORY_ID,
-            characteristic = CHARACTERISTIC_HISTORY_TIME_ID
-        );
-        let data = char.read().await.map_err(|err| Error::UnableToWrite {
-            characteristic_id: CHARACTERISTIC_HISTORY_TIME_ID,
-            service_id: SERVICE_HISTORY_ID,
-            cause: err,
-        })?;
-        let wall_time = (now() + start) / 2.0;
-        let epoch_offset = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
-        let epoch_time = (wall_time as u64) - (epoch_offset as u64);
-        Ok(epoch_time)
+        Ok(self.epoch_time().await?.timestamp())
     }
 
-    fn historical_entry_address(&self, index: u16) -> [u8; 3] {
-        let bytes = u16::to_le_bytes(index);
-        [0xa1, bytes[0], bytes[1]]
+    /// Reads the device clock and returns the boot time of the device, averaged with the
+    /// previous reads.
+    #[cfg(feature = "chrono")]
+    pub async fn read_epoch_datetime(&self) -> Result<chrono::DateTime<chrono::Utc>, Error> {
+        Ok(self.epoch_time().await?.datetime())
     }
 
-    #[tracing::instrument(skip(self), fields(address = %self.device.address()))]
-    pub async fn read_historical_values(&self) -> Result<Vec<HistoricalEntry>, Error> {
-        let ctrl_char = self
-            .characteristic(SERVICE_HISTORY_ID, CHARACTERISTIC_HISTORY_CTRL_ID)
+    /// Reads the device clock and updates the estimation of the boot time of the device.
+    ///
+    /// See [`EpochTime::accuracy`] for the error bound of the estimation.
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn epoch_time(&self) -> Result<EpochTime, Error> {
+        let start = unix_time(self.clock.as_ref());
+        let data = self
+            .read(SERVICE_HISTORY_UUID, CHARACTERISTIC_HISTORY_TIME_UUID)
             .await?;
-        tracing::trace!(
-            message = "writing",
-            service = SERVICE_HISTORY_ID,
-            characteristic = CHARACTERISTIC_HISTORY_CTRL_ID
+        let uptime = decode_uptime(&data)?;
+        let end = unix_time(self.clock.as_ref());
+        let estimate = self.epoch.lock().expect("epoch estimator poisoned").push(
+            uptime,
+            (start + end) / 2.0,
+            end - start,
         );
-        ctrl_char
-            .write_ext(&CMD_HISTORY_READ_INIT, &WRITE_OPTS)
-            .await
-            .map_err(|err| Error::UnableToWrite {
-                characteristic_id: CHARACTERISTIC_HISTORY_CTRL_ID,
-                service_id: SERVICE_HISTORY_ID,
-                cause: err,
-            })?;
-        //
-        let char = self
-            .characteristic(SERVICE_HISTORY_ID, CHARACTERISTIC_HISTORY_READ_ID)
-            .await?;
-        tracing::trace!(
-            message = "reading",
-            service = SERVICE_HISTORY_ID,
-            characteristic = CHARACTERISTIC_HISTORY_READ_ID
+        tracing::debug!(
+            message = "epoch time estimated",
+            epoch = estimate.timestamp(),
+            drift = estimate.drift()
         );
-        let raw_history_data = char.read().await.map_err(|err| Error::UnableToRead {
-            characteristic_id: CHARACTERISTIC_HISTORY_READ_ID,
-            service_id: SERVICE_HISTORY_ID,
-            cause: err,
-        })?;
-        let history_length = u16::from_le_bytes([raw_history_data[0], raw_history_data[1]]);
-        //
-        let mut result = Vec::with_capacity(history_length as usize);
-        if history_length > 0 {
-            let epoch_time = self.read_epoch_time().await?;
-            let read_char = self
-                .characteristic(SERVICE_HISTORY_ID, CHARACTERISTIC_HISTORY_READ_ID)
-                .await?;
-            for i in 0..history_length {
-                tracing::debug!("loading entry {i}");
-                let payload = self.historical_entry_address(i);
-                tracing::trace!(
-                    message = "writing",
-                    service = SERVICE_HISTORY_ID,
-                    characteristic = CHARACTERISTIC_HISTORY_CTRL_ID
-                );
-                ctrl_char
-                    .write_ext(&payload, &WRITE_OPTS)
-                    .await
-                    .map_err(|err| Error::UnableToWrite {
-                        characteristic_id: CHARACTERISTIC_HISTORY_CTRL_ID,
-                        service_id: SERVICE_HISTORY_ID,
-                        cause: err,
-                    })?;
-                tracing::trace!(
-                    message = "reading",
-                    service = SERVICE_HISTORY_ID,
-                    characteristic = CHARACTERISTIC_HISTORY_READ_ID
-                );
-                let data = read_char.read().await.map_err(|err| Error::UnableToRead {
-                    characteristic_id: CHARACTERISTIC_HISTORY_READ_ID,
-                    service_id: SERVICE_HISTORY_ID,
-                    cause: err,
-                })?;
-                result.push(HistoricalEntry::new(data, epoch_time));
+        Ok(estimate)
+    }
+
+    /// Sets the device clock to the given time, so the boot time of the device becomes the
+    /// unix epoch, and reads it back to check it was applied.
+    ///
+    /// Correcting the drift of the clock before leaving the device unattended improves the
+    /// timestamps of its history. The history should be read before, since the entries
+    /// already recorded keep their uptime and get timestamped from the new boot time.
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn set_device_time(&self, time: SystemTime) -> Result<(), Error> {
+        let seconds = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+            .min(u64::from(u32::MAX)) as u32;
+        self.write(
+            SERVICE_HISTORY_UUID,
+            CHARACTERISTIC_HISTORY_TIME_UUID,
+            &seconds.to_le_bytes(),
+        )
+        .await?;
+        self.epoch.lock().expect("epoch estimator poisoned").clear();
+        if !self.write_verification.is_enabled() {
+            return Ok(());
+        }
+        let data = self
+            .read(SERVICE_HISTORY_UUID, CHARACTERISTIC_HISTORY_TIME_UUID)
+            .await?;
+        let uptime = decode_uptime(&data)?;
+        // the clock keeps ticking between the write and the read
+        if (seconds..=seconds.saturating_add(DEVICE_TIME_TOLERANCE)).contains(&uptime) {
+            Ok(())
+        } else {
+            self.write_mismatch(
+                SERVICE_HISTORY_UUID,
+                CHARACTERISTIC_HISTORY_TIME_UUID,
+                &seconds.to_le_bytes(),
+                data,
+            )
+        }
+    }
+
+    /// Estimation of the boot time of the device from the previous reads, without reading
+    /// the device clock.
+    pub fn last_epoch_time(&self) -> Option<EpochTime> {
+        self.epoch
+            .lock()
+            .expect("epoch estimator poisoned")
+            .estimate()
+    }
+
+    /// Switches the device in history mode and reads the number of entries.
+    async fn read_history_length(&self) -> Result<u32, Error> {
+        self.write(
+            SERVICE_HISTORY_UUID,
+            CHARACTERISTIC_HISTORY_CTRL_UUID,
+            &CMD_HISTORY_READ_INIT,
+        )
+        .await?;
+        let raw_history_data = self
+            .read(SERVICE_HISTORY_UUID, CHARACTERISTIC_HISTORY_READ_UUID)
+            .await?;
+        Ok(decode_history_length(&raw_history_data, self.model)?)
+    }
+
+    async fn start_history_read(&self, start: u32) -> Result<HistoryReader<G>, Error> {
+        let length = self.read_history_length().await?;
+        let epoch = if length > 0 {
+            self.epoch_time().await?
+        } else {
+            EpochTime::exact(0)
+        };
+        self.history_open.store(length > 0, Ordering::Relaxed);
+        Ok(HistoryReader {
+            gatt: self.gatt.clone(),
+            client: self.client.clone(),
+            model: self.model,
+            epoch,
+            page: 0,
+            length,
+            index: start.min(length),
+            open: self.history_open.clone(),
+        })
+    }
+
+    /// Whether a history download is in progress, started and not read until the end, like
+    /// when the future reading it is dropped, or a [`HistorySession`] is neither committed
+    /// nor aborted.
+    ///
+    /// The device considers its entries as read unless [`Self::abort_history_read`] is
+    /// called then.
+    pub fn is_history_open(&self) -> bool {
+        self.history_open.load(Ordering::Relaxed)
+    }
+
+    /// Reads the number of historical entries stored on the device, without downloading them.
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn history_count(&self) -> Result<u32, Error> {
+        self.read_history_length().await
+    }
+
+    /// Streams the historical entries stored on the device.
+    ///
+    /// The entries are fetched one by one while the stream is polled, dropping the stream
+    /// stops the download. Unlike the other operations, a disconnection during the download
+    /// isn't recovered since the device leaves the history mode.
+    ///
+    /// The reboots only being found once all the entries are read, the streamed entries are
+    /// never marked [`TimestampConfidence::BeforeReboot`], unlike the ones of
+    /// [`Self::read_historical_values`].
+    pub fn historical_values_stream(
+        &self,
+    ) -> impl Stream<Item = Result<HistoricalEntry, Error>> + '_ {
+        stream::once(self.start_history_read(0))
+            .map_ok(|reader| {
+                stream::try_unfold(reader, |mut reader| async move {
+                    match reader.next().await? {
+                        Some(entry) => Ok(Some((entry, reader))),
+                        None => Ok(None),
+                    }
+                })
+            })
+            .try_flatten()
+    }
+
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn read_historical_values(&self) -> Result<Vec<HistoricalEntry>, Error> {
+        self.read_historical_values_with_progress(|_, _| {}).await
+    }
+
+    /// Reads the historical entries, sorted by timestamp without duplicates, with the gaps
+    /// and reboots found in the history.
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn read_history_set(&self) -> Result<HistorySet, Error> {
+        Ok(HistorySet::from(self.read_historical_values().await?))
+    }
+
+    /// Reads the historical entries, calling `progress` with the number of entries
+    /// loaded so far and the total number of entries after each of them.
+    #[tracing::instrument(skip(self, progress), fields(address = %self.client.address()))]
+    pub async fn read_historical_values_with_progress<F>(
+        &self,
+        mut progress: F,
+    ) -> Result<Vec<HistoricalEntry>, Error>
+    where
+        F: FnMut(u32, u32),
+    {
+        let mut reader = self.start_history_read(0).await?;
+        let mut result = Vec::with_capacity(reader.length as usize);
+        progress(reader.index, reader.length);
+        while let Some(entry) = reader.next().await? {
+            result.push(entry);
+            progress(reader.index, reader.length);
+        }
+        history::mark_before_reboot(&mut result);
+        Ok(result)
+    }
+
+    /// Reads the historical entries starting at the given cursor.
+    ///
+    /// On success, the returned cursor points after the last entry of the device. If the
+    /// download fails midway, a [`Error::HistoryInterrupted`] is returned with the entries
+    /// read so far and the cursor to resume from. If the cursor is beyond the number of
+    /// entries on the device, nothing is read.
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn read_historical_values_from(
+        &self,
+        cursor: HistoryCursor,
+    ) -> Result<(Vec<HistoricalEntry>, HistoryCursor), Error> {
+        let mut reader = self.start_history_read(cursor.index).await?;
+        let mut result = Vec::with_capacity((reader.length - reader.index) as usize);
+        loop {
+            match reader.next().await {
+                Ok(Some(entry)) => result.push(entry),
+                Ok(None) => {
+                    history::mark_before_reboot(&mut result);
+                    return Ok((result, HistoryCursor::new(reader.index)));
+                }
+                Err(err) => {
+                    return Err(Error::HistoryInterrupted {
+                        cursor: HistoryCursor::new(reader.index),
+                        entries: result,
+                        cause: Box::new(err),
+                    })
+                }
+            }
+        }
+    }
+
+    /// Reads the historical entries with a timestamp strictly after the given one.
+    ///
+    /// The entries being sorted on the device, the cutoff is located with a binary search
+    /// so only a few entries older than the timestamp are downloaded. When they aren't
+    /// sorted, like after a reboot of the device, all the entries are read instead.
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn read_historical_values_since(
+        &self,
+        timestamp: u64,
+    ) -> Result<Vec<HistoricalEntry>, Error> {
+        let mut reader = self.start_history_read(0).await?;
+        if reader.length == 0 {
+            return Ok(Vec::new());
+        }
+        let range = match reader.locate_since(timestamp).await? {
+            Some(range) => range,
+            None => {
+                tracing::debug!("history not sorted, reading all the entries");
+                0..reader.length
+            }
+        };
+        let mut result = Vec::with_capacity(range.len());
+        for index in range {
+            let entry = reader.read_entry(index).await?;
+            if entry.is_padding() {
+                tracing::trace!("skipping padding entry {index}");
+            } else if entry.timestamp() > timestamp {
+                result.push(entry);
             }
         }
+        reader.finish();
+        history::mark_before_reboot(&mut result);
         Ok(result)
     }
 
-    #[tracing::instrument(skip(self), fields(address = %self.device.address()))]
+    /// Reads the historical entries and returns a session to acknowledge them.
+    ///
+    /// The entries are only cleared from the device once the session is committed.
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn read_history_session(&self) -> Result<HistorySession<'_, G>, Error> {
+        let entries = self.read_historical_values().await?;
+        // the session is open until committed or aborted
+        self.history_open.store(true, Ordering::Relaxed);
+        Ok(HistorySession {
+            miflora: self,
+            entries,
+        })
+    }
+
+    #[deprecated(note = "use `read_history_session` and commit it, or `force_clear_history`")]
     pub async fn clear_historical_entries(&self) -> Result<(), Error> {
-        let ctrl_char = self
-            .characteristic(SERVICE_HISTORY_ID, CHARACTERISTIC_HISTORY_CTRL_ID)
-            .await?;
-        tracing::trace!(
-            message = "writing",
-            service = SERVICE_HISTORY_ID,
-            characteristic = CHARACTERISTIC_HISTORY_CTRL_ID
-        );
-        ctrl_char
-            .write_ext(&CMD_HISTORY_READ_SUCCESS, &WRITE_OPTS)
-            .await
-            .map_err(|err| Error::UnableToRead {
-                characteristic_id: CHARACTERISTIC_HISTORY_CTRL_ID,
-                service_id: SERVICE_HISTORY_ID,
-                cause: err,
-            })?;
+        self.force_clear_history().await
+    }
+
+    /// Clears the historical entries of the device, whether they have been read or not.
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn force_clear_history(&self) -> Result<(), Error> {
+        self.send_history_command(&CMD_HISTORY_READ_SUCCESS).await
+    }
+
+    /// Notifies the device the history transfer failed, so it doesn't consider the entries
+    /// as read.
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn abort_history_read(&self) -> Result<(), Error> {
+        self.send_history_command(&CMD_HISTORY_READ_FAILED).await
+    }
+
+    async fn send_history_command(&self, payload: &[u8]) -> Result<(), Error> {
+        self.write(
+            SERVICE_HISTORY_UUID,
+            CHARACTERISTIC_HISTORY_CTRL_UUID,
+            payload,
+        )
+        .await?;
+        self.history_open.store(false, Ordering::Relaxed);
         Ok(())
     }
 
-    async fn set_realtime_data_mode(&self, enabled: bool) -> Result<(), Error> {
+    /// Makes the device LED blink once, useful to physically identify a sensor.
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn blink_led(&self) -> Result<(), Error> {
+        self.set_device_mode(&CMD_BLINK_LED).await
+    }
+
+    /// Enables or disables the realtime mode, required to read the realtime values.
+    ///
+    /// The realtime mode drains the battery faster, it should be disabled when not needed.
+    #[tracing::instrument(skip(self), fields(address = %self.client.address()))]
+    pub async fn set_realtime_data_mode(&self, enabled: bool) -> Result<(), Error> {
         self.set_device_mode(if enabled {
             &CMD_REALTIME_ENABLE
         } else {
@@ -577,32 +1879,41 @@ impl Miflora {
     }
 
     async fn set_device_mode(&self, payload: &[u8]) -> Result<(), Error> {
-        let char = self
-            .characteristic(SERVICE_DATA_ID, CHARACTERISTIC_MODE_ID)
+        self.write(SERVICE_DATA_UUID, CHARACTERISTIC_MODE_UUID, payload)
             .await?;
-        tracing::trace!(
-            message = "writing",
-            service = SERVICE_DATA_ID,
-            characteristic = CHARACTERISTIC_MODE_ID
-        );
-        char.write_ext(payload, &WRITE_OPTS)
-            .await
-            .map_err(|err| Error::UnableToWrite {
-                service_id: SERVICE_DATA_ID,
-                characteristic_id: CHARACTERISTIC_MODE_ID,
-                cause: err,
-            })?;
-        let data = char.read().await.map_err(|err| Error::UnableToRead {
-            characteristic_id: CHARACTERISTIC_MODE_ID,
-            service_id: SERVICE_DATA_ID,
-            cause: err,
-        })?;
-        if !data.eq(payload) {
-            return Err(Error::InvalidWrittenValue {
-                characteristic_id: CHARACTERISTIC_MODE_ID,
-                service_id: SERVICE_DATA_ID,
-            });
+        if !self.write_verification.is_enabled() {
+            return Ok(());
+        }
+        let data = self
+            .read(SERVICE_DATA_UUID, CHARACTERISTIC_MODE_UUID)
+            .await?;
+        if data.eq(payload) {
+            Ok(())
+        } else {
+            self.write_mismatch(SERVICE_DATA_UUID, CHARACTERISTIC_MODE_UUID, payload, data)
+        }
+    }
+
+    /// Handles a value read back that doesn't match the written one, according to the
+    /// write verification.
+    fn write_mismatch(
+        &self,
+        service_id: Uuid,
+        characteristic_id: Uuid,
+        expected: &[u8],
+        actual: Vec<u8>,
+    ) -> Result<(), Error> {
+        let err = Error::WriteMismatch {
+            characteristic_id,
+            service_id,
+            expected: expected.to_vec(),
+            actual,
+        };
+        if self.write_verification == WriteVerification::Lenient {
+            tracing::warn!(message = "written value not read back", cause = %err);
+            Ok(())
+        } else {
+            Err(err)
         }
-        Ok(())
     }
 }
@@ -0,0 +1,164 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bluer::{Address, Uuid};
+
+use crate::Error;
+
+/// Operation with a device reported to an [`Observer`] when it fails.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Operation {
+    Connect,
+    Disconnect,
+    Read,
+    Write,
+    /// Reading the values broadcast by a device, see [`crate::MifloraFleet::collect`]
+    Advertisement,
+}
+
+impl Operation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Connect => "connect",
+            Self::Disconnect => "disconnect",
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::Advertisement => "advertisement",
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Hooks called along the communication with the devices, to plug logging, metrics or
+/// a user interface.
+///
+/// Every method does nothing by default. They're called inline, so they should return
+/// quickly and hand any slow work over to another task.
+pub trait Observer: fmt::Debug + Send + Sync {
+    /// The device is connected, after the retries if any.
+    fn on_connect(&self, address: Address, elapsed: Duration) {
+        let _ = (address, elapsed);
+    }
+
+    /// A characteristic has been read from the device.
+    fn on_read(&self, address: Address, characteristic: Uuid, payload: &[u8], elapsed: Duration) {
+        let _ = (address, characteristic, payload, elapsed);
+    }
+
+    /// An operation failed, after the retries if any.
+    fn on_error(&self, address: Address, operation: Operation, error: &Error, elapsed: Duration) {
+        let _ = (address, operation, error, elapsed);
+    }
+
+    /// An entry of the history has been read, `loaded` out of `total`.
+    fn on_history_progress(&self, address: Address, loaded: u32, total: u32) {
+        let _ = (address, loaded, total);
+    }
+
+    /// The device is disconnected.
+    fn on_disconnect(&self, address: Address, elapsed: Duration) {
+        let _ = (address, elapsed);
+    }
+}
+
+/// Shares the same observer between several devices.
+impl<O: Observer + ?Sized> Observer for Arc<O> {
+    fn on_connect(&self, address: Address, elapsed: Duration) {
+        (**self).on_connect(address, elapsed)
+    }
+
+    fn on_read(&self, address: Address, characteristic: Uuid, payload: &[u8], elapsed: Duration) {
+        (**self).on_read(address, characteristic, payload, elapsed)
+    }
+
+    fn on_error(&self, address: Address, operation: Operation, error: &Error, elapsed: Duration) {
+        (**self).on_error(address, operation, error, elapsed)
+    }
+
+    fn on_history_progress(&self, address: Address, loaded: u32, total: u32) {
+        (**self).on_history_progress(address, loaded, total)
+    }
+
+    fn on_disconnect(&self, address: Address, elapsed: Duration) {
+        (**self).on_disconnect(address, elapsed)
+    }
+}
+
+/// Observer used when none is configured.
+#[derive(Debug)]
+pub(crate) struct NoObserver;
+
+impl Observer for NoObserver {}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use bluer::{Address, Uuid};
+
+    use super::{Observer, Operation};
+    use crate::testing::FakeMiflora;
+    use crate::{Error, MifloraBuilder};
+
+    #[derive(Debug, Default)]
+    struct Events(Mutex<Vec<String>>);
+
+    impl Observer for Events {
+        fn on_connect(&self, _: Address, _: Duration) {
+            self.0.lock().unwrap().push("connect".into());
+        }
+
+        fn on_read(&self, _: Address, _: Uuid, _: &[u8], _: Duration) {
+            self.0.lock().unwrap().push("read".into());
+        }
+
+        fn on_error(&self, _: Address, operation: Operation, _: &Error, _: Duration) {
+            self.0.lock().unwrap().push(format!("error {operation}"));
+        }
+
+        fn on_history_progress(&self, _: Address, loaded: u32, total: u32) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("history {loaded}/{total}"));
+        }
+
+        fn on_disconnect(&self, _: Address, _: Duration) {
+            self.0.lock().unwrap().push("disconnect".into());
+        }
+    }
+
+    #[tokio::test]
+    async fn should_notify_the_observer() {
+        let events = Arc::new(Events::default());
+        let fake = FakeMiflora::default()
+            .with_uptime(7200)
+            .with_history_entry(3600, 180, 500, 30, 200)
+            .with_history_entry(7200, -15, 0, 31, 210);
+        let miflora = MifloraBuilder::from_client(fake)
+            .with_observer(events.clone())
+            .build();
+        miflora
+            .with_connection(|miflora| async move { miflora.read_historical_values().await })
+            .await
+            .unwrap();
+
+        let events = events.0.lock().unwrap();
+        assert_eq!(events.first().map(String::as_str), Some("connect"));
+        assert_eq!(events.last().map(String::as_str), Some("disconnect"));
+        let progress: Vec<_> = events
+            .iter()
+            .filter(|event| event.starts_with("history"))
+            .collect();
+        assert_eq!(progress, ["history 1/2", "history 2/2"]);
+        assert!(events.iter().any(|event| event == "read"));
+        assert!(!events.iter().any(|event| event.starts_with("error")));
+    }
+}
@@ -0,0 +1,392 @@
+//! Care profiles of the plants, with the ranges their values are recommended in.
+//!
+//! ```
+//! use bluer_miflora::plants::{evaluate, Issue, PlantDatabase};
+//! # use bluer_miflora::{Model, RealtimeEntry};
+//! # let mut payload = vec![0; 16];
+//! # payload[0..2].copy_from_slice(&215i16.to_le_bytes());
+//! # payload[3..7].copy_from_slice(&5000u32.to_le_bytes());
+//! # payload[7] = 10;
+//! # payload[8..10].copy_from_slice(&800u16.to_le_bytes());
+//! # let reading = RealtimeEntry::try_new(payload, Model::FlowerCare).unwrap();
+//!
+//! let database = PlantDatabase::bundled();
+//! let basil = database.get("ocimum basilicum").unwrap();
+//! let status = evaluate(&reading, basil);
+//! assert!(status.has(Issue::TooDry));
+//! ```
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::SensorReading;
+
+/// Bounds of a recommended range, both included.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Range<T> {
+    pub min: T,
+    pub max: T,
+}
+
+impl<T: PartialOrd> Range<T> {
+    pub const fn new(min: T, max: T) -> Self {
+        Self { min, max }
+    }
+
+    fn level(&self, value: T) -> Level {
+        if value < self.min {
+            Level::Low
+        } else if value > self.max {
+            Level::High
+        } else {
+            Level::Ok
+        }
+    }
+}
+
+/// Conditions a species grows best in.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlantProfile {
+    /// Scientific name of the species, used to find the profile
+    pub species: Cow<'static, str>,
+    /// Common name of the species
+    pub name: Cow<'static, str>,
+    /// Moisture of the soil in %
+    pub moisture: Range<u8>,
+    /// Conductivity of the soil in µS/cm
+    pub conductivity: Range<u16>,
+    /// Brightness in lux
+    pub brightness: Range<u32>,
+    /// Temperature in °C
+    pub temperature: Range<f32>,
+}
+
+macro_rules! profile {
+    ($species:literal, $name:literal, $moisture:expr, $conductivity:expr, $brightness:expr, $temperature:expr) => {
+        PlantProfile {
+            species: Cow::Borrowed($species),
+            name: Cow::Borrowed($name),
+            moisture: Range::new($moisture.0, $moisture.1),
+            conductivity: Range::new($conductivity.0, $conductivity.1),
+            brightness: Range::new($brightness.0, $brightness.1),
+            temperature: Range::new($temperature.0, $temperature.1),
+        }
+    };
+}
+
+/// Profiles shipped with the crate.
+fn bundled_profiles() -> [PlantProfile; 12] {
+    [
+        profile!(
+            "aloe vera",
+            "Aloe vera",
+            (7, 50),
+            (200, 2000),
+            (3700, 70000),
+            (10.0, 32.0)
+        ),
+        profile!(
+            "capsicum annuum",
+            "Chili pepper",
+            (15, 60),
+            (350, 2000),
+            (4000, 65000),
+            (10.0, 35.0)
+        ),
+        profile!(
+            "chlorophytum comosum",
+            "Spider plant",
+            (15, 60),
+            (350, 1000),
+            (1000, 30000),
+            (10.0, 32.0)
+        ),
+        profile!(
+            "epipremnum aureum",
+            "Pothos",
+            (15, 60),
+            (350, 2000),
+            (800, 30000),
+            (12.0, 32.0)
+        ),
+        profile!(
+            "ficus elastica",
+            "Rubber plant",
+            (15, 60),
+            (350, 2000),
+            (1500, 40000),
+            (12.0, 32.0)
+        ),
+        profile!(
+            "lavandula angustifolia",
+            "Lavender",
+            (10, 50),
+            (350, 2000),
+            (4000, 80000),
+            (5.0, 35.0)
+        ),
+        profile!(
+            "mentha spicata",
+            "Mint",
+            (20, 60),
+            (350, 2000),
+            (2500, 60000),
+            (5.0, 32.0)
+        ),
+        profile!(
+            "monstera deliciosa",
+            "Monstera",
+            (15, 60),
+            (350, 2000),
+            (800, 15000),
+            (12.0, 32.0)
+        ),
+        profile!(
+            "ocimum basilicum",
+            "Basil",
+            (15, 60),
+            (350, 2000),
+            (2500, 60000),
+            (10.0, 35.0)
+        ),
+        profile!(
+            "rosmarinus officinalis",
+            "Rosemary",
+            (10, 50),
+            (350, 2000),
+            (4000, 80000),
+            (5.0, 35.0)
+        ),
+        profile!(
+            "sansevieria trifasciata",
+            "Snake plant",
+            (7, 50),
+            (200, 1500),
+            (800, 30000),
+            (10.0, 32.0)
+        ),
+        profile!(
+            "solanum lycopersicum",
+            "Tomato",
+            (20, 60),
+            (350, 2000),
+            (5000, 70000),
+            (10.0, 35.0)
+        ),
+    ]
+}
+
+/// Profiles of the species, by scientific name.
+#[derive(Clone, Debug, Default)]
+pub struct PlantDatabase {
+    profiles: BTreeMap<String, PlantProfile>,
+}
+
+impl PlantDatabase {
+    /// Database of the profiles shipped with the crate.
+    pub fn bundled() -> Self {
+        let mut database = Self::default();
+        for profile in bundled_profiles() {
+            database.insert(profile);
+        }
+        database
+    }
+
+    /// Finds the profile of the species, ignoring the case.
+    pub fn get(&self, species: &str) -> Option<&PlantProfile> {
+        self.profiles.get(&species.to_lowercase())
+    }
+
+    /// Adds the profile, replacing the one of the same species.
+    pub fn insert(&mut self, profile: PlantProfile) -> Option<PlantProfile> {
+        self.profiles
+            .insert(profile.species.to_lowercase(), profile)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &PlantProfile> {
+        self.profiles.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.profiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.profiles.is_empty()
+    }
+
+    /// Adds the profiles of the JSON array, replacing the ones of the same species.
+    #[cfg(feature = "serde")]
+    pub fn extend_from_json(&mut self, json: &str) -> Result<(), serde_json::Error> {
+        let profiles: Vec<PlantProfile> = serde_json::from_str(json)?;
+        for profile in profiles {
+            self.insert(profile);
+        }
+        Ok(())
+    }
+}
+
+/// Position of a value compared to its recommended range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Level {
+    Low,
+    Ok,
+    High,
+}
+
+/// Condition out of the recommended range of the species.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Issue {
+    TooDry,
+    TooWet,
+    LowFertility,
+    HighFertility,
+    TooDark,
+    TooBright,
+    TooCold,
+    TooHot,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::TooDry => "too dry",
+            Self::TooWet => "too wet",
+            Self::LowFertility => "low fertility",
+            Self::HighFertility => "high fertility",
+            Self::TooDark => "too dark",
+            Self::TooBright => "too bright",
+            Self::TooCold => "too cold",
+            Self::TooHot => "too hot",
+        })
+    }
+}
+
+/// Result of the evaluation of a reading against a profile.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CareStatus {
+    issues: Vec<Issue>,
+}
+
+impl CareStatus {
+    /// Whether all the values are in their recommended range.
+    pub fn is_healthy(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub fn has(&self, issue: Issue) -> bool {
+        self.issues.contains(&issue)
+    }
+
+    pub fn issues(&self) -> &[Issue] {
+        &self.issues
+    }
+}
+
+/// Checks the values of the reading against the recommended ranges of the profile.
+///
+/// The brightness isn't checked when the device has no brightness sensor.
+pub fn evaluate<R: SensorReading + ?Sized>(reading: &R, profile: &PlantProfile) -> CareStatus {
+    let mut issues = Vec::new();
+    let mut check = |level: Level, low: Issue, high: Issue| match level {
+        Level::Low => issues.push(low),
+        Level::High => issues.push(high),
+        Level::Ok => {}
+    };
+    check(
+        profile.moisture.level(reading.moisture()),
+        Issue::TooDry,
+        Issue::TooWet,
+    );
+    check(
+        profile.conductivity.level(reading.conductivity()),
+        Issue::LowFertility,
+        Issue::HighFertility,
+    );
+    if let Some(brightness) = reading.brightness() {
+        check(
+            profile.brightness.level(brightness),
+            Issue::TooDark,
+            Issue::TooBright,
+        );
+    }
+    check(
+        profile.temperature.level(reading.temperature_celsius()),
+        Issue::TooCold,
+        Issue::TooHot,
+    );
+    CareStatus { issues }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{evaluate, Issue, PlantDatabase};
+    use crate::{Model, RealtimeEntry};
+
+    fn reading(
+        temperature: i16,
+        brightness: u32,
+        moisture: u8,
+        conductivity: u16,
+    ) -> RealtimeEntry {
+        let mut payload = vec![0; 16];
+        payload[0..2].copy_from_slice(&temperature.to_le_bytes());
+        payload[3..7].copy_from_slice(&brightness.to_le_bytes());
+        payload[7] = moisture;
+        payload[8..10].copy_from_slice(&conductivity.to_le_bytes());
+        RealtimeEntry::try_new(payload, Model::FlowerCare).unwrap()
+    }
+
+    #[test]
+    fn should_find_bundled_profiles_ignoring_case() {
+        let database = PlantDatabase::bundled();
+        assert_eq!(database.len(), 12);
+        assert_eq!(database.get("Ocimum Basilicum").unwrap().name, "Basil");
+        assert!(database.get("unknown").is_none());
+    }
+
+    #[test]
+    fn should_evaluate_reading() {
+        let database = PlantDatabase::bundled();
+        let basil = database.get("ocimum basilicum").unwrap();
+
+        let status = evaluate(&reading(215, 10000, 30, 800), basil);
+        assert!(status.is_healthy());
+
+        let status = evaluate(&reading(215, 100, 5, 800), basil);
+        assert_eq!(status.issues(), &[Issue::TooDry, Issue::TooDark]);
+
+        let status = evaluate(&reading(400, 10000, 80, 100), basil);
+        assert_eq!(
+            status.issues(),
+            &[Issue::TooWet, Issue::LowFertility, Issue::TooHot]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn should_override_profiles_from_json() {
+        let mut database = PlantDatabase::bundled();
+        database
+            .extend_from_json(
+                r#"[{
+                    "species": "Ocimum basilicum",
+                    "name": "Thai basil",
+                    "moisture": { "min": 20, "max": 50 },
+                    "conductivity": { "min": 350, "max": 2000 },
+                    "brightness": { "min": 2500, "max": 60000 },
+                    "temperature": { "min": 10.0, "max": 35.0 }
+                }]"#,
+            )
+            .unwrap();
+        assert_eq!(database.len(), 12);
+        let basil = database.get("ocimum basilicum").unwrap();
+        assert_eq!(basil.name, "Thai basil");
+        assert_eq!(basil.moisture.min, 20);
+    }
+}
@@ -0,0 +1,156 @@
+use crate::{Error, HistoricalEntry, RealtimeEntry};
+
+/// Range of temperatures the sensor can measure, in 0.1 °C.
+const TEMPERATURE_RANGE: std::ops::RangeInclusive<i16> = -400..=800;
+/// Maximum moisture, in %.
+const MAX_MOISTURE: u8 = 100;
+/// Maximum conductivity the sensor can measure, in µS/cm.
+const MAX_CONDUCTIVITY: u16 = 20000;
+
+/// Values measured by the sensors, common to the realtime and historical entries, to
+/// handle both of them the same way.
+pub trait SensorReading {
+    /// Temperature in 0.1 °C, negative below freezing.
+    fn temperature(&self) -> i16;
+
+    /// Temperature in °C.
+    fn temperature_celsius(&self) -> f32 {
+        self.temperature() as f32 / 10.0
+    }
+
+    /// Brightness in lux, if the device has a brightness sensor.
+    fn brightness(&self) -> Option<u32>;
+
+    /// Moisture in %.
+    fn moisture(&self) -> u8;
+
+    /// Conductivity in µS/cm.
+    fn conductivity(&self) -> u16;
+
+    /// Unix timestamp in seconds of the measure, when recorded by the device.
+    fn timestamp(&self) -> Option<u64>;
+
+    /// Checks the values are in the ranges the sensor can measure, the devices sometimes
+    /// sending frames filled with zeros or `0xFF`.
+    fn validate(&self) -> Result<(), Error> {
+        let reason = if !TEMPERATURE_RANGE.contains(&self.temperature()) {
+            "temperature out of range"
+        } else if self.moisture() > MAX_MOISTURE {
+            "moisture out of range"
+        } else if self.conductivity() >= MAX_CONDUCTIVITY {
+            "conductivity out of range"
+        } else if self.temperature() == 0
+            && self.brightness().unwrap_or(0) == 0
+            && self.moisture() == 0
+            && self.conductivity() == 0
+        {
+            "all the values are zero"
+        } else {
+            return Ok(());
+        };
+        Err(Error::ImplausibleReading { reason })
+    }
+
+    /// Whether the values are in the ranges the sensor can measure, see [`Self::validate`].
+    fn is_plausible(&self) -> bool {
+        self.validate().is_ok()
+    }
+}
+
+/// Formats the values with their units, like `21.3°C, 34% moisture, 351 µS/cm, 1243 lux`.
+fn fmt_values<R: SensorReading>(reading: &R, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(
+        f,
+        "{:.1}°C, {}% moisture, {} µS/cm",
+        reading.temperature_celsius(),
+        reading.moisture(),
+        reading.conductivity()
+    )?;
+    if let Some(brightness) = reading.brightness() {
+        write!(f, ", {brightness} lux")?;
+    }
+    Ok(())
+}
+
+impl std::fmt::Display for RealtimeEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_values(self, f)
+    }
+}
+
+/// Prefixes the values with the date of the entry, or its unix timestamp without the
+/// `chrono` feature.
+impl std::fmt::Display for HistoricalEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        #[cfg(feature = "chrono")]
+        write!(f, "{}: ", self.datetime().to_rfc3339())?;
+        #[cfg(not(feature = "chrono"))]
+        write!(f, "{}: ", HistoricalEntry::timestamp(self))?;
+        fmt_values(self, f)
+    }
+}
+
+impl SensorReading for RealtimeEntry {
+    fn temperature(&self) -> i16 {
+        RealtimeEntry::temperature(self)
+    }
+
+    fn brightness(&self) -> Option<u32> {
+        RealtimeEntry::brightness(self)
+    }
+
+    fn moisture(&self) -> u8 {
+        RealtimeEntry::moisture(self)
+    }
+
+    fn conductivity(&self) -> u16 {
+        RealtimeEntry::conductivity(self)
+    }
+
+    fn timestamp(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl SensorReading for HistoricalEntry {
+    fn temperature(&self) -> i16 {
+        HistoricalEntry::temperature(self)
+    }
+
+    fn brightness(&self) -> Option<u32> {
+        HistoricalEntry::brightness(self)
+    }
+
+    fn moisture(&self) -> u8 {
+        HistoricalEntry::moisture(self)
+    }
+
+    fn conductivity(&self) -> u16 {
+        HistoricalEntry::conductivity(self)
+    }
+
+    fn timestamp(&self) -> Option<u64> {
+        Some(HistoricalEntry::timestamp(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Model, RealtimeEntry};
+
+    #[test]
+    fn should_display_values_with_units() {
+        let mut payload = vec![0; 16];
+        payload[0..2].copy_from_slice(&213i16.to_le_bytes());
+        payload[3..7].copy_from_slice(&1243u32.to_le_bytes());
+        payload[7] = 34;
+        payload[8..10].copy_from_slice(&351u16.to_le_bytes());
+        let entry = RealtimeEntry::try_new(payload.clone(), Model::FlowerCare).unwrap();
+        assert_eq!(
+            entry.to_string(),
+            "21.3°C, 34% moisture, 351 µS/cm, 1243 lux"
+        );
+        let entry = RealtimeEntry::try_new(payload, Model::Ropot).unwrap();
+        assert_eq!(entry.to_string(), "21.3°C, 34% moisture, 351 µS/cm");
+    }
+}
@@ -0,0 +1,452 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use bluer::{Address, Uuid};
+use futures::stream::{self, BoxStream, StreamExt};
+
+use crate::{Error, GattClient};
+
+/// Exchange with a characteristic, as captured by a [`Recorder`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Exchange {
+    Read {
+        service_id: Uuid,
+        characteristic_id: Uuid,
+        payload: Vec<u8>,
+    },
+    Write {
+        service_id: Uuid,
+        characteristic_id: Uuid,
+        payload: Vec<u8>,
+    },
+    /// Value received from a subscription
+    Notify {
+        service_id: Uuid,
+        characteristic_id: Uuid,
+        payload: Vec<u8>,
+    },
+}
+
+impl Exchange {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Read { .. } => "read",
+            Self::Write { .. } => "write",
+            Self::Notify { .. } => "notify",
+        }
+    }
+
+    fn parts(&self) -> (Uuid, Uuid, &[u8]) {
+        match self {
+            Self::Read {
+                service_id,
+                characteristic_id,
+                payload,
+            }
+            | Self::Write {
+                service_id,
+                characteristic_id,
+                payload,
+            }
+            | Self::Notify {
+                service_id,
+                characteristic_id,
+                payload,
+            } => (*service_id, *characteristic_id, payload),
+        }
+    }
+}
+
+/// Exchanges of a session with a device, in the order they happened.
+///
+/// The recording is stored as text, one exchange per line with the service,
+/// characteristic and payload in hexadecimal, so it can be attached to a bug report.
+///
+/// ```text
+/// address C4:7C:8D:6A:3E:1F
+/// write 00001204-0000-1000-8000-00805f9b34fb 00001a00-0000-1000-8000-00805f9b34fb a01f
+/// read 00001204-0000-1000-8000-00805f9b34fb 00001a01-0000-1000-8000-00805f9b34fb d80000...
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Recording {
+    address: Address,
+    exchanges: Vec<Exchange>,
+}
+
+impl Recording {
+    pub fn new(address: Address) -> Self {
+        Self {
+            address,
+            exchanges: Vec::new(),
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    pub fn exchanges(&self) -> &[Exchange] {
+        &self.exchanges
+    }
+
+    pub fn push(&mut self, exchange: Exchange) {
+        self.exchanges.push(exchange);
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        std::fs::read_to_string(path)?
+            .parse()
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+}
+
+impl fmt::Display for Recording {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "address {}", self.address)?;
+        for exchange in &self.exchanges {
+            let (service_id, characteristic_id, payload) = exchange.parts();
+            writeln!(
+                f,
+                "{} {service_id} {characteristic_id} {}",
+                exchange.name(),
+                Hex(payload)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Recording {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut address = None;
+        let mut exchanges = Vec::new();
+        for (index, line) in value.lines().enumerate() {
+            let error = |reason| Error::InvalidRecording {
+                line: index + 1,
+                reason,
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let name = words.next().unwrap_or_default();
+            if name == "address" {
+                let value = words.next().ok_or_else(|| error("missing address"))?;
+                address = Some(value.parse().map_err(|_| error("invalid address"))?);
+                continue;
+            }
+            let service_id = words
+                .next()
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| error("invalid service"))?;
+            let characteristic_id = words
+                .next()
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| error("invalid characteristic"))?;
+            let payload = decode_hex(words.next().unwrap_or_default())
+                .ok_or_else(|| error("invalid payload"))?;
+            exchanges.push(match name {
+                "read" => Exchange::Read {
+                    service_id,
+                    characteristic_id,
+                    payload,
+                },
+                "write" => Exchange::Write {
+                    service_id,
+                    characteristic_id,
+                    payload,
+                },
+                "notify" => Exchange::Notify {
+                    service_id,
+                    characteristic_id,
+                    payload,
+                },
+                _ => return Err(error("unknown exchange")),
+            });
+        }
+        Ok(Self {
+            address: address.ok_or(Error::InvalidRecording {
+                line: 0,
+                reason: "missing address",
+            })?,
+            exchanges,
+        })
+    }
+}
+
+/// Formats bytes in lowercase hexadecimal, without separator.
+pub(crate) struct Hex<'a>(pub &'a [u8]);
+
+impl fmt::Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+pub(crate) fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(value.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+/// Client recording the successful exchanges of the wrapped client.
+#[derive(Clone, Debug)]
+pub struct Recorder<G> {
+    client: G,
+    recording: Arc<Mutex<Recording>>,
+}
+
+impl<G: GattClient> Recorder<G> {
+    pub fn new(client: G) -> Self {
+        let recording = Recording::new(client.address());
+        Self {
+            client,
+            recording: Arc::new(Mutex::new(recording)),
+        }
+    }
+
+    pub fn client(&self) -> &G {
+        &self.client
+    }
+
+    /// Exchanges recorded so far.
+    pub fn recording(&self) -> Recording {
+        self.recording.lock().expect("recording poisoned").clone()
+    }
+
+    fn record(&self, exchange: Exchange) {
+        self.recording
+            .lock()
+            .expect("recording poisoned")
+            .push(exchange);
+    }
+}
+
+impl<G: GattClient> GattClient for Recorder<G> {
+    fn address(&self) -> Address {
+        self.client.address()
+    }
+
+    async fn is_connected(&self) -> Result<bool, Error> {
+        self.client.is_connected().await
+    }
+
+    async fn connect(&self) -> Result<(), Error> {
+        self.client.connect().await
+    }
+
+    async fn disconnect(&self) -> Result<(), Error> {
+        self.client.disconnect().await
+    }
+
+    async fn read(&self, service_id: Uuid, char_id: Uuid) -> Result<Vec<u8>, Error> {
+        let payload = self.client.read(service_id, char_id).await?;
+        self.record(Exchange::Read {
+            service_id,
+            characteristic_id: char_id,
+            payload: payload.clone(),
+        });
+        Ok(payload)
+    }
+
+    async fn write(&self, service_id: Uuid, char_id: Uuid, payload: &[u8]) -> Result<(), Error> {
+        self.client.write(service_id, char_id, payload).await?;
+        self.record(Exchange::Write {
+            service_id,
+            characteristic_id: char_id,
+            payload: payload.to_vec(),
+        });
+        Ok(())
+    }
+
+    async fn subscribe(
+        &self,
+        service_id: Uuid,
+        char_id: Uuid,
+    ) -> Result<BoxStream<'static, Vec<u8>>, Error> {
+        let recorder = self.clone();
+        let notifications = self.client.subscribe(service_id, char_id).await?;
+        Ok(notifications
+            .inspect(move |payload| {
+                recorder.record(Exchange::Notify {
+                    service_id,
+                    characteristic_id: char_id,
+                    payload: payload.clone(),
+                })
+            })
+            .boxed())
+    }
+
+    async fn resolve(&self, characteristics: &[(Uuid, Uuid)]) -> Result<(), Error> {
+        self.client.resolve(characteristics).await
+    }
+}
+
+/// Client serving the exchanges of a [`Recording`] in order.
+///
+/// An operation that doesn't match the next recorded exchange fails with
+/// [`Error::ReplayMismatch`].
+#[derive(Clone, Debug)]
+pub struct Replay {
+    address: Address,
+    length: usize,
+    connected: Arc<Mutex<bool>>,
+    /// Exchanges not served yet, with their position in the recording
+    exchanges: Arc<Mutex<VecDeque<(usize, Exchange)>>>,
+}
+
+impl Replay {
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            address: recording.address,
+            length: recording.exchanges.len(),
+            connected: Default::default(),
+            exchanges: Arc::new(Mutex::new(
+                recording.exchanges.into_iter().enumerate().collect(),
+            )),
+        }
+    }
+
+    /// Number of recorded exchanges not served yet.
+    pub fn remaining(&self) -> usize {
+        self.exchanges.lock().expect("replay poisoned").len()
+    }
+
+    fn next(&self, expected: impl Fn(&Exchange) -> bool) -> Result<Exchange, Error> {
+        let mut exchanges = self.exchanges.lock().expect("replay poisoned");
+        match exchanges.front() {
+            Some((_, exchange)) if expected(exchange) => {
+                Ok(exchanges.pop_front().map(|(_, exchange)| exchange).unwrap())
+            }
+            Some((index, _)) => Err(Error::ReplayMismatch { index: *index }),
+            None => Err(Error::ReplayMismatch { index: self.length }),
+        }
+    }
+}
+
+impl GattClient for Replay {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn is_connected(&self) -> Result<bool, Error> {
+        Ok(*self.connected.lock().expect("replay poisoned"))
+    }
+
+    async fn connect(&self) -> Result<(), Error> {
+        *self.connected.lock().expect("replay poisoned") = true;
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<(), Error> {
+        *self.connected.lock().expect("replay poisoned") = false;
+        Ok(())
+    }
+
+    async fn read(&self, service_id: Uuid, char_id: Uuid) -> Result<Vec<u8>, Error> {
+        let exchange = self.next(|exchange| {
+            matches!(exchange, Exchange::Read { .. })
+                && exchange.parts().0 == service_id
+                && exchange.parts().1 == char_id
+        })?;
+        Ok(exchange.parts().2.to_vec())
+    }
+
+    async fn write(&self, service_id: Uuid, char_id: Uuid, payload: &[u8]) -> Result<(), Error> {
+        self.next(|exchange| {
+            matches!(exchange, Exchange::Write { .. })
+                && exchange.parts() == (service_id, char_id, payload)
+        })?;
+        Ok(())
+    }
+
+    /// Serves the notifications recorded next for the characteristic.
+    async fn subscribe(
+        &self,
+        service_id: Uuid,
+        char_id: Uuid,
+    ) -> Result<BoxStream<'static, Vec<u8>>, Error> {
+        let is_notification = |exchange: &Exchange| {
+            matches!(exchange, Exchange::Notify { .. })
+                && exchange.parts().0 == service_id
+                && exchange.parts().1 == char_id
+        };
+        let mut notifications = vec![self.next(is_notification)?.parts().2.to_vec()];
+        while let Ok(exchange) = self.next(is_notification) {
+            notifications.push(exchange.parts().2.to_vec());
+        }
+        Ok(stream::iter(notifications).boxed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Recorder, Recording, Replay};
+    use crate::testing::FakeMiflora;
+    use crate::{Error, MifloraBuilder};
+
+    #[tokio::test]
+    async fn should_replay_recorded_session() {
+        let fake = FakeMiflora::default()
+            .with_battery(54)
+            .with_realtime(230, 800, 40, 300)
+            .with_history_entry(60, 210, 100, 35, 250);
+        let recorder = Recorder::new(fake);
+        let miflora = MifloraBuilder::from_client(recorder.clone()).build();
+        miflora.connect().await.unwrap();
+        let snapshot = miflora.read_all(false).await.unwrap();
+        let entries = miflora.read_historical_values().await.unwrap();
+
+        let recording: Recording = recorder.recording().to_string().parse().unwrap();
+        assert_eq!(recording, recorder.recording());
+
+        let replay = Replay::new(recording);
+        let miflora = MifloraBuilder::from_client(replay.clone()).build();
+        miflora.connect().await.unwrap();
+        let replayed = miflora.read_all(false).await.unwrap();
+        assert_eq!(replayed.system().battery(), snapshot.system().battery());
+        assert_eq!(
+            replayed.realtime().temperature(),
+            snapshot.realtime().temperature()
+        );
+        let replayed = miflora.read_historical_values().await.unwrap();
+        assert_eq!(replayed.len(), entries.len());
+        assert_eq!(replayed[0].moisture(), 35);
+        assert_eq!(replay.remaining(), 0);
+    }
+
+    #[tokio::test]
+    async fn should_fail_when_diverging_from_recording() {
+        let recorder = Recorder::new(FakeMiflora::default());
+        let miflora = MifloraBuilder::from_client(recorder.clone()).build();
+        miflora.connect().await.unwrap();
+        miflora.read_battery().await.unwrap();
+
+        let miflora = MifloraBuilder::from_client(Replay::new(recorder.recording())).build();
+        miflora.connect().await.unwrap();
+        let err = miflora.blink_led().await.unwrap_err();
+        assert!(matches!(err, Error::ReplayMismatch { index: 0 }));
+    }
+
+    #[test]
+    fn should_reject_invalid_recording() {
+        let err = "address C4:7C:8D:00:00:01\nread 1204 1a01 00"
+            .parse::<Recording>()
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidRecording { line: 2, .. }));
+    }
+}
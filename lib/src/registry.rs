@@ -0,0 +1,237 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use bluer::{Adapter, AdapterEvent, Address};
+use futures::{pin_mut, StreamExt};
+use tokio::sync::broadcast;
+
+use crate::advertisement::{read_advertisement, PassiveReading};
+use crate::{detect_model, Clock, Error, Model, SignalQuality, SystemClock};
+
+/// Number of values kept for the subscribers too slow to receive them.
+const SUBSCRIPTION_CAPACITY: usize = 64;
+
+/// Value decoded from a new advertisement, sent to the subscribers of a [`Registry`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Advertised {
+    pub address: Address,
+    /// Time the advertisement was received
+    pub received: SystemTime,
+    pub reading: PassiveReading,
+}
+
+/// State of a device, as last seen by a [`Registry`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeviceState {
+    model: Model,
+    last_seen: SystemTime,
+    rssi: Option<i16>,
+    frame_counter: Option<u8>,
+    last_reading: Option<(SystemTime, PassiveReading)>,
+    last_connection: Option<SystemTime>,
+}
+
+impl DeviceState {
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    /// Last time an event was received for the device.
+    pub fn last_seen(&self) -> SystemTime {
+        self.last_seen
+    }
+
+    /// Last signal strength received, in dBm.
+    pub fn rssi(&self) -> Option<i16> {
+        self.rssi
+    }
+
+    pub fn signal_quality(&self) -> SignalQuality {
+        SignalQuality::from_rssi(self.rssi)
+    }
+
+    /// Last value decoded from the advertisements, with the time it was received.
+    pub fn last_reading(&self) -> Option<(SystemTime, PassiveReading)> {
+        self.last_reading
+    }
+
+    /// Last time a connection to the device succeeded, see [`Registry::record_connection`].
+    pub fn last_connection(&self) -> Option<SystemTime> {
+        self.last_connection
+    }
+}
+
+/// Keeps track of the mifloras around an adapter, from the adapter events.
+///
+/// The registry can be cloned to be read while another clone runs the discovery.
+#[derive(Clone, Debug)]
+pub struct Registry {
+    adapter: Adapter,
+    clock: Arc<dyn Clock>,
+    devices: Arc<Mutex<BTreeMap<Address, DeviceState>>>,
+    /// Devices that aren't mifloras
+    ignored: Arc<Mutex<HashSet<Address>>>,
+    advertised: broadcast::Sender<Advertised>,
+}
+
+impl Registry {
+    pub fn new(adapter: Adapter) -> Self {
+        Self {
+            adapter,
+            clock: Arc::new(SystemClock),
+            devices: Default::default(),
+            ignored: Default::default(),
+            advertised: broadcast::channel(SUBSCRIPTION_CAPACITY).0,
+        }
+    }
+
+    /// Clock used to timestamp the events.
+    pub fn with_clock<C: Clock + 'static>(mut self, value: C) -> Self {
+        self.clock = Arc::new(value);
+        self
+    }
+
+    pub fn adapter(&self) -> &Adapter {
+        &self.adapter
+    }
+
+    /// Receives the values decoded from the advertisements from now on, each frame once.
+    ///
+    /// A subscriber falling behind misses the oldest values, see [`broadcast::Receiver`].
+    pub fn subscribe(&self) -> broadcast::Receiver<Advertised> {
+        self.advertised.subscribe()
+    }
+
+    /// Discovers the devices and handles the events until the discovery stops.
+    pub async fn run(&self) -> Result<(), Error> {
+        let events = self
+            .adapter
+            .discover_devices_with_changes()
+            .await
+            .map_err(|err| Error::CommandFailed { cause: err })?;
+        pin_mut!(events);
+        while let Some(event) = events.next().await {
+            if let Err(err) = self.handle_event(event).await {
+                tracing::warn!(message = "unable to handle adapter event", cause = %err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Updates the state of the device concerned by the event, the devices that aren't
+    /// mifloras being ignored.
+    pub async fn handle_event(&self, event: AdapterEvent) -> Result<(), Error> {
+        let AdapterEvent::DeviceAdded(address) = event else {
+            return Ok(());
+        };
+        if self
+            .ignored
+            .lock()
+            .expect("registry poisoned")
+            .contains(&address)
+        {
+            return Ok(());
+        }
+        let device = self
+            .adapter
+            .device(address)
+            .map_err(|cause| Error::DeviceNotFound { address, cause })?;
+        let known_model = self.get(&address).map(|state| state.model);
+        let model = match known_model {
+            Some(model) => model,
+            None => match detect_model(&device).await {
+                Ok(Some(model)) => model,
+                Ok(None) => {
+                    self.ignored
+                        .lock()
+                        .expect("registry poisoned")
+                        .insert(address);
+                    return Ok(());
+                }
+                // not advertising yet, checked again on the next event
+                Err(Error::NoServiceData) => return Ok(()),
+                Err(err) => return Err(err),
+            },
+        };
+        let rssi = device
+            .rssi()
+            .await
+            .map_err(|err| Error::CommandFailed { cause: err })?;
+        let beacon = match read_advertisement(&device).await {
+            Ok(beacon) => beacon,
+            Err(err) => {
+                tracing::debug!(message = "unable to decode advertisement", address = %address, cause = %err);
+                None
+            }
+        };
+
+        let now = self.clock.now();
+        let mut devices = self.devices.lock().expect("registry poisoned");
+        let state = devices.entry(address).or_insert_with(|| DeviceState {
+            model,
+            last_seen: now,
+            rssi: None,
+            frame_counter: None,
+            last_reading: None,
+            last_connection: None,
+        });
+        state.last_seen = now;
+        if rssi.is_some() {
+            state.rssi = rssi;
+        }
+        if let Some(beacon) = beacon {
+            let previous = state.frame_counter.replace(beacon.frame_counter());
+            if previous != Some(beacon.frame_counter()) {
+                if let Some(reading) = beacon.reading() {
+                    state.last_reading = Some((now, reading));
+                    // failing only without any subscriber
+                    let _ = self.advertised.send(Advertised {
+                        address,
+                        received: now,
+                        reading,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Records a successful connection to the device.
+    pub fn record_connection(&self, address: Address) {
+        let now = self.clock.now();
+        if let Some(state) = self
+            .devices
+            .lock()
+            .expect("registry poisoned")
+            .get_mut(&address)
+        {
+            state.last_connection = Some(now);
+        }
+    }
+
+    pub fn get(&self, address: &Address) -> Option<DeviceState> {
+        self.devices
+            .lock()
+            .expect("registry poisoned")
+            .get(address)
+            .cloned()
+    }
+
+    /// States of the known devices, ordered by address.
+    pub fn devices(&self) -> Vec<(Address, DeviceState)> {
+        self.devices
+            .lock()
+            .expect("registry poisoned")
+            .iter()
+            .map(|(address, state)| (*address, state.clone()))
+            .collect()
+    }
+
+    pub fn remove(&self, address: &Address) -> Option<DeviceState> {
+        self.devices
+            .lock()
+            .expect("registry poisoned")
+            .remove(address)
+    }
+}
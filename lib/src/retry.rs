@@ -0,0 +1,170 @@
+use std::future::Future;
+use std::time::Duration;
+
+use crate::Error;
+
+/// Defines how many times an operation is retried and how long to wait between attempts.
+///
+/// The delay before the retry `n` is `initial_delay * multiplier^(n - 1)`, capped to
+/// `max_delay`, and randomly shifted by up to `jitter` percent of its value so several
+/// devices failing at the same time don't retry together.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    max_retries: u8,
+    initial_delay: Duration,
+    multiplier: f64,
+    max_delay: Duration,
+    jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            initial_delay: Duration::from_millis(500),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(10),
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Policy that never retries.
+    pub fn none() -> Self {
+        Self::default().with_max_retries(0)
+    }
+
+    /// Policy that retries without waiting between attempts.
+    pub fn immediate(max_retries: u8) -> Self {
+        Self {
+            max_retries,
+            initial_delay: Duration::ZERO,
+            multiplier: 1.0,
+            max_delay: Duration::ZERO,
+            jitter: 0.0,
+        }
+    }
+
+    pub fn with_max_retries(mut self, value: u8) -> Self {
+        self.max_retries = value;
+        self
+    }
+
+    pub fn with_initial_delay(mut self, value: Duration) -> Self {
+        self.initial_delay = value;
+        self
+    }
+
+    pub fn with_multiplier(mut self, value: f64) -> Self {
+        self.multiplier = value.max(1.0);
+        self
+    }
+
+    pub fn with_max_delay(mut self, value: Duration) -> Self {
+        self.max_delay = value;
+        self
+    }
+
+    /// Sets the jitter, as a ratio between 0 and 1 of the delay.
+    pub fn with_jitter(mut self, value: f64) -> Self {
+        self.jitter = value.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn max_retries(&self) -> u8 {
+        self.max_retries
+    }
+
+    /// Delay to wait before the given retry, starting at 1.
+    pub fn delay(&self, retry: u8) -> Duration {
+        let exponent = i32::from(retry.saturating_sub(1));
+        let delay = self.initial_delay.as_secs_f64() * self.multiplier.powi(exponent);
+        let delay = delay.min(self.max_delay.as_secs_f64());
+        let jitter = delay * self.jitter * (fastrand::f64() * 2.0 - 1.0);
+        // the float can round beyond the largest duration, close to it
+        Duration::try_from_secs_f64((delay + jitter).max(0.0)).unwrap_or(self.max_delay)
+    }
+
+    /// Delay before the next attempt, or `None` once the given number of failed attempts
+    /// exhausted the retries.
+    pub(crate) fn next_delay(&self, attempts: u16) -> Option<Duration> {
+        let retry = u8::try_from(attempts).ok()?;
+        (retry <= self.max_retries).then(|| self.delay(retry))
+    }
+
+    /// Runs the operation until it succeeds, the retries are exhausted or the error can't be
+    /// fixed by retrying, in which case the number of attempts and the last error are
+    /// returned.
+    pub(crate) async fn run<T, F, Fut>(&self, mut operation: F) -> Result<T, (u16, Error)>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        // wider than the retries, the attempts of a policy with 255 retries being one more
+        let mut attempts: u16 = 0;
+        loop {
+            match operation().await {
+                Ok(value) => return Ok(value),
+                Err(err) => {
+                    attempts += 1;
+                    if !err.is_retryable() {
+                        return Err((attempts, err));
+                    }
+                    let Some(delay) = self.next_delay(attempts) else {
+                        return Err((attempts, err));
+                    };
+                    tracing::warn!(message = "operation failed", tries = attempts, delay = ?delay, cause = %err);
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU16, Ordering};
+    use std::time::Duration;
+
+    use super::RetryPolicy;
+    use crate::Error;
+
+    #[tokio::test]
+    async fn should_stop_on_errors_not_retryable() {
+        let attempts = AtomicU16::new(0);
+        let result: Result<(), _> = RetryPolicy::immediate(3)
+            .run(|| async {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                Err(Error::DeviceNotSupported)
+            })
+            .await;
+        assert!(matches!(result, Err((1, Error::DeviceNotSupported))));
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn should_exhaust_the_largest_number_of_retries() {
+        let result: Result<(), _> = RetryPolicy::immediate(u8::MAX)
+            .run(|| async {
+                Err(Error::Timeout {
+                    timeout: Duration::ZERO,
+                })
+            })
+            .await;
+        assert!(matches!(result, Err((256, Error::Timeout { .. }))));
+    }
+
+    #[test]
+    fn should_wait_the_largest_delay() {
+        let policy = RetryPolicy::default()
+            .with_initial_delay(Duration::MAX)
+            .with_max_delay(Duration::MAX);
+        assert_eq!(policy.clone().with_jitter(0.0).delay(3), Duration::MAX);
+        // shifted beyond the largest duration half of the time
+        let policy = policy.with_jitter(1.0);
+        for retry in 1..=32 {
+            policy.delay(retry);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+use bluer::{Adapter, AdapterEvent, Address, DiscoveryFilter, DiscoveryTransport};
+use futures::stream::{self, Stream, StreamExt};
+
+use crate::{detect_model, Error, Miflora};
+
+type DiscoveryStream = Pin<Box<dyn Stream<Item = AdapterEvent> + Send>>;
+
+struct ScanState {
+    adapter: Adapter,
+    events: Option<DiscoveryStream>,
+    /// Last time each miflora was yielded
+    seen: HashMap<Address, Instant>,
+    cooldown: Option<Duration>,
+}
+
+impl ScanState {
+    /// Whether the device was yielded too recently to be yielded again.
+    fn is_cooling_down(&self, address: &Address) -> bool {
+        self.seen.get(address).is_some_and(|last| {
+            self.cooldown
+                .is_none_or(|cooldown| last.elapsed() < cooldown)
+        })
+    }
+}
+
+/// Discovers the devices around and yields each miflora once, ready to be used.
+///
+/// The discovery stops when the stream is dropped. The devices not advertising their
+/// service data when discovered are recognized by their name, their services or the
+/// Xiaomi prefix of their address, see [`detect_model`](crate::detect_model).
+pub fn scan(adapter: &Adapter) -> impl Stream<Item = Result<Miflora, Error>> {
+    scan_devices(adapter, None)
+}
+
+/// Discovers the devices around like [`scan`], yielding a miflora again when it's
+/// announced again once the cooldown elapsed since it was last yielded.
+///
+/// The devices announced repeatedly during a long discovery are this way handled at most
+/// once per cooldown.
+pub fn scan_with_cooldown(
+    adapter: &Adapter,
+    cooldown: Duration,
+) -> impl Stream<Item = Result<Miflora, Error>> {
+    scan_devices(adapter, Some(cooldown))
+}
+
+fn scan_devices(
+    adapter: &Adapter,
+    cooldown: Option<Duration>,
+) -> impl Stream<Item = Result<Miflora, Error>> {
+    let state = ScanState {
+        adapter: adapter.clone(),
+        events: None,
+        seen: HashMap::new(),
+        cooldown,
+    };
+    stream::unfold(Some(state), |state| async move {
+        let mut state = state?;
+        if state.events.is_none() {
+            match start_discovery(&state.adapter).await {
+                Ok(events) => state.events = Some(events),
+                Err(err) => return Some((Err(err), None)),
+            }
+        }
+        loop {
+            let event = state.events.as_mut()?.next().await?;
+            let AdapterEvent::DeviceAdded(address) = event else {
+                continue;
+            };
+            if state.is_cooling_down(&address) {
+                tracing::trace!(message = "ignoring device announced again", address = %address);
+                continue;
+            }
+            let device = match state.adapter.device(address) {
+                Ok(device) => device,
+                Err(cause) => {
+                    return Some((Err(Error::DeviceNotFound { address, cause }), Some(state)))
+                }
+            };
+            match detect_model(&device).await {
+                Ok(Some(model)) => {
+                    tracing::debug!(message = "miflora discovered", address = %address, model = ?model);
+                    state.seen.insert(address, Instant::now());
+                    let miflora = Miflora::builder(device).with_model(model).build();
+                    return Some((Ok(miflora), Some(state)));
+                }
+                Ok(None) | Err(Error::NoServiceData) => continue,
+                Err(err) => return Some((Err(err), Some(state))),
+            }
+        }
+    })
+}
+
+async fn start_discovery(adapter: &Adapter) -> Result<DiscoveryStream, Error> {
+    adapter
+        .set_discovery_filter(DiscoveryFilter {
+            transport: DiscoveryTransport::Le,
+            ..Default::default()
+        })
+        .await
+        .map_err(|err| Error::CommandFailed { cause: err })?;
+    let events = adapter
+        .discover_devices()
+        .await
+        .map_err(|err| Error::CommandFailed { cause: err })?;
+    Ok(Box::pin(events))
+}
@@ -0,0 +1,33 @@
+/// Estimate of the quality of the connection to a device, from its signal strength.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SignalQuality {
+    /// No signal received recently, the device is out of range or not advertising
+    OutOfRange,
+    /// Below -90 dBm, the connection will most likely fail
+    Poor,
+    /// Between -90 and -80 dBm, the connection may need a few retries
+    Fair,
+    /// Between -80 and -65 dBm
+    Good,
+    /// Above -65 dBm
+    Excellent,
+}
+
+impl SignalQuality {
+    /// Estimates the quality from the signal strength in dBm, if any.
+    pub fn from_rssi(rssi: Option<i16>) -> Self {
+        match rssi {
+            None => Self::OutOfRange,
+            Some(value) if value < -90 => Self::Poor,
+            Some(value) if value < -80 => Self::Fair,
+            Some(value) if value < -65 => Self::Good,
+            Some(_) => Self::Excellent,
+        }
+    }
+
+    /// Whether connecting to the device is worth trying.
+    pub fn is_reachable(&self) -> bool {
+        *self > Self::Poor
+    }
+}
@@ -0,0 +1,141 @@
+//! Metrics of the communication with the devices, recorded with the global meter provider
+//! of OpenTelemetry when the `opentelemetry` feature is enabled, and ignored otherwise.
+#![cfg_attr(not(feature = "opentelemetry"), allow(unused_variables))]
+
+use std::time::Duration;
+
+use bluer::Address;
+
+use crate::Error;
+
+#[cfg(feature = "opentelemetry")]
+mod instruments {
+    use std::sync::LazyLock;
+
+    use opentelemetry::metrics::{Counter, Histogram};
+
+    pub(super) struct Instruments {
+        pub connection_attempts: Counter<u64>,
+        pub errors: Counter<u64>,
+        pub gatt_duration: Histogram<f64>,
+        pub history_entries: Counter<u64>,
+    }
+
+    /// Created on first use, once the application had a chance to set the meter provider.
+    pub(super) static INSTRUMENTS: LazyLock<Instruments> = LazyLock::new(|| {
+        let meter = opentelemetry::global::meter("bluer-miflora");
+        Instruments {
+            connection_attempts: meter
+                .u64_counter("miflora.connection.attempts")
+                .with_description("Attempts to connect to the devices, by outcome.")
+                .build(),
+            errors: meter
+                .u64_counter("miflora.errors")
+                .with_description(
+                    "Failed operations with the devices, by operation and kind of error.",
+                )
+                .build(),
+            gatt_duration: meter
+                .f64_histogram("miflora.gatt.duration")
+                .with_unit("s")
+                .with_description("Duration of each read or write of a characteristic.")
+                .build(),
+            history_entries: meter
+                .u64_counter("miflora.history.entries")
+                .with_description("Entries of the history fetched from the devices.")
+                .build(),
+        }
+    });
+}
+
+#[cfg(feature = "opentelemetry")]
+fn kind_name(kind: crate::ErrorKind) -> &'static str {
+    use crate::ErrorKind;
+
+    match kind {
+        ErrorKind::Transient => "transient",
+        ErrorKind::NotFound => "not_found",
+        ErrorKind::Protocol => "protocol",
+        ErrorKind::Timeout => "timeout",
+    }
+}
+
+#[cfg(feature = "opentelemetry")]
+fn record_error(address: Address, operation: &'static str, error: &Error) {
+    use opentelemetry::KeyValue;
+
+    instruments::INSTRUMENTS.errors.add(
+        1,
+        &[
+            KeyValue::new("device.address", address.to_string()),
+            KeyValue::new("operation", operation),
+            KeyValue::new("error.kind", kind_name(error.kind())),
+        ],
+    );
+}
+
+/// Records an attempt to connect to the device.
+pub(crate) fn connection_attempt(address: Address, error: Option<&Error>) {
+    #[cfg(feature = "opentelemetry")]
+    {
+        use opentelemetry::KeyValue;
+
+        let outcome = if error.is_some() {
+            "failure"
+        } else {
+            "success"
+        };
+        instruments::INSTRUMENTS.connection_attempts.add(
+            1,
+            &[
+                KeyValue::new("device.address", address.to_string()),
+                KeyValue::new("outcome", outcome),
+            ],
+        );
+        if let Some(error) = error {
+            record_error(address, "connect", error);
+        }
+    }
+}
+
+/// Records a single attempt to read or write a characteristic.
+pub(crate) fn gatt_operation(
+    address: Address,
+    operation: &'static str,
+    elapsed: Duration,
+    error: Option<&Error>,
+) {
+    #[cfg(feature = "opentelemetry")]
+    {
+        use opentelemetry::KeyValue;
+
+        let outcome = if error.is_some() {
+            "failure"
+        } else {
+            "success"
+        };
+        instruments::INSTRUMENTS.gatt_duration.record(
+            elapsed.as_secs_f64(),
+            &[
+                KeyValue::new("device.address", address.to_string()),
+                KeyValue::new("operation", operation),
+                KeyValue::new("outcome", outcome),
+            ],
+        );
+        if let Some(error) = error {
+            record_error(address, operation, error);
+        }
+    }
+}
+
+/// Records an entry fetched from the history of the device.
+pub(crate) fn history_entry(address: Address) {
+    #[cfg(feature = "opentelemetry")]
+    instruments::INSTRUMENTS.history_entries.add(
+        1,
+        &[opentelemetry::KeyValue::new(
+            "device.address",
+            address.to_string(),
+        )],
+    );
+}
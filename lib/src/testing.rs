@@ -0,0 +1,651 @@
+//! In-memory devices to exercise the protocol without a sensor.
+//!
+//! [`FakeMiflora`] emulates the mode, data, firmware and history characteristics of a
+//! Flower Care with the classic history layout, so the parsers and the history state
+//! machine can run in tests.
+//!
+//! [`VirtualMiflora`] publishes the same device through a local adapter, to run the end to
+//! end tests against BlueZ.
+
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use bluer::{Address, Uuid};
+use futures::stream::{self, BoxStream, StreamExt};
+
+#[cfg(feature = "testing")]
+mod peripheral;
+
+use miflora_protocol::{
+    CHARACTERISTIC_DATA_UUID, CHARACTERISTIC_DEVICE_NAME_UUID, CHARACTERISTIC_FIRMWARE_UUID,
+    CHARACTERISTIC_HISTORY_CTRL_UUID, CHARACTERISTIC_HISTORY_READ_UUID,
+    CHARACTERISTIC_HISTORY_TIME_UUID, CHARACTERISTIC_MODE_UUID, CMD_REALTIME_ENABLE,
+    HISTORY_PAYLOAD_LENGTH, REALTIME_PAYLOAD_LENGTH, SERVICE_DATA_UUID,
+    SERVICE_GENERIC_ACCESS_UUID, SERVICE_HISTORY_UUID,
+};
+#[cfg(feature = "testing")]
+pub use peripheral::{VirtualMiflora, VirtualMifloraHandle};
+
+use crate::{Clock, Error, GattClient, Miflora, MifloraBuilder};
+
+/// Values returned by the data characteristic when the realtime mode is disabled.
+const REALTIME_DISABLED_PAYLOAD: [u8; REALTIME_PAYLOAD_LENGTH] = [
+    0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff, 0x99, 0x88, 0x77, 0x66, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// Clock always returning the same time.
+#[derive(Clone, Copy, Debug)]
+pub struct FixedClock(pub SystemTime);
+
+impl Clock for FixedClock {
+    fn now(&self) -> SystemTime {
+        self.0
+    }
+}
+
+/// What the history read characteristic returns, depending on the last command.
+#[derive(Clone, Copy, Debug)]
+enum HistoryMode {
+    Idle,
+    Header,
+    Entry(u16),
+}
+
+#[derive(Debug)]
+struct FakeState {
+    connected: bool,
+    battery: u8,
+    firmware: String,
+    name: String,
+    mode: Vec<u8>,
+    echo_mode: bool,
+    realtime: [u8; REALTIME_PAYLOAD_LENGTH],
+    uptime: u32,
+    history: Vec<[u8; HISTORY_PAYLOAD_LENGTH]>,
+    history_mode: HistoryMode,
+}
+
+/// In-memory Flower Care, the clones sharing the same state.
+#[derive(Clone, Debug)]
+pub struct FakeMiflora {
+    address: Address,
+    state: Arc<Mutex<FakeState>>,
+}
+
+impl Default for FakeMiflora {
+    fn default() -> Self {
+        Self::new(Address::new([0xc4, 0x7c, 0x8d, 0x00, 0x00, 0x01]))
+    }
+}
+
+impl FakeMiflora {
+    pub fn new(address: Address) -> Self {
+        Self {
+            address,
+            state: Arc::new(Mutex::new(FakeState {
+                connected: false,
+                battery: 100,
+                firmware: "3.2.2".into(),
+                name: "Flower care".into(),
+                mode: Vec::new(),
+                echo_mode: true,
+                realtime: [0; REALTIME_PAYLOAD_LENGTH],
+                uptime: 0,
+                history: Vec::new(),
+                history_mode: HistoryMode::Idle,
+            })),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, FakeState> {
+        self.state.lock().expect("fake state poisoned")
+    }
+
+    pub fn with_battery(self, value: u8) -> Self {
+        self.state().battery = value;
+        self
+    }
+
+    pub fn with_firmware(self, value: &str) -> Self {
+        self.state().firmware = value.to_string();
+        self
+    }
+
+    /// Sets the realtime values, with the temperature in 0.1 °C.
+    pub fn with_realtime(
+        self,
+        temperature: i16,
+        brightness: u32,
+        moisture: u8,
+        conductivity: u16,
+    ) -> Self {
+        {
+            let mut state = self.state();
+            state.realtime = [0; REALTIME_PAYLOAD_LENGTH];
+            state.realtime[0..2].copy_from_slice(&temperature.to_le_bytes());
+            state.realtime[3..7].copy_from_slice(&brightness.to_le_bytes());
+            state.realtime[7] = moisture;
+            state.realtime[8..10].copy_from_slice(&conductivity.to_le_bytes());
+        }
+        self
+    }
+
+    /// Sets the seconds elapsed since the device booted.
+    pub fn with_uptime(self, value: u32) -> Self {
+        self.state().uptime = value;
+        self
+    }
+
+    /// Adds an entry to the history, recorded at the given uptime.
+    pub fn with_history_entry(
+        self,
+        uptime: u32,
+        temperature: i16,
+        brightness: u32,
+        moisture: u8,
+        conductivity: u16,
+    ) -> Self {
+        let mut entry = [0; HISTORY_PAYLOAD_LENGTH];
+        entry[0..4].copy_from_slice(&uptime.to_le_bytes());
+        entry[4..6].copy_from_slice(&temperature.to_le_bytes());
+        entry[7..10].copy_from_slice(&brightness.to_le_bytes()[0..3]);
+        entry[11] = moisture;
+        entry[12..14].copy_from_slice(&conductivity.to_le_bytes());
+        self.state().history.push(entry);
+        self
+    }
+
+    /// Applies the mode written but reads back zeros, like some clones.
+    pub fn without_mode_echo(self) -> Self {
+        self.state().echo_mode = false;
+        self
+    }
+
+    /// Adds an entry to the history with the raw payload, like the padding frames of some
+    /// firmwares.
+    pub fn with_raw_history_entry(self, payload: [u8; HISTORY_PAYLOAD_LENGTH]) -> Self {
+        self.state().history.push(payload);
+        self
+    }
+
+    /// Creates a miflora communicating with this device.
+    pub fn miflora(&self) -> Miflora<Self> {
+        MifloraBuilder::from_client(self.clone()).build()
+    }
+
+    pub fn is_connected_now(&self) -> bool {
+        self.state().connected
+    }
+
+    /// Drops the connection, like a device going out of range.
+    pub fn drop_connection(&self) {
+        self.state().connected = false;
+    }
+
+    /// Last value written to the mode characteristic.
+    pub fn mode(&self) -> Vec<u8> {
+        self.state().mode.clone()
+    }
+
+    pub fn history_len(&self) -> usize {
+        self.state().history.len()
+    }
+
+    fn check_connected(&self) -> Result<(), bluer::Error> {
+        if self.state().connected {
+            Ok(())
+        } else {
+            Err(bluer::Error {
+                kind: bluer::ErrorKind::Failed,
+                message: "Not connected".into(),
+            })
+        }
+    }
+
+    fn handle_read(&self, service_id: Uuid, char_id: Uuid) -> Result<Vec<u8>, Error> {
+        self.check_connected()
+            .map_err(|cause| Error::UnableToRead {
+                characteristic_id: char_id,
+                service_id,
+                cause,
+            })?;
+        let state = self.state();
+        match (service_id, char_id) {
+            (SERVICE_DATA_UUID, CHARACTERISTIC_MODE_UUID) if state.echo_mode => {
+                Ok(state.mode.clone())
+            }
+            (SERVICE_DATA_UUID, CHARACTERISTIC_MODE_UUID) => Ok(vec![0; 2]),
+            (SERVICE_DATA_UUID, CHARACTERISTIC_DATA_UUID) => {
+                if state.mode == CMD_REALTIME_ENABLE {
+                    Ok(state.realtime.to_vec())
+                } else {
+                    Ok(REALTIME_DISABLED_PAYLOAD.to_vec())
+                }
+            }
+            (SERVICE_GENERIC_ACCESS_UUID, CHARACTERISTIC_DEVICE_NAME_UUID) => {
+                Ok(state.name.as_bytes().to_vec())
+            }
+            (SERVICE_DATA_UUID, CHARACTERISTIC_FIRMWARE_UUID) => {
+                let mut data = vec![state.battery, 0];
+                data.extend_from_slice(state.firmware.as_bytes());
+                Ok(data)
+            }
+            (SERVICE_HISTORY_UUID, CHARACTERISTIC_HISTORY_READ_UUID) => {
+                let mut data = vec![0; HISTORY_PAYLOAD_LENGTH];
+                match state.history_mode {
+                    HistoryMode::Idle => {}
+                    HistoryMode::Header => {
+                        let count = state.history.len() as u16;
+                        data[0..2].copy_from_slice(&count.to_le_bytes());
+                    }
+                    HistoryMode::Entry(index) => {
+                        if let Some(entry) = state.history.get(index as usize) {
+                            data.copy_from_slice(entry);
+                        }
+                    }
+                }
+                Ok(data)
+            }
+            (SERVICE_HISTORY_UUID, CHARACTERISTIC_HISTORY_TIME_UUID) => {
+                Ok(state.uptime.to_le_bytes().to_vec())
+            }
+            _ => Err(not_found(service_id, char_id)),
+        }
+    }
+
+    fn handle_write(&self, service_id: Uuid, char_id: Uuid, payload: &[u8]) -> Result<(), Error> {
+        self.check_connected()
+            .map_err(|cause| Error::UnableToWrite {
+                characteristic_id: char_id,
+                service_id,
+                cause,
+            })?;
+        let mut state = self.state();
+        match (service_id, char_id) {
+            (SERVICE_DATA_UUID, CHARACTERISTIC_MODE_UUID) => {
+                state.mode = payload.to_vec();
+                Ok(())
+            }
+            (SERVICE_GENERIC_ACCESS_UUID, CHARACTERISTIC_DEVICE_NAME_UUID) => {
+                state.name = String::from_utf8_lossy(payload).into_owned();
+                Ok(())
+            }
+            (SERVICE_HISTORY_UUID, CHARACTERISTIC_HISTORY_TIME_UUID) => {
+                let seconds: [u8; 4] =
+                    payload
+                        .try_into()
+                        .map_err(|_| Error::InvalidPayloadLength {
+                            expected: 4,
+                            actual: payload.len(),
+                            characteristic_id: char_id,
+                        })?;
+                state.uptime = u32::from_le_bytes(seconds);
+                Ok(())
+            }
+            (SERVICE_HISTORY_UUID, CHARACTERISTIC_HISTORY_CTRL_UUID) => {
+                match payload {
+                    [0xa0, ..] => state.history_mode = HistoryMode::Header,
+                    [0xa1, low, high] => {
+                        state.history_mode = HistoryMode::Entry(u16::from_le_bytes([*low, *high]))
+                    }
+                    [0xa2, ..] => {
+                        state.history.clear();
+                        state.history_mode = HistoryMode::Idle;
+                    }
+                    _ => state.history_mode = HistoryMode::Idle,
+                }
+                Ok(())
+            }
+            _ => Err(not_found(service_id, char_id)),
+        }
+    }
+}
+
+/// Error of BlueZ for a characteristic missing from an emulated service, or for a service
+/// not emulated at all, like the device information.
+fn not_found(service_id: Uuid, char_id: Uuid) -> Error {
+    if ![
+        SERVICE_DATA_UUID,
+        SERVICE_HISTORY_UUID,
+        SERVICE_GENERIC_ACCESS_UUID,
+    ]
+    .contains(&service_id)
+    {
+        return Error::ServiceNotFound {
+            service_id,
+            cause: bluer::Error {
+                kind: bluer::ErrorKind::NotFound,
+                message: "service not found".into(),
+            },
+        };
+    }
+    Error::CharacteristicNotFound {
+        characteristic_id: char_id,
+        service_id,
+        cause: bluer::Error {
+            kind: bluer::ErrorKind::NotFound,
+            message: "characteristic not found".into(),
+        },
+    }
+}
+
+impl GattClient for FakeMiflora {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn is_connected(&self) -> Result<bool, Error> {
+        Ok(self.state().connected)
+    }
+
+    async fn connect(&self) -> Result<(), Error> {
+        self.state().connected = true;
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<(), Error> {
+        let mut state = self.state();
+        state.connected = false;
+        state.history_mode = HistoryMode::Idle;
+        Ok(())
+    }
+
+    async fn read(&self, service_id: Uuid, char_id: Uuid) -> Result<Vec<u8>, Error> {
+        self.handle_read(service_id, char_id)
+    }
+
+    async fn write(&self, service_id: Uuid, char_id: Uuid, payload: &[u8]) -> Result<(), Error> {
+        self.handle_write(service_id, char_id, payload)
+    }
+
+    /// Notifies the current realtime values once.
+    async fn subscribe(
+        &self,
+        service_id: Uuid,
+        char_id: Uuid,
+    ) -> Result<BoxStream<'static, Vec<u8>>, Error> {
+        let data = self.handle_read(service_id, char_id)?;
+        Ok(stream::iter([data]).boxed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use futures::StreamExt;
+
+    use super::{FakeMiflora, FixedClock};
+    use crate::{
+        DeviceInfo, Error, MifloraBuilder, RetryPolicy, TimestampConfidence, WriteVerification,
+    };
+
+    #[tokio::test]
+    async fn should_read_all_values() {
+        let fake = FakeMiflora::default()
+            .with_battery(87)
+            .with_firmware("3.2.2")
+            .with_realtime(215, 1200, 42, 350);
+        let miflora = fake.miflora();
+        let snapshot = miflora
+            .with_connection(|miflora| async move { miflora.read_all(false).await })
+            .await
+            .unwrap();
+        assert_eq!(snapshot.system().battery(), 87);
+        assert_eq!(snapshot.system().firmware(), "3.2.2");
+        assert_eq!(snapshot.realtime().temperature(), 215);
+        assert_eq!(snapshot.realtime().brightness(), Some(1200));
+        assert_eq!(snapshot.realtime().moisture(), 42);
+        assert_eq!(snapshot.realtime().conductivity(), 350);
+        assert!(!fake.is_connected_now());
+    }
+
+    #[tokio::test]
+    async fn should_read_history_with_timestamps() {
+        let boot = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let fake = FakeMiflora::default()
+            .with_uptime(7200)
+            .with_history_entry(3600, 180, 500, 30, 200)
+            .with_history_entry(7200, -15, 0, 31, 210);
+        let miflora = MifloraBuilder::from_client(fake.clone())
+            .with_clock(FixedClock(boot + Duration::from_secs(7200)))
+            .build();
+        miflora.connect().await.unwrap();
+        let entries = miflora.read_historical_values().await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].timestamp(), 1_700_003_600);
+        assert_eq!(entries[0].temperature(), 180);
+        assert_eq!(entries[1].timestamp(), 1_700_007_200);
+        assert_eq!(entries[1].temperature(), -15);
+        assert_eq!(entries[1].moisture(), 31);
+    }
+
+    #[tokio::test]
+    async fn should_set_device_time() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let fake = FakeMiflora::default().with_uptime(7200).with_history_entry(
+            1_700_003_600,
+            180,
+            500,
+            30,
+            200,
+        );
+        let miflora = MifloraBuilder::from_client(fake.clone())
+            .with_clock(FixedClock(now))
+            .build();
+        miflora.connect().await.unwrap();
+        miflora.epoch_time().await.unwrap();
+        miflora.set_device_time(now).await.unwrap();
+        assert_eq!(miflora.read_epoch_time().await.unwrap(), 0);
+        let entries = miflora.read_historical_values().await.unwrap();
+        assert_eq!(entries[0].timestamp(), 1_700_003_600);
+    }
+
+    #[tokio::test]
+    async fn should_verify_writes() {
+        let fake = FakeMiflora::default().without_mode_echo();
+        let build = |verification| {
+            MifloraBuilder::from_client(fake.clone())
+                .with_write_verification(verification)
+                .build()
+        };
+        fake.miflora().connect().await.unwrap();
+
+        let err = build(WriteVerification::Always)
+            .blink_led()
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::WriteMismatch { ref expected, ref actual, .. }
+                if expected == &miflora_protocol::CMD_BLINK_LED && actual == &[0, 0]
+        ));
+        build(WriteVerification::Lenient).blink_led().await.unwrap();
+        build(WriteVerification::Never).blink_led().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_rename_device() {
+        let fake = FakeMiflora::default();
+        let miflora = fake.miflora();
+        miflora.connect().await.unwrap();
+        assert_eq!(
+            miflora.read_device_name().await.unwrap().as_deref(),
+            Some("Flower care")
+        );
+        miflora.set_device_name("Basil").await.unwrap();
+        assert_eq!(
+            miflora.read_device_name().await.unwrap().as_deref(),
+            Some("Basil")
+        );
+        assert!(matches!(
+            miflora.set_device_name("").await,
+            Err(Error::InvalidDeviceName { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_leave_device_info_empty_without_the_service() {
+        let miflora = FakeMiflora::default().miflora();
+        miflora.connect().await.unwrap();
+        let info = miflora.read_device_info().await.unwrap();
+        assert_eq!(info, DeviceInfo::default());
+    }
+
+    #[tokio::test]
+    async fn should_skip_padding_history_entries() {
+        let mut magic = [0x42; 16];
+        magic[0..2].copy_from_slice(&[0xAA, 0xBB]);
+        let fake = FakeMiflora::default()
+            .with_history_entry(10, 200, 100, 20, 100)
+            .with_raw_history_entry([0xFF; 16])
+            .with_raw_history_entry(magic)
+            .with_raw_history_entry([0; 16])
+            .with_history_entry(20, 210, 100, 20, 100);
+        let miflora = fake.miflora();
+        miflora.connect().await.unwrap();
+        let entries = miflora.read_historical_values().await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].temperature(), 210);
+    }
+
+    #[tokio::test]
+    async fn should_read_history_since_timestamp() {
+        let boot = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let fake = FakeMiflora::default()
+            .with_uptime(18000)
+            .with_history_entry(3600, 180, 500, 30, 200)
+            .with_raw_history_entry([0xFF; 16])
+            .with_history_entry(7200, 181, 500, 30, 200)
+            .with_history_entry(10800, 182, 500, 30, 200)
+            .with_raw_history_entry([0; 16])
+            .with_history_entry(14400, 183, 500, 30, 200)
+            .with_raw_history_entry([0xFF; 16]);
+        let miflora = MifloraBuilder::from_client(fake)
+            .with_clock(FixedClock(boot + Duration::from_secs(18000)))
+            .build();
+        miflora.connect().await.unwrap();
+        for (since, expected) in [
+            (0, &[180, 181, 182, 183][..]),
+            (1_700_003_600, &[181, 182, 183]),
+            (1_700_009_000, &[182, 183]),
+            (1_700_014_400, &[]),
+        ] {
+            let entries = miflora.read_historical_values_since(since).await.unwrap();
+            let temperatures: Vec<_> = entries.iter().map(|entry| entry.temperature()).collect();
+            assert_eq!(temperatures, expected, "since {since}");
+        }
+    }
+
+    #[tokio::test]
+    async fn should_read_history_since_timestamp_across_reboot() {
+        let boot = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        // recorded before and after a reboot, the older entries looking more recent
+        let fake = FakeMiflora::default()
+            .with_uptime(10800)
+            .with_history_entry(36000, 170, 500, 30, 200)
+            .with_history_entry(39600, 171, 500, 30, 200)
+            .with_history_entry(43200, 172, 500, 30, 200)
+            .with_history_entry(3600, 180, 500, 30, 200)
+            .with_history_entry(7200, 181, 500, 30, 200)
+            .with_history_entry(10800, 182, 500, 30, 200);
+        let miflora = MifloraBuilder::from_client(fake)
+            .with_clock(FixedClock(boot + Duration::from_secs(10800)))
+            .build();
+        miflora.connect().await.unwrap();
+        let entries = miflora
+            .read_historical_values_since(1_700_003_600)
+            .await
+            .unwrap();
+        assert!(entries
+            .iter()
+            .all(|entry| entry.timestamp() > 1_700_003_600));
+        let reliable: Vec<_> = entries
+            .iter()
+            .filter(|entry| entry.timestamp_confidence() == TimestampConfidence::Reliable)
+            .map(|entry| entry.temperature())
+            .collect();
+        assert_eq!(reliable, [181, 182]);
+        assert_eq!(entries.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn should_clear_history_on_commit_only() {
+        let fake = FakeMiflora::default()
+            .with_history_entry(10, 200, 100, 20, 100)
+            .with_history_entry(20, 200, 100, 20, 100);
+        let miflora = fake.miflora();
+        miflora.connect().await.unwrap();
+        let session = miflora.read_history_session().await.unwrap();
+        assert_eq!(session.entries().len(), 2);
+        session.abort().await.unwrap();
+        assert_eq!(fake.history_len(), 2);
+        let session = miflora.read_history_session().await.unwrap();
+        session.commit().await.unwrap();
+        assert_eq!(fake.history_len(), 0);
+    }
+
+    #[tokio::test]
+    async fn should_track_the_history_downloads_in_progress() {
+        let fake = FakeMiflora::default()
+            .with_history_entry(10, 200, 100, 20, 100)
+            .with_history_entry(20, 200, 100, 20, 100);
+        let miflora = fake.miflora();
+        miflora.connect().await.unwrap();
+        assert!(!miflora.is_history_open());
+        {
+            let stream = miflora.historical_values_stream();
+            futures::pin_mut!(stream);
+            stream.next().await.unwrap().unwrap();
+        }
+        assert!(miflora.is_history_open());
+        miflora.abort_history_read().await.unwrap();
+        assert!(!miflora.is_history_open());
+        miflora.read_historical_values().await.unwrap();
+        assert!(!miflora.is_history_open());
+        let session = miflora.read_history_session().await.unwrap();
+        assert!(miflora.is_history_open());
+        session.commit().await.unwrap();
+        assert!(!miflora.is_history_open());
+    }
+
+    #[tokio::test]
+    async fn should_reconnect_when_connection_dropped() {
+        let fake = FakeMiflora::default().with_battery(12);
+        let miflora = fake.miflora();
+        miflora.connect().await.unwrap();
+        fake.drop_connection();
+        assert_eq!(miflora.read_battery().await.unwrap(), 12);
+        assert!(fake.is_connected_now());
+    }
+
+    #[tokio::test]
+    async fn should_read_again_implausible_values() {
+        let fake = FakeMiflora::default();
+        let miflora = MifloraBuilder::from_client(fake.clone())
+            .with_retry_policy(RetryPolicy::immediate(2))
+            .with_reject_implausible(true)
+            .build();
+        miflora.connect().await.unwrap();
+        let err = miflora.read_realtime_values().await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::TooManyRetries { retries: 3, ref cause } if matches!(**cause, Error::ImplausibleReading { .. })
+        ));
+
+        fake.with_realtime(200, 10, 20, 30);
+        let entry = miflora.read_realtime_values().await.unwrap();
+        assert!(entry.is_plausible());
+        assert_eq!(entry.moisture(), 20);
+    }
+
+    #[tokio::test]
+    async fn should_disable_realtime_mode_after_single_read() {
+        let fake = FakeMiflora::default().with_realtime(200, 10, 20, 30);
+        let miflora = fake.miflora();
+        miflora.connect().await.unwrap();
+        let entry = miflora.read_realtime_values_once().await.unwrap();
+        assert_eq!(entry.conductivity(), 30);
+        assert_eq!(fake.mode(), miflora_protocol::CMD_REALTIME_DISABLE);
+    }
+}
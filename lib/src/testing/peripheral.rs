@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+
+use bluer::adv::{Advertisement, AdvertisementHandle};
+use bluer::gatt::local::{
+    Application, ApplicationHandle, Characteristic, CharacteristicNotify,
+    CharacteristicNotifyMethod, CharacteristicRead, CharacteristicWrite, CharacteristicWriteMethod,
+    ReqError, Service,
+};
+use bluer::{Adapter, Uuid};
+use futures::FutureExt;
+use miflora_protocol::{
+    CHARACTERISTIC_DATA_UUID, CHARACTERISTIC_FIRMWARE_UUID, CHARACTERISTIC_HISTORY_CTRL_UUID,
+    CHARACTERISTIC_HISTORY_READ_UUID, CHARACTERISTIC_HISTORY_TIME_UUID, CHARACTERISTIC_MODE_UUID,
+    SERVICE_DATA_UUID, SERVICE_HISTORY_UUID,
+};
+
+use super::FakeMiflora;
+use crate::{Error, Model};
+
+/// Service carrying the MiBeacon frames in the advertisements.
+const SERVICE_MIBEACON_UUID: Uuid = Uuid::from_u128(0x0000fe95_0000_1000_8000_00805f9b34fb);
+
+/// Miflora exposed by a local adapter through BlueZ, to run end to end tests against a
+/// real Bluetooth stack.
+///
+/// The characteristics behave like the ones of the wrapped [`FakeMiflora`], which can be
+/// used to set the values and check the state of the device during the test.
+#[derive(Clone, Debug)]
+pub struct VirtualMiflora {
+    fake: FakeMiflora,
+    model: Model,
+}
+
+/// Keeps the virtual miflora published until dropped.
+#[derive(Debug)]
+pub struct VirtualMifloraHandle {
+    _application: ApplicationHandle,
+    _advertisement: AdvertisementHandle,
+}
+
+impl VirtualMiflora {
+    pub fn new(fake: FakeMiflora) -> Self {
+        Self {
+            fake,
+            model: Model::FlowerCare,
+        }
+    }
+
+    /// Model announced in the advertisements.
+    pub fn with_model(mut self, value: Model) -> Self {
+        self.model = value;
+        self
+    }
+
+    pub fn fake(&self) -> &FakeMiflora {
+        &self.fake
+    }
+
+    /// Publishes the services and advertises the device on the adapter.
+    pub async fn serve(&self, adapter: &Adapter) -> Result<VirtualMifloraHandle, Error> {
+        // the connections are handled by BlueZ, the fake only needs to accept the requests
+        self.fake.state().connected = true;
+        let application = Application {
+            services: vec![
+                Service {
+                    uuid: SERVICE_DATA_UUID,
+                    primary: true,
+                    characteristics: vec![
+                        self.characteristic(SERVICE_DATA_UUID, CHARACTERISTIC_MODE_UUID, true),
+                        self.notifying_characteristic(SERVICE_DATA_UUID, CHARACTERISTIC_DATA_UUID),
+                        self.characteristic(SERVICE_DATA_UUID, CHARACTERISTIC_FIRMWARE_UUID, false),
+                    ],
+                    ..Default::default()
+                },
+                Service {
+                    uuid: SERVICE_HISTORY_UUID,
+                    primary: true,
+                    characteristics: vec![
+                        self.characteristic(
+                            SERVICE_HISTORY_UUID,
+                            CHARACTERISTIC_HISTORY_CTRL_UUID,
+                            true,
+                        ),
+                        self.characteristic(
+                            SERVICE_HISTORY_UUID,
+                            CHARACTERISTIC_HISTORY_READ_UUID,
+                            false,
+                        ),
+                        self.characteristic(
+                            SERVICE_HISTORY_UUID,
+                            CHARACTERISTIC_HISTORY_TIME_UUID,
+                            false,
+                        ),
+                    ],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let application = adapter
+            .serve_gatt_application(application)
+            .await
+            .map_err(|err| Error::CommandFailed { cause: err })?;
+
+        let product_id = self.model.product_id().to_le_bytes();
+        let advertisement = Advertisement {
+            service_data: BTreeMap::from([(
+                SERVICE_MIBEACON_UUID,
+                vec![0x20, 0x00, product_id[0], product_id[1], 0x00],
+            )]),
+            local_name: Some(advertised_name(self.model).to_string()),
+            discoverable: Some(true),
+            ..Default::default()
+        };
+        let advertisement = adapter
+            .advertise(advertisement)
+            .await
+            .map_err(|err| Error::CommandFailed { cause: err })?;
+
+        Ok(VirtualMifloraHandle {
+            _application: application,
+            _advertisement: advertisement,
+        })
+    }
+
+    fn read(&self, service_id: Uuid, char_id: Uuid) -> CharacteristicRead {
+        let fake = self.fake.clone();
+        CharacteristicRead {
+            read: true,
+            fun: Box::new(move |_| {
+                let result = fake
+                    .handle_read(service_id, char_id)
+                    .map_err(|_| ReqError::Failed);
+                async move { result }.boxed()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn characteristic(&self, service_id: Uuid, char_id: Uuid, writable: bool) -> Characteristic {
+        let write = writable.then(|| {
+            let fake = self.fake.clone();
+            CharacteristicWrite {
+                write: true,
+                method: CharacteristicWriteMethod::Fun(Box::new(move |payload, _| {
+                    let result = fake
+                        .handle_write(service_id, char_id, &payload)
+                        .map_err(|_| ReqError::Failed);
+                    async move { result }.boxed()
+                })),
+                ..Default::default()
+            }
+        });
+        Characteristic {
+            uuid: char_id,
+            read: Some(self.read(service_id, char_id)),
+            write,
+            ..Default::default()
+        }
+    }
+
+    /// Characteristic notifying its current value once per subscription.
+    fn notifying_characteristic(&self, service_id: Uuid, char_id: Uuid) -> Characteristic {
+        let fake = self.fake.clone();
+        Characteristic {
+            notify: Some(CharacteristicNotify {
+                notify: true,
+                method: CharacteristicNotifyMethod::Fun(Box::new(move |mut notifier| {
+                    let value = fake.handle_read(service_id, char_id);
+                    async move {
+                        if let Ok(value) = value {
+                            if let Err(err) = notifier.notify(value).await {
+                                tracing::debug!(message = "unable to notify", cause = %err);
+                            }
+                        }
+                    }
+                    .boxed()
+                })),
+                ..Default::default()
+            }),
+            ..self.characteristic(service_id, char_id, false)
+        }
+    }
+}
+
+/// Name advertised by the devices, as recognized by [`Model::from_name`].
+fn advertised_name(model: Model) -> &'static str {
+    match model {
+        Model::FlowerCare => "Flower care",
+        Model::Ropot => "ropot",
+        Model::GrowCareGarden => "Grow care garden",
+    }
+}
@@ -0,0 +1,206 @@
+//! Serializable views of the data read from the device.
+//!
+//! The entries keep the raw bytes returned by the device, these views expose the decoded
+//! values instead so they can be pushed as is into JSON APIs. The raw bytes can be included
+//! in hexadecimal with `with_raw`, the entries being restored from them when deserialized.
+
+use crate::recording::{decode_hex, Hex};
+use crate::{EpochTime, HistoricalEntry, Model, RealtimeEntry, System};
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct SystemView {
+    /// Battery level in %
+    pub battery: u8,
+    pub firmware: String,
+}
+
+impl From<System> for SystemView {
+    fn from(value: System) -> Self {
+        Self {
+            battery: value.battery(),
+            firmware: value.firmware().into_owned(),
+        }
+    }
+}
+
+impl From<SystemView> for System {
+    fn from(value: SystemView) -> Self {
+        let mut inner = Vec::with_capacity(2 + value.firmware.len());
+        inner.push(value.battery);
+        inner.push(0);
+        inner.extend_from_slice(value.firmware.as_bytes());
+        Self { inner }
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct RealtimeEntryView {
+    /// Temperature in °C
+    pub temperature: f32,
+    /// Brightness in lux, if the device has a brightness sensor
+    pub brightness: Option<u32>,
+    /// Moisture in %
+    pub moisture: u8,
+    /// Conductivity in µS/cm
+    pub conductivity: u16,
+    /// Payload sent by the device, in hexadecimal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<String>,
+}
+
+impl RealtimeEntryView {
+    /// View including the payload sent by the device.
+    pub fn with_raw(value: &RealtimeEntry) -> Self {
+        Self {
+            raw: Some(Hex(value.as_bytes()).to_string()),
+            ..Self::from(value.clone())
+        }
+    }
+}
+
+impl From<RealtimeEntry> for RealtimeEntryView {
+    fn from(value: RealtimeEntry) -> Self {
+        Self {
+            temperature: value.temperature_celsius(),
+            brightness: value.brightness(),
+            moisture: value.moisture(),
+            conductivity: value.conductivity(),
+            raw: None,
+        }
+    }
+}
+
+impl From<RealtimeEntryView> for RealtimeEntry {
+    fn from(value: RealtimeEntryView) -> Self {
+        let model = model_from_brightness(value.brightness);
+        if let Some(entry) = value
+            .raw
+            .as_deref()
+            .and_then(decode_hex)
+            .and_then(|inner| RealtimeEntry::try_new(inner, model).ok())
+        {
+            return entry;
+        }
+        let mut inner = vec![0; miflora_protocol::REALTIME_PAYLOAD_LENGTH];
+        inner[0..2].copy_from_slice(&encode_temperature(value.temperature));
+        inner[3..7].copy_from_slice(&value.brightness.unwrap_or_default().to_le_bytes());
+        inner[7] = value.moisture;
+        inner[8..10].copy_from_slice(&value.conductivity.to_le_bytes());
+        Self { model, inner }
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct HistoricalEntryView {
+    /// Unix timestamp in seconds
+    pub timestamp: u64,
+    #[serde(default)]
+    pub timestamp_confidence: crate::TimestampConfidence,
+    /// Temperature in °C
+    pub temperature: f32,
+    /// Brightness in lux, if the device has a brightness sensor
+    pub brightness: Option<u32>,
+    /// Moisture in %
+    pub moisture: u8,
+    /// Conductivity in µS/cm
+    pub conductivity: u16,
+    /// Payload sent by the device, in hexadecimal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<String>,
+}
+
+impl HistoricalEntryView {
+    /// View including the payload sent by the device.
+    pub fn with_raw(value: &HistoricalEntry) -> Self {
+        Self {
+            raw: Some(Hex(value.as_bytes()).to_string()),
+            ..Self::from(value.clone())
+        }
+    }
+}
+
+impl From<HistoricalEntry> for HistoricalEntryView {
+    fn from(value: HistoricalEntry) -> Self {
+        Self {
+            timestamp: value.timestamp(),
+            timestamp_confidence: value.timestamp_confidence(),
+            temperature: value.temperature_celsius(),
+            brightness: value.brightness(),
+            moisture: value.moisture(),
+            conductivity: value.conductivity(),
+            raw: None,
+        }
+    }
+}
+
+impl From<HistoricalEntryView> for HistoricalEntry {
+    fn from(value: HistoricalEntryView) -> Self {
+        let model = model_from_brightness(value.brightness);
+        if let Some(inner) = value.raw.as_deref().and_then(decode_hex) {
+            // the epoch time is shifted so the uptime of the payload gives the timestamp
+            if let Ok(mut entry) = HistoricalEntry::try_new(inner, model, EpochTime::exact(0)) {
+                entry.epoch =
+                    EpochTime::exact(value.timestamp.saturating_sub(entry.uptime().into()));
+                entry.confidence = value.timestamp_confidence;
+                return entry;
+            }
+        }
+        // the brightness is stored on 3 bytes in the history
+        let brightness = value
+            .brightness
+            .unwrap_or_default()
+            .min(0x00ff_ffff)
+            .to_le_bytes();
+        let mut inner = vec![0; miflora_protocol::HISTORY_PAYLOAD_LENGTH];
+        inner[4..6].copy_from_slice(&encode_temperature(value.temperature));
+        inner[7..10].copy_from_slice(&brightness[0..3]);
+        inner[11] = value.moisture;
+        inner[12..14].copy_from_slice(&value.conductivity.to_le_bytes());
+        // the uptime is left to 0 so the epoch time is the timestamp
+        Self {
+            model,
+            epoch: EpochTime::exact(value.timestamp),
+            confidence: value.timestamp_confidence,
+            inner,
+        }
+    }
+}
+
+/// The Ropot is the only model without brightness sensor.
+fn model_from_brightness(value: Option<u32>) -> Model {
+    if value.is_some() {
+        Model::FlowerCare
+    } else {
+        Model::Ropot
+    }
+}
+
+fn encode_temperature(value: f32) -> [u8; 2] {
+    ((value * 10.0).round() as i16).to_le_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RealtimeEntryView;
+    use crate::{Model, RealtimeEntry};
+
+    #[test]
+    fn should_restore_entry_from_raw_payload() {
+        let payload = vec![
+            0xd7, 0x00, 0x42, 0xb0, 0x04, 0x00, 0x00, 0x2a, 0x5e, 0x01, 0x02, 0x3c, 0x00, 0xfb,
+            0x34, 0x9b,
+        ];
+        let entry = RealtimeEntry::try_new(payload.clone(), Model::FlowerCare).unwrap();
+        assert_eq!(entry.unknown_2(), 0x42);
+        assert_eq!(entry.unknown_10_15(), &payload[10..]);
+
+        let json = serde_json::to_string(&RealtimeEntryView::with_raw(&entry)).unwrap();
+        assert!(json.contains(r#""raw":"d70042b00400002a5e01023c00fb349b""#));
+        let view: RealtimeEntryView = serde_json::from_str(&json).unwrap();
+        assert_eq!(RealtimeEntry::from(view).as_bytes(), payload.as_slice());
+
+        let json = serde_json::to_string(&entry).unwrap();
+        assert!(!json.contains("raw"));
+        assert!(format!("{entry:#?}").contains("d70042b00400002a5e01023c00fb349b"));
+    }
+}
@@ -0,0 +1,94 @@
+//! Property tests ensuring the payloads sent by the devices, valid or not, never panic
+//! the parsers.
+
+use bluer_miflora::advertisement::MiBeacon;
+use bluer_miflora::{EpochTime, FirmwareVersion, HistoricalEntry, Model, RealtimeEntry, System};
+use proptest::prelude::*;
+
+fn model() -> impl Strategy<Value = Model> {
+    prop_oneof![
+        Just(Model::FlowerCare),
+        Just(Model::Ropot),
+        Just(Model::GrowCareGarden),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn should_parse_any_system_payload(data in prop::collection::vec(any::<u8>(), 0..32)) {
+        let length = data.len();
+        match System::try_from(data) {
+            Ok(system) => {
+                let _ = system.battery();
+                let _ = system.firmware();
+                let _ = system.firmware_version();
+            }
+            Err(_) => prop_assert!(length < 2),
+        }
+    }
+
+    #[test]
+    fn should_parse_any_realtime_payload(
+        data in prop::collection::vec(any::<u8>(), 0..32),
+        model in model(),
+    ) {
+        let length = data.len();
+        match RealtimeEntry::try_new(data, model) {
+            Ok(entry) => {
+                let _ = entry.temperature();
+                let _ = entry.temperature_celsius();
+                prop_assert_eq!(entry.brightness().is_some(), model.has_brightness());
+                let _ = entry.moisture();
+                let _ = entry.conductivity();
+            }
+            Err(_) => prop_assert!(length < 16),
+        }
+    }
+
+    #[test]
+    fn should_parse_any_historical_payload(
+        data in prop::collection::vec(any::<u8>(), 0..32),
+        model in model(),
+        epoch in any::<u64>(),
+    ) {
+        let length = data.len();
+        match HistoricalEntry::try_new(data, model, EpochTime::exact(epoch)) {
+            Ok(entry) => {
+                let _ = entry.timestamp();
+                let _ = entry.temperature();
+                prop_assert_eq!(entry.brightness().is_some(), model.has_brightness());
+                let _ = entry.moisture();
+                let _ = entry.conductivity();
+            }
+            Err(_) => prop_assert!(length < 16),
+        }
+    }
+
+    #[test]
+    fn should_decode_any_advertisement(data in prop::collection::vec(any::<u8>(), 0..64)) {
+        if let Ok(beacon) = MiBeacon::decode(&data) {
+            let _ = beacon.version();
+            let _ = beacon.model();
+            let _ = beacon.address();
+            let _ = beacon.reading();
+        }
+    }
+
+    #[test]
+    fn should_parse_any_firmware_version(value in ".*") {
+        let version = FirmwareVersion::from(value.as_str());
+        prop_assert_eq!(version.as_str(), value.as_str());
+    }
+}
+
+#[cfg(feature = "encryption")]
+proptest! {
+    #[test]
+    fn should_decrypt_any_advertisement(
+        data in prop::collection::vec(any::<u8>(), 0..64),
+        key in any::<[u8; 16]>(),
+    ) {
+        let key = bluer_miflora::advertisement::BindKey::from(key);
+        let _ = MiBeacon::decode_with_key(&data, &key, None);
+    }
+}
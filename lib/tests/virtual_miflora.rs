@@ -0,0 +1,54 @@
+//! End to end tests against BlueZ, publishing a virtual miflora on one adapter and
+//! reading it from another one.
+//!
+//! Run with `cargo test --features testing -- --ignored`, the adapters being selected with
+//! the `MIFLORA_SERVER_ADAPTER` and `MIFLORA_CLIENT_ADAPTER` variables.
+#![cfg(feature = "testing")]
+
+use std::time::Duration;
+
+use bluer_miflora::testing::{FakeMiflora, VirtualMiflora};
+use bluer_miflora::{GattClient, Miflora};
+
+async fn adapter(session: &bluer::Session, variable: &str) -> bluer::Adapter {
+    let adapter = match std::env::var(variable) {
+        Ok(name) => session.adapter(&name).unwrap(),
+        Err(_) => session.default_adapter().await.unwrap(),
+    };
+    adapter.set_powered(true).await.unwrap();
+    adapter
+}
+
+#[tokio::test]
+#[ignore = "requires BlueZ with two adapters"]
+async fn should_read_virtual_miflora() {
+    let session = bluer::Session::new().await.unwrap();
+    let server = adapter(&session, "MIFLORA_SERVER_ADAPTER").await;
+    let client = adapter(&session, "MIFLORA_CLIENT_ADAPTER").await;
+
+    let fake = FakeMiflora::new(server.address().await.unwrap())
+        .with_battery(76)
+        .with_realtime(201, 1500, 33, 420)
+        .with_uptime(120)
+        .with_history_entry(60, 195, 1400, 32, 410);
+    let virtual_miflora = VirtualMiflora::new(fake.clone());
+    let _handle = virtual_miflora.serve(&server).await.unwrap();
+
+    let _discovery = client.discover_devices().await.unwrap();
+    tokio::time::sleep(Duration::from_secs(5)).await;
+    let miflora = Miflora::try_from_adapter(&client, fake.address())
+        .await
+        .unwrap();
+    let (snapshot, entries) = miflora
+        .with_connection(|miflora| async move {
+            let snapshot = miflora.read_all(false).await?;
+            let entries = miflora.read_historical_values().await?;
+            Ok((snapshot, entries))
+        })
+        .await
+        .unwrap();
+    assert_eq!(snapshot.system().battery(), 76);
+    assert_eq!(snapshot.realtime().conductivity(), 420);
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].moisture(), 32);
+}
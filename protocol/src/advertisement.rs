@@ -0,0 +1,280 @@
+//! Decoding of the MiBeacon frames broadcast in the service data of the `fe95` service.
+//!
+//! The devices broadcast one value at a time, alternating between them. The newer
+//! firmwares encrypt the values with the bind key of the device, the [`Frame`] giving
+//! access to the encrypted object to decrypt it.
+
+use crate::{Error, Model};
+
+const FRAME_HEADER_LENGTH: usize = 5;
+const FRAME_ENCRYPTED: u16 = 0x0008;
+const FRAME_MAC_INCLUDED: u16 = 0x0010;
+const FRAME_CAPABILITY_INCLUDED: u16 = 0x0020;
+const FRAME_OBJECT_INCLUDED: u16 = 0x0040;
+const CAPABILITY_IO: u8 = 0x20;
+
+const OBJECT_TEMPERATURE: u16 = 0x1004;
+const OBJECT_BRIGHTNESS: u16 = 0x1007;
+const OBJECT_MOISTURE: u16 = 0x1008;
+const OBJECT_CONDUCTIVITY: u16 = 0x1009;
+const OBJECT_BATTERY: u16 = 0x100a;
+
+/// Value broadcast by the device in an advertisement.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PassiveReading {
+    /// Temperature in 0.1 °C, negative below freezing
+    Temperature(i16),
+    /// Brightness in lux
+    Brightness(u32),
+    /// Moisture in %
+    Moisture(u8),
+    /// Conductivity in µS/cm
+    Conductivity(u16),
+    /// Battery level in %
+    Battery(u8),
+    /// Object not decoded by this crate, with its type and raw value
+    Unknown(u16, [u8; 4]),
+}
+
+impl PassiveReading {
+    fn decode(object_id: u16, data: &[u8]) -> Option<Self> {
+        let byte = |index: usize| data.get(index).copied();
+        match object_id {
+            OBJECT_TEMPERATURE => Some(Self::Temperature(i16::from_le_bytes([byte(0)?, byte(1)?]))),
+            OBJECT_BRIGHTNESS => Some(Self::Brightness(u32::from_le_bytes([
+                byte(0)?,
+                byte(1)?,
+                byte(2)?,
+                0,
+            ]))),
+            OBJECT_MOISTURE => Some(Self::Moisture(byte(0)?)),
+            OBJECT_CONDUCTIVITY => {
+                Some(Self::Conductivity(u16::from_le_bytes([byte(0)?, byte(1)?])))
+            }
+            OBJECT_BATTERY => Some(Self::Battery(byte(0)?)),
+            _ => {
+                let mut raw = [0; 4];
+                let len = data.len().min(raw.len());
+                raw[..len].copy_from_slice(&data[..len]);
+                Some(Self::Unknown(object_id, raw))
+            }
+        }
+    }
+}
+
+/// Frame broadcast by the device in the service data of the `fe95` service.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Frame<'a> {
+    frame_control: u16,
+    product_id: u16,
+    frame_counter: u8,
+    address: Option<[u8; 6]>,
+    object: Option<&'a [u8]>,
+}
+
+impl<'a> Frame<'a> {
+    /// Decodes the header of the frame, the object being decoded with [`decode_object`].
+    pub fn decode(data: &'a [u8]) -> Result<Self, Error> {
+        if data.len() < FRAME_HEADER_LENGTH {
+            return Err(Error::InvalidAdvertisement {
+                reason: "frame header too short",
+            });
+        }
+        let frame_control = u16::from_le_bytes([data[0], data[1]]);
+        let mut frame = Self {
+            frame_control,
+            product_id: u16::from_le_bytes([data[2], data[3]]),
+            frame_counter: data[4],
+            address: None,
+            object: None,
+        };
+        let mut cursor = FRAME_HEADER_LENGTH;
+        if frame_control & FRAME_MAC_INCLUDED != 0 {
+            let mac = data
+                .get(cursor..cursor + 6)
+                .ok_or(Error::InvalidAdvertisement {
+                    reason: "address too short",
+                })?;
+            // the address is sent in reverse order
+            frame.address = Some([mac[5], mac[4], mac[3], mac[2], mac[1], mac[0]]);
+            cursor += 6;
+        }
+        if frame_control & FRAME_CAPABILITY_INCLUDED != 0 {
+            let capability = *data.get(cursor).ok_or(Error::InvalidAdvertisement {
+                reason: "capability missing",
+            })?;
+            cursor += 1;
+            if capability & CAPABILITY_IO != 0 {
+                cursor += 2;
+            }
+        }
+        if frame_control & FRAME_OBJECT_INCLUDED != 0 {
+            frame.object = Some(data.get(cursor..).unwrap_or_default());
+        }
+        Ok(frame)
+    }
+
+    /// Version of the MiBeacon protocol.
+    pub fn version(&self) -> u8 {
+        (self.frame_control >> 12) as u8
+    }
+
+    /// Whether the object is encrypted with the bind key of the device.
+    pub fn is_encrypted(&self) -> bool {
+        self.frame_control & FRAME_ENCRYPTED != 0
+    }
+
+    pub fn product_id(&self) -> u16 {
+        self.product_id
+    }
+
+    pub fn model(&self) -> Option<Model> {
+        Model::from_product_id(self.product_id)
+    }
+
+    /// Counter incremented by the device for each new frame, to ignore the duplicates.
+    pub fn frame_counter(&self) -> u8 {
+        self.frame_counter
+    }
+
+    /// Address of the device, when included in the frame, most significant byte first.
+    pub fn address(&self) -> Option<[u8; 6]> {
+        self.address
+    }
+
+    /// Object of the frame, encrypted if [`Self::is_encrypted`], the devices sending frames
+    /// without any object too.
+    pub fn object(&self) -> Option<&'a [u8]> {
+        self.object
+    }
+}
+
+/// Decodes the object of the frame, made of its type on 2 bytes, its length and its value.
+pub fn decode_object(data: &[u8]) -> Result<PassiveReading, Error> {
+    if data.len() < 3 {
+        return Err(Error::InvalidAdvertisement {
+            reason: "object header too short",
+        });
+    }
+    let object_id = u16::from_le_bytes([data[0], data[1]]);
+    let length = data[2] as usize;
+    let value = data.get(3..3 + length).ok_or(Error::InvalidAdvertisement {
+        reason: "object value too short",
+    })?;
+    PassiveReading::decode(object_id, value).ok_or(Error::InvalidAdvertisement {
+        reason: "object value too short",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_object, Frame, PassiveReading};
+    use crate::{Error, Model};
+
+    /// Frame of a Flower Care with its address, capability and a temperature of 23 °C.
+    const FRAME_WITH_MAC: [u8; 17] = [
+        0x71, 0x20, 0x98, 0x00, 0x8a, 0x1f, 0x3e, 0x6a, 0x8d, 0x7c, 0xc4, 0x0d, 0x04, 0x10, 0x02,
+        0xe6, 0x00,
+    ];
+
+    fn decode(frame: &[u8]) -> Result<PassiveReading, Error> {
+        decode_object(Frame::decode(frame)?.object().unwrap())
+    }
+
+    #[test]
+    fn should_decode_frame_with_address() {
+        let frame = Frame::decode(&FRAME_WITH_MAC).unwrap();
+        assert_eq!(frame.version(), 2);
+        assert!(!frame.is_encrypted());
+        assert_eq!(frame.model(), Some(Model::FlowerCare));
+        assert_eq!(frame.frame_counter(), 0x8a);
+        assert_eq!(frame.address(), Some([0xc4, 0x7c, 0x8d, 0x6a, 0x3e, 0x1f]));
+        assert_eq!(
+            decode_object(frame.object().unwrap()),
+            Ok(PassiveReading::Temperature(230))
+        );
+    }
+
+    #[test]
+    fn should_skip_the_io_capability() {
+        // capability with the IO bit, followed by its 2 bytes, and a moisture of 43 %
+        let frame = [
+            0x60, 0x20, 0x98, 0x00, 0x05, 0x28, 0xaa, 0xbb, 0x08, 0x10, 0x01, 0x2b,
+        ];
+        assert_eq!(Frame::decode(&frame).unwrap().address(), None);
+        assert_eq!(decode(&frame), Ok(PassiveReading::Moisture(43)));
+        // without the IO bit, the object follows the capability
+        let frame = [0x60, 0x20, 0x98, 0x00, 0x05, 0x08, 0x08, 0x10, 0x01, 0x2b];
+        assert_eq!(decode(&frame), Ok(PassiveReading::Moisture(43)));
+    }
+
+    #[test]
+    fn should_decode_each_object() {
+        let cases: [(&[u8], PassiveReading); 6] = [
+            (
+                &[0x04, 0x10, 0x02, 0xe6, 0x00],
+                PassiveReading::Temperature(230),
+            ),
+            (
+                &[0x04, 0x10, 0x02, 0xf1, 0xff],
+                PassiveReading::Temperature(-15),
+            ),
+            (
+                &[0x07, 0x10, 0x03, 0x10, 0x27, 0x01],
+                PassiveReading::Brightness(75536),
+            ),
+            (&[0x08, 0x10, 0x01, 0x2b], PassiveReading::Moisture(43)),
+            (
+                &[0x09, 0x10, 0x02, 0xf4, 0x01],
+                PassiveReading::Conductivity(500),
+            ),
+            (&[0x0a, 0x10, 0x01, 0x5c], PassiveReading::Battery(92)),
+        ];
+        for (object, expected) in cases {
+            assert_eq!(decode_object(object), Ok(expected), "object {object:02x?}");
+        }
+    }
+
+    #[test]
+    fn should_reject_truncated_frames() {
+        let truncated: [&[u8]; 4] = [
+            // header
+            &FRAME_WITH_MAC[..4],
+            // address
+            &FRAME_WITH_MAC[..8],
+            // capability, announced by the frame control without the address
+            &[0x60, 0x20, 0x98, 0x00, 0x05],
+            // capability missing after the address
+            &FRAME_WITH_MAC[..11],
+        ];
+        for frame in truncated {
+            assert!(
+                matches!(
+                    Frame::decode(frame),
+                    Err(Error::InvalidAdvertisement { .. })
+                ),
+                "frame {frame:02x?}"
+            );
+        }
+    }
+
+    #[test]
+    fn should_reject_truncated_objects() {
+        let truncated: [&[u8]; 4] = [
+            &[0x04, 0x10],
+            &[0x04, 0x10, 0x02, 0xe6],
+            &[0x07, 0x10, 0x02, 0x10, 0x27],
+            &FRAME_WITH_MAC[12..16],
+        ];
+        for object in truncated {
+            assert!(
+                matches!(
+                    decode_object(object),
+                    Err(Error::InvalidAdvertisement { .. })
+                ),
+                "object {object:02x?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,13 @@
+use uuid::Uuid;
+
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("invalid payload length for characteristic {characteristic_id}, expected {expected} bytes but got {actual}")]
+    InvalidPayloadLength {
+        expected: usize,
+        actual: usize,
+        characteristic_id: Uuid,
+    },
+    #[error("invalid advertisement: {reason}")]
+    InvalidAdvertisement { reason: &'static str },
+}
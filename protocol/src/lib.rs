@@ -0,0 +1,100 @@
+//! Constants and parsers of the protocol spoken by the miflora devices, without depending
+//! on a bluetooth stack.
+//!
+//! The devices expose their values through GATT characteristics identified by the UUIDs
+//! below, the payloads being decoded with [`SystemPayload`], [`RealtimePayload`] and
+//! [`HistoryPayload`]. The values broadcast in the advertisements are decoded with the
+//! [`advertisement`] module.
+#![no_std]
+
+use uuid::Uuid;
+
+pub mod advertisement;
+mod error;
+mod model;
+mod payload;
+
+pub use error::Error;
+pub use model::Model;
+pub use payload::{
+    decode_history_length, decode_uptime, HistoryPayload, PnpId, RealtimePayload, SystemPayload,
+};
+
+/// Device UUID prefix of miflora service
+pub const DEVICE_UUID_PREFIX: u32 = 0xfe95;
+/// Prefix of the addresses of the Flower Care devices
+pub const XIAOMI_OUI: [u8; 3] = [0xc4, 0x7c, 0x8d];
+
+// The services and characteristics are resolved by UUID, the numeric handles exposed by
+// BlueZ can change between firmware versions and connections.
+pub const SERVICE_DATA_UUID: Uuid = Uuid::from_u128(0x00001204_0000_1000_8000_00805f9b34fb);
+pub const CHARACTERISTIC_MODE_UUID: Uuid = Uuid::from_u128(0x00001a00_0000_1000_8000_00805f9b34fb);
+pub const CHARACTERISTIC_DATA_UUID: Uuid = Uuid::from_u128(0x00001a01_0000_1000_8000_00805f9b34fb);
+pub const CHARACTERISTIC_FIRMWARE_UUID: Uuid =
+    Uuid::from_u128(0x00001a02_0000_1000_8000_00805f9b34fb);
+
+pub const SERVICE_HISTORY_UUID: Uuid = Uuid::from_u128(0x00001206_0000_1000_8000_00805f9b34fb);
+pub const CHARACTERISTIC_HISTORY_CTRL_UUID: Uuid =
+    Uuid::from_u128(0x00001a10_0000_1000_8000_00805f9b34fb);
+pub const CHARACTERISTIC_HISTORY_READ_UUID: Uuid =
+    Uuid::from_u128(0x00001a11_0000_1000_8000_00805f9b34fb);
+pub const CHARACTERISTIC_HISTORY_TIME_UUID: Uuid =
+    Uuid::from_u128(0x00001a12_0000_1000_8000_00805f9b34fb);
+
+/// Generic access service, exposing the name of the device.
+pub const SERVICE_GENERIC_ACCESS_UUID: Uuid =
+    Uuid::from_u128(0x00001800_0000_1000_8000_00805f9b34fb);
+pub const CHARACTERISTIC_DEVICE_NAME_UUID: Uuid =
+    Uuid::from_u128(0x00002a00_0000_1000_8000_00805f9b34fb);
+/// Longest name fitting in a single write of the device name characteristic, in bytes.
+pub const DEVICE_NAME_MAX_LENGTH: usize = 20;
+
+pub const SERVICE_DEVICE_INFO_UUID: Uuid = Uuid::from_u128(0x0000180a_0000_1000_8000_00805f9b34fb);
+pub const CHARACTERISTIC_MODEL_NUMBER_UUID: Uuid =
+    Uuid::from_u128(0x00002a24_0000_1000_8000_00805f9b34fb);
+pub const CHARACTERISTIC_SERIAL_NUMBER_UUID: Uuid =
+    Uuid::from_u128(0x00002a25_0000_1000_8000_00805f9b34fb);
+pub const CHARACTERISTIC_FIRMWARE_REVISION_UUID: Uuid =
+    Uuid::from_u128(0x00002a26_0000_1000_8000_00805f9b34fb);
+pub const CHARACTERISTIC_HARDWARE_REVISION_UUID: Uuid =
+    Uuid::from_u128(0x00002a27_0000_1000_8000_00805f9b34fb);
+pub const CHARACTERISTIC_SOFTWARE_REVISION_UUID: Uuid =
+    Uuid::from_u128(0x00002a28_0000_1000_8000_00805f9b34fb);
+pub const CHARACTERISTIC_MANUFACTURER_UUID: Uuid =
+    Uuid::from_u128(0x00002a29_0000_1000_8000_00805f9b34fb);
+pub const CHARACTERISTIC_PNP_ID_UUID: Uuid =
+    Uuid::from_u128(0x00002a50_0000_1000_8000_00805f9b34fb);
+
+/// Makes the device blink its LED, written to the mode characteristic.
+pub const CMD_BLINK_LED: [u8; 2] = [0xfd, 0xff];
+/// Makes the history read characteristic return the number of entries.
+pub const CMD_HISTORY_READ_INIT: [u8; 3] = [0xa0, 0x00, 0x00];
+/// Clears the history, once it has been read.
+pub const CMD_HISTORY_READ_SUCCESS: [u8; 3] = [0xa2, 0x00, 0x00];
+/// Ends the history read without clearing it.
+pub const CMD_HISTORY_READ_FAILED: [u8; 3] = [0xa3, 0x00, 0x00];
+pub const CMD_REALTIME_DISABLE: [u8; 2] = [0xc0, 0x1f];
+/// Makes the data characteristic return the realtime values.
+pub const CMD_REALTIME_ENABLE: [u8; 2] = [0xa0, 0x1f];
+
+pub const SYSTEM_PAYLOAD_MIN_LENGTH: usize = 2;
+pub const REALTIME_PAYLOAD_LENGTH: usize = 16;
+pub const HISTORY_HEADER_MIN_LENGTH: usize = 2;
+pub const HISTORY_PAGED_HEADER_MIN_LENGTH: usize = 4;
+/// Number of entries in each page of the paged history.
+pub const HISTORY_PAGE_SIZE: u32 = 4096;
+pub const HISTORY_PAYLOAD_LENGTH: usize = 16;
+pub const EPOCH_TIME_PAYLOAD_MIN_LENGTH: usize = 4;
+
+/// Command making the history read characteristic return the entry at the given offset,
+/// in the current page for the paged history.
+pub fn history_entry_command(offset: u16) -> [u8; 3] {
+    let [low, high] = offset.to_le_bytes();
+    [0xa1, low, high]
+}
+
+/// Command selecting the page of the paged history, see [`Model::has_paged_history`].
+pub fn history_page_command(page: u16) -> [u8; 3] {
+    let [low, high] = page.to_le_bytes();
+    [0xa0, low, high]
+}
@@ -0,0 +1,67 @@
+/// Minimum length of the MiBeacon frame to read the product id.
+const MIBEACON_HEADER_MIN_LENGTH: usize = 4;
+
+/// Devices speaking the miflora protocol.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Model {
+    /// Flower Care, HHCCJCY01
+    #[default]
+    FlowerCare,
+    /// Ropot, HHCCPOT002, which doesn't have a brightness sensor
+    Ropot,
+    /// Grow Care Garden, HHCCJCY10, which stores a larger history split in pages
+    GrowCareGarden,
+}
+
+impl Model {
+    /// Finds the model from the product id of the MiBeacon advertisement.
+    pub fn from_product_id(value: u16) -> Option<Self> {
+        match value {
+            0x0098 => Some(Self::FlowerCare),
+            0x015d => Some(Self::Ropot),
+            0x0bbc => Some(Self::GrowCareGarden),
+            _ => None,
+        }
+    }
+
+    /// Finds the model from the name of the device.
+    pub fn from_name(value: &str) -> Option<Self> {
+        let value = value.trim();
+        [
+            ("flower care", Self::FlowerCare),
+            ("flower mate", Self::FlowerCare),
+            ("ropot", Self::Ropot),
+            ("grow care garden", Self::GrowCareGarden),
+        ]
+        .into_iter()
+        .find(|(name, _)| value.eq_ignore_ascii_case(name))
+        .map(|(_, model)| model)
+    }
+
+    /// Finds the model from the service data advertised by the device, which starts with
+    /// the frame control and the product id.
+    pub fn from_service_data(data: &[u8]) -> Option<Self> {
+        if data.len() < MIBEACON_HEADER_MIN_LENGTH {
+            return None;
+        }
+        Self::from_product_id(u16::from_le_bytes([data[2], data[3]]))
+    }
+
+    pub fn product_id(&self) -> u16 {
+        match self {
+            Self::FlowerCare => 0x0098,
+            Self::Ropot => 0x015d,
+            Self::GrowCareGarden => 0x0bbc,
+        }
+    }
+
+    pub fn has_brightness(&self) -> bool {
+        matches!(self, Self::FlowerCare | Self::GrowCareGarden)
+    }
+
+    /// Whether the history header has a 4 bytes count and the entries are addressed by page.
+    pub fn has_paged_history(&self) -> bool {
+        matches!(self, Self::GrowCareGarden)
+    }
+}
@@ -0,0 +1,369 @@
+use uuid::Uuid;
+
+use crate::{
+    Error, Model, CHARACTERISTIC_DATA_UUID, CHARACTERISTIC_FIRMWARE_UUID,
+    CHARACTERISTIC_HISTORY_READ_UUID, CHARACTERISTIC_HISTORY_TIME_UUID,
+    EPOCH_TIME_PAYLOAD_MIN_LENGTH, HISTORY_HEADER_MIN_LENGTH, HISTORY_PAGED_HEADER_MIN_LENGTH,
+    HISTORY_PAGE_SIZE, HISTORY_PAYLOAD_LENGTH, REALTIME_PAYLOAD_LENGTH, SYSTEM_PAYLOAD_MIN_LENGTH,
+};
+
+/// Length of the payload of the PnP ID characteristic.
+const PNP_ID_PAYLOAD_LENGTH: usize = 7;
+/// First bytes of the frames padding the history on some firmwares.
+const HISTORY_PADDING_MAGIC: [u8; 2] = [0xAA, 0xBB];
+
+/// Ensures the payload returned by the device is long enough to be decoded.
+fn check_payload_length(
+    data: &[u8],
+    expected: usize,
+    characteristic_id: Uuid,
+) -> Result<(), Error> {
+    if data.len() < expected {
+        Err(Error::InvalidPayloadLength {
+            expected,
+            actual: data.len(),
+            characteristic_id,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Takes the fixed size beginning of the payload.
+fn fixed<const N: usize>(data: &[u8], characteristic_id: Uuid) -> Result<&[u8; N], Error> {
+    check_payload_length(data, N, characteristic_id)?;
+    Ok(data[..N].try_into().expect("length checked"))
+}
+
+/// Payload of the firmware characteristic, made of the battery level, an unknown byte and
+/// the firmware version.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SystemPayload<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> SystemPayload<'a> {
+    pub fn decode(data: &'a [u8]) -> Result<Self, Error> {
+        check_payload_length(
+            data,
+            SYSTEM_PAYLOAD_MIN_LENGTH,
+            CHARACTERISTIC_FIRMWARE_UUID,
+        )?;
+        Ok(Self { data })
+    }
+
+    /// Battery level in %.
+    pub fn battery(&self) -> u8 {
+        self.data[0]
+    }
+
+    /// Firmware version, as sent by the device.
+    pub fn firmware(&self) -> &'a [u8] {
+        &self.data[2..]
+    }
+}
+
+/// Payload of the data characteristic once the realtime mode is enabled.
+///
+/// Semantics of the data (in little endian encoding):
+/// bytes   0-1: temperature in 0.1 °C (signed)
+/// byte      2: unknown
+/// bytes   3-6: brightness in lux
+/// byte      7: moisture in %
+/// byted   8-9: conductivity in µS/cm
+/// bytes 10-15: unknown
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RealtimePayload<'a> {
+    model: Model,
+    data: &'a [u8; REALTIME_PAYLOAD_LENGTH],
+}
+
+impl<'a> RealtimePayload<'a> {
+    /// Decodes the payload sent by the given model of device.
+    pub fn decode(data: &'a [u8], model: Model) -> Result<Self, Error> {
+        Ok(Self {
+            model,
+            data: fixed(data, CHARACTERISTIC_DATA_UUID)?,
+        })
+    }
+
+    /// Temperature in 0.1 °C, negative below freezing.
+    pub fn temperature(&self) -> i16 {
+        i16::from_le_bytes([self.data[0], self.data[1]])
+    }
+
+    /// Brightness in lux, if the device has a brightness sensor.
+    pub fn brightness(&self) -> Option<u32> {
+        self.model
+            .has_brightness()
+            .then(|| u32::from_le_bytes([self.data[3], self.data[4], self.data[5], self.data[6]]))
+    }
+
+    /// Moisture in %.
+    pub fn moisture(&self) -> u8 {
+        self.data[7]
+    }
+
+    /// Conductivity in µS/cm.
+    pub fn conductivity(&self) -> u16 {
+        u16::from_le_bytes([self.data[8], self.data[9]])
+    }
+
+    /// Byte 2, of unknown meaning.
+    pub fn unknown_2(&self) -> u8 {
+        self.data[2]
+    }
+
+    /// Bytes 10 to 15, of unknown meaning.
+    pub fn unknown_10_15(&self) -> &'a [u8] {
+        &self.data[10..16]
+    }
+}
+
+/// Payload of an entry of the history.
+///
+/// Semantics of the data (in little endian encoding):
+/// bytes   0-3: timestamp, seconds since boot
+/// bytes   4-5: temperature in 0.1 °C (signed)
+/// byte      6: unknown
+/// bytes   7-9: brightness in lux
+/// byte     10: unknown
+/// byte     11: moisture in %
+/// bytes 12-13: conductivity in µS/cm
+/// bytes 14-15: unknown
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HistoryPayload<'a> {
+    model: Model,
+    data: &'a [u8; HISTORY_PAYLOAD_LENGTH],
+}
+
+impl<'a> HistoryPayload<'a> {
+    /// Decodes the payload sent by the given model of device.
+    pub fn decode(data: &'a [u8], model: Model) -> Result<Self, Error> {
+        Ok(Self {
+            model,
+            data: fixed(data, CHARACTERISTIC_HISTORY_READ_UUID)?,
+        })
+    }
+
+    /// Seconds elapsed since the device booted when the entry was recorded.
+    pub fn uptime(&self) -> u32 {
+        u32::from_le_bytes([self.data[0], self.data[1], self.data[2], self.data[3]])
+    }
+
+    /// Temperature in 0.1 °C, negative below freezing.
+    pub fn temperature(&self) -> i16 {
+        i16::from_le_bytes([self.data[4], self.data[5]])
+    }
+
+    /// Brightness in lux, if the device has a brightness sensor.
+    pub fn brightness(&self) -> Option<u32> {
+        self.model
+            .has_brightness()
+            .then(|| u32::from_le_bytes([self.data[7], self.data[8], self.data[9], 0]))
+    }
+
+    /// Moisture in %.
+    pub fn moisture(&self) -> u8 {
+        self.data[11]
+    }
+
+    /// Conductivity in µS/cm.
+    pub fn conductivity(&self) -> u16 {
+        u16::from_le_bytes([self.data[12], self.data[13]])
+    }
+
+    /// Byte 6, of unknown meaning.
+    pub fn unknown_6(&self) -> u8 {
+        self.data[6]
+    }
+
+    /// Byte 10, of unknown meaning.
+    pub fn unknown_10(&self) -> u8 {
+        self.data[10]
+    }
+
+    /// Bytes 14 and 15, of unknown meaning.
+    pub fn unknown_14_15(&self) -> &'a [u8] {
+        &self.data[14..16]
+    }
+
+    /// Whether the entry is a frame some firmwares pad the history with, filled with
+    /// `0xFF` or zeros or starting with the `0xAA 0xBB` magic, instead of a measure.
+    pub fn is_padding(&self) -> bool {
+        self.data.starts_with(&HISTORY_PADDING_MAGIC)
+            || self.data.iter().all(|byte| *byte == 0xFF)
+            || self.data.iter().all(|byte| *byte == 0)
+    }
+}
+
+/// Decodes the number of entries in the history, returned after the
+/// [`CMD_HISTORY_READ_INIT`](crate::CMD_HISTORY_READ_INIT) command.
+///
+/// The paged history has a 4 bytes count, clamped to the entries that can be addressed.
+pub fn decode_history_length(data: &[u8], model: Model) -> Result<u32, Error> {
+    if model.has_paged_history() {
+        let data: &[u8; HISTORY_PAGED_HEADER_MIN_LENGTH] =
+            fixed(data, CHARACTERISTIC_HISTORY_READ_UUID)?;
+        let length = u32::from_le_bytes(*data);
+        Ok(length.min(HISTORY_PAGE_SIZE * (u16::MAX as u32 + 1) - 1))
+    } else {
+        let data: &[u8; HISTORY_HEADER_MIN_LENGTH] = fixed(data, CHARACTERISTIC_HISTORY_READ_UUID)?;
+        Ok(u16::from_le_bytes(*data) as u32)
+    }
+}
+
+/// Decodes the seconds elapsed since the device booted, from the history time
+/// characteristic.
+pub fn decode_uptime(data: &[u8]) -> Result<u32, Error> {
+    let data: &[u8; EPOCH_TIME_PAYLOAD_MIN_LENGTH] = fixed(data, CHARACTERISTIC_HISTORY_TIME_UUID)?;
+    Ok(u32::from_le_bytes(*data))
+}
+
+/// Identifies the vendor and the product of a device.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PnpId {
+    vendor_id_source: u8,
+    vendor_id: u16,
+    product_id: u16,
+    product_version: u16,
+}
+
+impl PnpId {
+    /// Decodes the payload, ignoring it when too short.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() < PNP_ID_PAYLOAD_LENGTH {
+            return None;
+        }
+        Some(Self {
+            vendor_id_source: data[0],
+            vendor_id: u16::from_le_bytes([data[1], data[2]]),
+            product_id: u16::from_le_bytes([data[3], data[4]]),
+            product_version: u16::from_le_bytes([data[5], data[6]]),
+        })
+    }
+
+    /// Registry of the vendor id, 1 for Bluetooth SIG and 2 for USB Implementer's Forum.
+    pub fn vendor_id_source(&self) -> u8 {
+        self.vendor_id_source
+    }
+
+    pub fn vendor_id(&self) -> u16 {
+        self.vendor_id
+    }
+
+    pub fn product_id(&self) -> u16 {
+        self.product_id
+    }
+
+    pub fn product_version(&self) -> u16 {
+        self.product_version
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        decode_history_length, decode_uptime, HistoryPayload, RealtimePayload, SystemPayload,
+    };
+    use crate::{
+        Error, Model, CHARACTERISTIC_DATA_UUID, CHARACTERISTIC_FIRMWARE_UUID,
+        CHARACTERISTIC_HISTORY_READ_UUID, CHARACTERISTIC_HISTORY_TIME_UUID,
+    };
+
+    /// Entry recorded one hour after boot, at 23 °C, 1234 lux, 30 % and 500 µS/cm.
+    const HISTORY_ENTRY: [u8; 16] = [
+        0x10, 0x0e, 0x00, 0x00, 0xe6, 0x00, 0x00, 0xd2, 0x04, 0x00, 0x00, 0x1e, 0xf4, 0x01, 0x00,
+        0x00,
+    ];
+
+    #[test]
+    fn should_reject_truncated_payloads() {
+        assert_eq!(
+            RealtimePayload::decode(&[0; 15], Model::FlowerCare),
+            Err(Error::InvalidPayloadLength {
+                expected: 16,
+                actual: 15,
+                characteristic_id: CHARACTERISTIC_DATA_UUID,
+            })
+        );
+        assert_eq!(
+            HistoryPayload::decode(&HISTORY_ENTRY[..10], Model::FlowerCare),
+            Err(Error::InvalidPayloadLength {
+                expected: 16,
+                actual: 10,
+                characteristic_id: CHARACTERISTIC_HISTORY_READ_UUID,
+            })
+        );
+        assert_eq!(
+            SystemPayload::decode(&[100]),
+            Err(Error::InvalidPayloadLength {
+                expected: 2,
+                actual: 1,
+                characteristic_id: CHARACTERISTIC_FIRMWARE_UUID,
+            })
+        );
+        assert_eq!(
+            decode_uptime(&[]),
+            Err(Error::InvalidPayloadLength {
+                expected: 4,
+                actual: 0,
+                characteristic_id: CHARACTERISTIC_HISTORY_TIME_UUID,
+            })
+        );
+        // the paged history has a longer header
+        assert_eq!(
+            decode_history_length(&[0x2a, 0x00], Model::FlowerCare),
+            Ok(42)
+        );
+        assert_eq!(
+            decode_history_length(&[0x2a, 0x00], Model::GrowCareGarden),
+            Err(Error::InvalidPayloadLength {
+                expected: 4,
+                actual: 2,
+                characteristic_id: CHARACTERISTIC_HISTORY_READ_UUID,
+            })
+        );
+    }
+
+    #[test]
+    fn should_decode_history_entry() {
+        let entry = HistoryPayload::decode(&HISTORY_ENTRY, Model::FlowerCare).unwrap();
+        assert_eq!(entry.uptime(), 3600);
+        assert_eq!(entry.temperature(), 230);
+        assert_eq!(entry.brightness(), Some(1234));
+        assert_eq!(entry.moisture(), 30);
+        assert_eq!(entry.conductivity(), 500);
+        assert!(!entry.is_padding());
+        let entry = HistoryPayload::decode(&HISTORY_ENTRY, Model::Ropot).unwrap();
+        assert_eq!(entry.brightness(), None);
+    }
+
+    #[test]
+    fn should_decode_negative_temperatures() {
+        let mut data = [0; 16];
+        data[..2].copy_from_slice(&[0xf1, 0xff]);
+        let realtime = RealtimePayload::decode(&data, Model::FlowerCare).unwrap();
+        assert_eq!(realtime.temperature(), -15);
+        let mut entry = HISTORY_ENTRY;
+        entry[4..6].copy_from_slice(&[0xf1, 0xff]);
+        let entry = HistoryPayload::decode(&entry, Model::FlowerCare).unwrap();
+        assert_eq!(entry.temperature(), -15);
+    }
+
+    #[test]
+    fn should_detect_padding() {
+        let mut magic = HISTORY_ENTRY;
+        magic[..2].copy_from_slice(&[0xAA, 0xBB]);
+        for data in [magic, [0xFF; 16], [0; 16]] {
+            let entry = HistoryPayload::decode(&data, Model::FlowerCare).unwrap();
+            assert!(entry.is_padding(), "{data:02x?}");
+        }
+        // the magic is only looked for at the beginning
+        let mut shifted = HISTORY_ENTRY;
+        shifted[14..].copy_from_slice(&[0xAA, 0xBB]);
+        let entry = HistoryPayload::decode(&shifted, Model::FlowerCare).unwrap();
+        assert!(!entry.is_padding());
+    }
+}